@@ -0,0 +1,65 @@
+//! Account-level configuration: parameters and resource monitors, snapshotted so platform
+//! changes can be tracked alongside schema changes.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::provenance::Provenance;
+
+/// Written next to the catalog files; `catalog::load_snapshot` skips it
+pub const ACCOUNT_FILE: &str = "account.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct AccountSnapshot {
+    /// Snapshot structure version, see [`crate::upgrade`]
+    #[serde(default = "crate::upgrade::unversioned")]
+    pub format_version: u32,
+    /// RFC 3339 time the snapshot was taken
+    pub captured_at: String,
+    /// The run that took it; `None` in documents from before it was recorded
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    pub parameters: Vec<AccountParameter>,
+    pub resource_monitors: Vec<ResourceMonitor>,
+}
+
+/// One row of `SHOW PARAMETERS IN ACCOUNT`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct AccountParameter {
+    pub key: String,
+    pub value: String,
+    pub default: String,
+    /// Where the value is set, e.g. `ACCOUNT`; empty when it is the default
+    pub level: String,
+    pub description: Option<String>,
+}
+
+/// One row of `SHOW RESOURCE MONITORS`; quotas and credits as reported, e.g. `100.00`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ResourceMonitor {
+    pub name: String,
+    pub credit_quota: Option<String>,
+    pub used_credits: Option<String>,
+    pub remaining_credits: Option<String>,
+    /// `ACCOUNT` for the account monitor, `WAREHOUSE` otherwise
+    pub level: Option<String>,
+    pub frequency: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// Percentages of the quota, e.g. `75%,90%`
+    pub notify_at: Option<String>,
+    pub suspend_at: Option<String>,
+    pub suspend_immediately_at: Option<String>,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl AccountSnapshot {
+    /// Parameters changed from their default
+    pub fn overridden_parameters(&self) -> impl Iterator<Item = &AccountParameter> {
+        self.parameters.iter().filter(|p| p.value != p.default)
+    }
+}
@@ -0,0 +1,100 @@
+//! Publishing catalog entities to Apache Atlas.
+
+use anyhow::Result;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::formats::atlas::{self, AtlasModel};
+use crate::{SnowflakeMapperError, TableInfo};
+
+#[derive(Deserialize)]
+struct MutationResponse {
+    #[serde(default, rename = "mutatedEntities")]
+    mutated_entities: HashMap<String, Vec<Value>>,
+}
+
+pub struct AtlasClient {
+    http: reqwest::Client,
+    base_url: String,
+    credentials: Option<(String, String)>,
+    model: AtlasModel,
+    cluster: String,
+}
+
+impl AtlasClient {
+    pub fn new(base_url: &str, credentials: Option<(String, String)>, model: AtlasModel, cluster: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            credentials,
+            model,
+            cluster: cluster.to_string(),
+        }
+    }
+
+    /// Register the Snowflake model's types, unless Atlas already has them. Nothing to do for
+    /// the built-in Hive types.
+    pub async fn register_types(&self) -> Result<()> {
+        if self.model == AtlasModel::Hive {
+            return Ok(());
+        }
+        let existing = self.request(reqwest::Method::GET, "/api/atlas/v2/types/typedef/name/snowflake_column", None).await?;
+        match existing.status() {
+            status if status.is_success() => return Ok(()),
+            StatusCode::NOT_FOUND => {}
+            _ => return Err(Self::failure("type definitions", existing).await),
+        }
+        let response = self.request(reqwest::Method::POST, "/api/atlas/v2/types/typedefs", Some(&atlas::typedefs())).await?;
+        if !response.status().is_success() {
+            return Err(Self::failure("type definitions", response).await);
+        }
+        info!("Registered the snowflake_* types with Atlas");
+        Ok(())
+    }
+
+    /// Create or update the entities of a database's tables, one request per schema
+    pub async fn publish_tables(&self, database: &str, tables: &[TableInfo]) -> Result<()> {
+        let mut schemas: Vec<(&str, Vec<TableInfo>)> = Vec::new();
+        for table in tables {
+            match schemas.iter_mut().find(|(name, _)| *name == table.schema_name) {
+                Some((_, members)) => members.push(table.clone()),
+                None => schemas.push((&table.schema_name, vec![table.clone()])),
+            }
+        }
+        for (schema, tables) in schemas {
+            let entities = atlas::render_entities(database, &tables, self.model, &self.cluster);
+            let response = self.request(reqwest::Method::POST, "/api/atlas/v2/entity/bulk", Some(&entities)).await?;
+            let subject = format!("{}.{}", database, schema);
+            if !response.status().is_success() {
+                return Err(Self::failure(&subject, response).await);
+            }
+            let MutationResponse { mutated_entities } = response.json().await?;
+            let count = |operation: &str| mutated_entities.get(operation).map_or(0, Vec::len);
+            info!("Published {} to Atlas ({} entities created, {} updated)", subject, count("CREATE"), count("UPDATE"));
+        }
+        Ok(())
+    }
+
+    async fn request(&self, method: reqwest::Method, path: &str, body: Option<&Value>) -> Result<reqwest::Response> {
+        let mut request = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        if let Some((username, password)) = &self.credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| SnowflakeMapperError::AtlasError(format!("Request to {} failed: {}", path, e)).into())
+    }
+
+    async fn failure(subject: &str, response: reqwest::Response) -> anyhow::Error {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        SnowflakeMapperError::AtlasError(format!("Publishing {} failed with {}: {}", subject, status, text)).into()
+    }
+}
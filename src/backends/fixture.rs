@@ -0,0 +1,227 @@
+//! Recording and replaying Snowflake query results as JSON fixtures, so pipelines and
+//! integration tests can run without a live account.
+//!
+//! Each query is stored as `<hash>.json` holding the query text and its rows, keyed by the
+//! query with whitespace normalized.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::snowflake::{FixtureMode, SnowflakeConfig, SnowflakeMapper, SnowflakeOperations};
+use super::WarehouseMapper;
+use crate::account::{AccountParameter, ResourceMonitor};
+use crate::guardrails::QueryBudget;
+use crate::hash::fnv1a;
+use crate::hooks::SqlHooks;
+use crate::output;
+use crate::replication::{DatabaseReplication, ReplicationGroup};
+use crate::shares::{ShareInfo, SharedObject};
+use crate::users::{UserInfo, UserSource};
+use crate::progress::ProgressListener;
+use crate::querylog::QueryLog;
+use crate::throttle::RateLimiter;
+use crate::visibility::AccountObject;
+use crate::{DatabaseInfo, RoleInfo, SchemaInfo, SnowflakeMapperError, TableInfo, WarehouseInfo};
+
+/// One result row, keyed by lower-cased column name
+pub type QueryRow = BTreeMap<String, Option<String>>;
+
+#[derive(Serialize, Deserialize)]
+struct Fixture {
+    query: String,
+    rows: Vec<QueryRow>,
+}
+
+pub struct FixtureStore {
+    dir: PathBuf,
+}
+
+impl FixtureStore {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    pub fn load(&self, query: &str) -> Result<Vec<QueryRow>> {
+        let path = self.path(query);
+        let contents = std::fs::read_to_string(&path).map_err(|_| {
+            SnowflakeMapperError::QueryError(format!(
+                "No fixture recorded for query `{}` (expected {})",
+                normalize(query),
+                path.display()
+            ))
+        })?;
+        let fixture: Fixture = serde_json::from_str(&contents)
+            .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Invalid fixture {}: {}", path.display(), e)))?;
+        Ok(fixture.rows)
+    }
+
+    /// Save the rows of `query`, replacing any earlier recording of it. The file is written
+    /// atomically, so an interrupted recording never leaves a truncated fixture behind.
+    pub fn save(&self, query: &str, rows: &[QueryRow]) -> Result<()> {
+        let fixture = Fixture {
+            query: normalize(query),
+            rows: rows.to_vec(),
+        };
+        let path = self.path(query);
+        let contents = serde_json::to_string_pretty(&fixture)?;
+        output::write_atomically(&path, |file| file.write_all(contents.as_bytes()))
+    }
+
+    fn path(&self, query: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(normalize(query).as_bytes())))
+    }
+}
+
+fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Snowflake backend that answers every query from recorded fixtures instead of a session
+pub struct MockSnowflakeOperations {
+    inner: SnowflakeMapper,
+}
+
+impl MockSnowflakeOperations {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            inner: SnowflakeMapper::new(SnowflakeConfig::default())
+                .with_fixtures(FixtureMode::Replay(FixtureStore::new(dir))),
+        }
+    }
+
+    pub fn with_access_history(mut self, access_history: bool) -> Self {
+        self.inner = self.inner.with_access_history(access_history);
+        self
+    }
+
+    pub fn with_variant_sample(mut self, rows: Option<u32>) -> Self {
+        self.inner = self.inner.with_variant_sample(rows);
+        self
+    }
+
+    pub fn with_usage_window(mut self, days: Option<u32>) -> Self {
+        self.inner = self.inner.with_usage_window(days);
+        self
+    }
+
+    pub fn with_shard_columns(mut self, columns: Option<usize>) -> Self {
+        self.inner = self.inner.with_shard_columns(columns);
+        self
+    }
+
+    pub fn with_sql_hooks(mut self, hooks: Option<Arc<SqlHooks>>) -> Self {
+        self.inner = self.inner.with_sql_hooks(hooks);
+        self
+    }
+
+    pub fn with_enforce_readonly(mut self, enforce_readonly: bool) -> Self {
+        self.inner = self.inner.with_enforce_readonly(enforce_readonly);
+        self
+    }
+
+    pub fn with_query_log(mut self, log: Arc<QueryLog>) -> Self {
+        self.inner = self.inner.with_query_log(log);
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.inner = self.inner.with_rate_limiter(limiter);
+        self
+    }
+
+    pub fn with_query_budget(mut self, budget: Arc<QueryBudget>) -> Self {
+        self.inner = self.inner.with_query_budget(budget);
+        self
+    }
+}
+
+#[async_trait]
+impl WarehouseMapper for MockSnowflakeOperations {
+    async fn connect(&mut self) -> Result<()> {
+        self.inner.connect().await
+    }
+
+    async fn get_all_databases(&mut self) -> Result<Vec<DatabaseInfo>> {
+        self.inner.get_all_databases().await
+    }
+
+    fn set_progress_listener(&mut self, listener: Arc<dyn ProgressListener>) {
+        self.inner.set_progress_listener(listener);
+    }
+
+    async fn get_warehouses(&mut self) -> Result<Vec<WarehouseInfo>> {
+        WarehouseMapper::get_warehouses(&mut self.inner).await
+    }
+
+    async fn get_tables_for_database(&mut self, database: &str) -> Result<Vec<TableInfo>> {
+        self.inner.get_tables_for_database(database).await
+    }
+}
+
+#[async_trait]
+impl SnowflakeOperations for MockSnowflakeOperations {
+    async fn set_warehouse(&mut self, warehouse: &str) -> Result<()> {
+        self.inner.set_warehouse(warehouse).await
+    }
+
+    async fn set_role(&mut self, role: &str) -> Result<()> {
+        self.inner.set_role(role).await
+    }
+
+    async fn list_warehouses(&mut self) -> Result<Vec<WarehouseInfo>> {
+        self.inner.list_warehouses().await
+    }
+
+    async fn list_schemas(&mut self, database: &str) -> Result<Vec<SchemaInfo>> {
+        self.inner.list_schemas(database).await
+    }
+
+    async fn list_roles(&mut self) -> Result<Vec<RoleInfo>> {
+        self.inner.list_roles().await
+    }
+
+    async fn get_account_parameters(&mut self) -> Result<Vec<AccountParameter>> {
+        self.inner.get_account_parameters().await
+    }
+
+    async fn list_resource_monitors(&mut self) -> Result<Vec<ResourceMonitor>> {
+        self.inner.list_resource_monitors().await
+    }
+
+    async fn list_users(&mut self, source: UserSource) -> Result<Vec<UserInfo>> {
+        self.inner.list_users(source).await
+    }
+
+    async fn list_shares(&mut self) -> Result<Vec<ShareInfo>> {
+        self.inner.list_shares().await
+    }
+
+    async fn describe_share(&mut self, share: &str) -> Result<Vec<SharedObject>> {
+        self.inner.describe_share(share).await
+    }
+
+    async fn list_replication_groups(&mut self) -> Result<Vec<ReplicationGroup>> {
+        self.inner.list_replication_groups().await
+    }
+
+    async fn list_replication_group_databases(&mut self, group: &str) -> Result<Vec<String>> {
+        self.inner.list_replication_group_databases(group).await
+    }
+
+    async fn list_replication_databases(&mut self) -> Result<Vec<DatabaseReplication>> {
+        self.inner.list_replication_databases().await
+    }
+
+    async fn list_account_usage_objects(&mut self) -> Result<Vec<AccountObject>> {
+        self.inner.list_account_usage_objects().await
+    }
+
+    async fn list_visible_objects(&mut self, database: &str) -> Result<Vec<AccountObject>> {
+        self.inner.list_visible_objects(database).await
+    }
+}
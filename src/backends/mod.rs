@@ -0,0 +1,85 @@
+//! Warehouse backends that produce the common catalog format.
+
+pub mod fixture;
+pub mod pool;
+pub mod postgres;
+pub mod rest;
+pub mod snowflake;
+pub mod sweep;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use std::sync::Arc;
+
+use crate::progress::{NoProgress, ProgressEvent, ProgressListener};
+use crate::{DatabaseInfo, TableInfo, WarehouseInfo};
+
+/// Operations every backend provides to map its databases into `TableInfo`s
+#[async_trait]
+pub trait WarehouseMapper: Send {
+    async fn connect(&mut self) -> Result<()>;
+    async fn get_all_databases(&mut self) -> Result<Vec<DatabaseInfo>>;
+    async fn get_tables_for_database(&mut self, database: &str) -> Result<Vec<TableInfo>>;
+
+    /// Receive `DatabaseStep` events while a database is mapped; backends that fetch a
+    /// database in one go can ignore it
+    fn set_progress_listener(&mut self, _listener: Arc<dyn ProgressListener>) {}
+
+    /// Retries spent on the database being mapped. The caller sets it before each attempt
+    /// and reads it back, so retries a backend makes itself, such as of chunked queries,
+    /// count against the same retry limit; backends that don't retry can ignore it
+    fn set_retries_spent(&mut self, _retries: u32) {}
+
+    fn retries_spent(&self) -> u32 {
+        0
+    }
+
+    /// Compute warehouses and their sizing, written once per run; backends without
+    /// separately configured compute have none
+    async fn get_warehouses(&mut self) -> Result<Vec<WarehouseInfo>> {
+        Ok(Vec::new())
+    }
+
+    /// Map a single database in memory
+    async fn map_database(&mut self, database: &str) -> Result<Vec<TableInfo>> {
+        self.get_tables_for_database(database).await
+    }
+
+    /// Map every accessible database in memory, in the order they are listed
+    async fn map_account(&mut self) -> Result<Vec<TableInfo>> {
+        self.map_account_with_progress(&NoProgress).await
+    }
+
+    /// Like [`map_account`](Self::map_account), reporting each step to `listener`
+    async fn map_account_with_progress(&mut self, listener: &dyn ProgressListener) -> Result<Vec<TableInfo>> {
+        let databases = self.get_all_databases().await?;
+        let total = databases.len();
+        listener.on_event(&ProgressEvent::Started { total });
+
+        let mut tables = Vec::new();
+        for (index, database) in databases.into_iter().enumerate() {
+            listener.on_event(&ProgressEvent::DatabaseStarted { database: database.name.clone(), index, total });
+            match self.get_tables_for_database(&database.name).await {
+                Ok(found) => {
+                    listener.on_event(&ProgressEvent::DatabaseFinished { database: database.name, tables: found.len() });
+                    tables.extend(found);
+                }
+                Err(e) => {
+                    listener.on_event(&ProgressEvent::DatabaseFailed { database: database.name, error: e.to_string() });
+                    return Err(e);
+                }
+            }
+        }
+        listener.on_event(&ProgressEvent::Finished { databases: total, tables: tables.len(), failed: 0, retries: 0 });
+        Ok(tables)
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Snowflake, configured through the SNOWFLAKE_* environment variables
+    Snowflake,
+    /// PostgreSQL via information_schema, configured through POSTGRES_URL
+    Postgres,
+}
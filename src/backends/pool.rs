@@ -0,0 +1,294 @@
+//! A small pool of Snowflake sessions shared by everything that queries one account.
+//!
+//! Sessions are opened on demand, up to `max_sessions`, and each is lent to one caller at a
+//! time, so parallel workers, keep-alive pings and long-running callers can query through a
+//! shared `Arc<SessionPool>` instead of contending for a `&mut` mapper. Session-level
+//! statements (`USE WAREHOUSE`, `USE ROLE`) are remembered and brought up to date on every
+//! session before it is lent out, so all sessions query in the same context.
+//!
+//! Sessions are opened by the connector, or over the REST API for logins the connector can't
+//! make, see [`rest`](super::rest). A REST login is made once and shared by every session.
+
+use anyhow::{Context, Result};
+use snowflake_connector_rs::{SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig, SnowflakeSession};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use super::fixture::QueryRow;
+use super::rest::{RestLogin, RestSession};
+use super::snowflake::SnowflakeConfig;
+use crate::guardrails::QueryBudget;
+use crate::querylog::QueryLog;
+use crate::throttle::RateLimiter;
+use crate::SnowflakeMapperError;
+
+/// Client timeout for queries. Logins waiting on a Duo push are bounded by `--mfa-timeout`
+/// instead, around `create_session`, so a long approval window doesn't let hung queries
+/// wait as long.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Query sent to keep an idle session alive
+const PING: &str = "SELECT 1";
+
+/// The query budget, `--max-qps` limiter and query log that keep-alive pings go through, as
+/// the mapper's own queries do
+#[derive(Debug, Clone, Default)]
+pub struct QueryAccounting {
+    pub budget: Option<Arc<QueryBudget>>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub query_log: Option<Arc<QueryLog>>,
+}
+
+pub struct SessionPool {
+    login: Login,
+    username: String,
+    mfa_timeout: Option<Duration>,
+    max_sessions: usize,
+    permits: Semaphore,
+    /// Idle sessions, with how many `setup` statements each has run and when it was last used
+    idle: Mutex<Vec<(Session, usize, Instant)>>,
+    setup: Mutex<Vec<String>>,
+}
+
+/// How new sessions log in
+enum Login {
+    /// Through the connector, once per session
+    Connector(SnowflakeClient),
+    /// Over the REST API, once for the whole pool
+    Rest(Box<RestLogin>, OnceCell<RestSession>),
+}
+
+/// A Snowflake session, opened by the connector or over the REST API
+pub enum Session {
+    Connector(SnowflakeSession),
+    Rest(RestSession),
+}
+
+/// A session lent from a [`SessionPool`]; it goes back to the pool when dropped
+pub struct PooledSession<'a> {
+    session: Option<(Session, usize)>,
+    pool: &'a SessionPool,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl SessionPool {
+    /// A pool for `config`, querying on `warehouse`. No session is opened until one is acquired.
+    pub fn new(config: &SnowflakeConfig, warehouse: &str, max_sessions: usize) -> Result<Self> {
+        if config.okta_url.is_some() && config.mfa_passcode.is_some() {
+            return Err(SnowflakeMapperError::ConnectionError(
+                "An MFA passcode is for Snowflake's own password check; with Okta, MFA is up to Okta".to_string(),
+            )
+            .into());
+        }
+        let login = if config.mfa_passcode.is_some() || config.okta_url.is_some() {
+            // The connector has no field for a passcode or a SAML response
+            Login::Rest(Box::new(RestLogin::new(config, warehouse)), OnceCell::new())
+        } else {
+            let client = SnowflakeClient::new(
+                &config.username,
+                SnowflakeAuthMethod::Password(config.password.clone()),
+                SnowflakeClientConfig {
+                    account: config.connector_account()?,
+                    role: config.role.clone(),
+                    warehouse: Some(warehouse.to_string()),
+                    database: config.database.clone(),
+                    schema: None,
+                    timeout: Some(QUERY_TIMEOUT),
+                },
+            )
+            .context("Failed to create Snowflake client")?;
+            Login::Connector(client)
+        };
+        let max_sessions = max_sessions.max(1);
+        Ok(Self {
+            login,
+            username: config.username.clone(),
+            mfa_timeout: config.mfa_timeout,
+            max_sessions,
+            permits: Semaphore::new(max_sessions),
+            idle: Mutex::new(Vec::new()),
+            setup: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn max_sessions(&self) -> usize {
+        self.max_sessions
+    }
+
+    /// Wait for a free session, opening one if none is idle
+    pub async fn acquire(&self) -> Result<PooledSession<'_>> {
+        let permit = self.permits.acquire().await.context("Session pool closed")?;
+        let idle = self.idle.lock().unwrap().pop();
+        let (session, applied) = match idle {
+            Some((session, applied, _)) => (session, applied),
+            None => (self.open_session().await?, 0),
+        };
+        let applied = self.apply_setup(&session, applied).await?;
+        Ok(PooledSession { session: Some((session, applied)), pool: self, _permit: permit })
+    }
+
+    /// Log a new session in. With MFA the login blocks until the user approves the Duo push
+    /// it sends, so every new session asks for one approval. A REST login, which sends a
+    /// passcode or goes through Okta instead, is made by the first session and shared by the
+    /// rest.
+    async fn open_session(&self) -> Result<Session> {
+        let client = match &self.login {
+            Login::Connector(client) => client,
+            Login::Rest(login, session) => {
+                let session = session.get_or_try_init(|| login.login(QUERY_TIMEOUT)).await?;
+                return Ok(Session::Rest(session.clone()));
+            }
+        };
+        let Some(mfa_timeout) = self.mfa_timeout else {
+            let session = client.create_session().await.context("Failed to create Snowflake session")?;
+            return Ok(Session::Connector(session));
+        };
+        let wait = humantime::format_duration(mfa_timeout);
+        info!("Approve the Duo push sent to {} to log in to Snowflake (waiting up to {})", self.username, wait);
+        match tokio::time::timeout(mfa_timeout, client.create_session()).await {
+            Ok(session) => Ok(Session::Connector(session.context("Failed to create Snowflake session")?)),
+            Err(_) => Err(SnowflakeMapperError::ConnectionError(format!(
+                "The Duo push for {} was not approved within {}; approve it sooner or raise --mfa-timeout",
+                self.username, wait
+            ))
+            .into()),
+        }
+    }
+
+    /// Run `SELECT 1` every `interval` on each session that has sat idle for that long, so
+    /// Snowflake doesn't expire sessions left waiting while another worker spends half an
+    /// hour on one database. Pings count against `accounting` like any other query. The task
+    /// stops once the pool is dropped.
+    pub fn keep_alive(self: &Arc<Self>, interval: Duration, accounting: QueryAccounting) -> JoinHandle<()> {
+        let pool = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            // The first tick is immediate, when nothing can be idle yet
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let Some(pool) = pool.upgrade() else { break };
+                pool.ping_idle(interval, &accounting).await;
+            }
+        })
+    }
+
+    /// Ping the sessions idle for at least `idle_for`; a session whose ping fails is dropped
+    /// so the next caller opens a fresh one. Sessions are taken out one at a time, so the
+    /// others stay available to [`acquire`](Self::acquire) rather than making it log in again.
+    async fn ping_idle(&self, idle_for: Duration, accounting: &QueryAccounting) {
+        let is_stale = |since: &Instant| since.elapsed() >= idle_for;
+        loop {
+            // Hold a permit like any other borrower, so pings never exceed `max_sessions`
+            let Ok(_permit) = self.permits.try_acquire() else { return };
+            if !self.idle.lock().unwrap().iter().any(|(_, _, since)| is_stale(since)) {
+                return;
+            }
+            if let Some(budget) = &accounting.budget {
+                if let Err(e) = budget.acquire() {
+                    debug!("Skipping keep-alive pings: {}", e);
+                    return;
+                }
+            }
+            if let Some(limiter) = &accounting.rate_limiter {
+                limiter.acquire().await;
+            }
+            let (session, applied, since) = {
+                let mut idle = self.idle.lock().unwrap();
+                let Some(index) = idle.iter().position(|(_, _, since)| is_stale(since)) else { return };
+                idle.remove(index)
+            };
+
+            let started = Instant::now();
+            let result = session.query(PING).await;
+            if let Some(log) = &accounting.query_log {
+                let outcome = result.as_ref().map(Vec::len).map_err(|e| e.to_string());
+                log.record("snowflake", PING, started.elapsed(), outcome, false);
+            }
+            match result {
+                Ok(_) => {
+                    debug!("Pinged a session idle for {}s", since.elapsed().as_secs());
+                    self.idle.lock().unwrap().push((session, applied, Instant::now()));
+                }
+                Err(e) => warn!("Keep-alive ping failed; dropping the session: {}", e),
+            }
+        }
+    }
+
+    /// Run `statement` on every session before it is next lent out, in the order statements
+    /// were remembered
+    pub fn remember_setup(&self, statement: &str) {
+        let mut setup = self.setup.lock().unwrap();
+        if setup.last().is_none_or(|last| last != statement) {
+            setup.push(statement.to_string());
+        }
+    }
+
+    /// Run the setup statements `session` hasn't seen yet; returns how many it has now run
+    async fn apply_setup(&self, session: &Session, applied: usize) -> Result<usize> {
+        let pending: Vec<String> = self.setup.lock().unwrap()[applied..].to_vec();
+        for statement in &pending {
+            session
+                .query(statement)
+                .await
+                .with_context(|| format!("Failed to prepare pooled session with `{}`", statement))?;
+        }
+        Ok(applied + pending.len())
+    }
+}
+
+impl fmt::Debug for SessionPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionPool")
+            .field("max_sessions", &self.max_sessions)
+            .field("idle", &self.idle.lock().unwrap().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Session {
+    /// Run `sql` and return its rows keyed by lower-cased column name. Queries over the REST
+    /// API are bounded by the same timeout the connector's are.
+    pub async fn query(&self, sql: &str) -> Result<Vec<QueryRow>> {
+        let session = match self {
+            Session::Connector(session) => session,
+            Session::Rest(session) => {
+                return tokio::time::timeout(QUERY_TIMEOUT, session.query(sql)).await.unwrap_or_else(|_| {
+                    let timeout = humantime::format_duration(QUERY_TIMEOUT);
+                    Err(SnowflakeMapperError::QueryError(format!("timed out after {}", timeout)).into())
+                });
+            }
+        };
+        let rows = session.query(sql).await?;
+        let mut converted = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut values = QueryRow::new();
+            for column in row.column_names() {
+                values.insert(column.to_lowercase(), row.get::<Option<String>>(column)?);
+            }
+            converted.push(values);
+        }
+        Ok(converted)
+    }
+}
+
+impl Deref for PooledSession<'_> {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        &self.session.as_ref().expect("present until dropped").0
+    }
+}
+
+impl Drop for PooledSession<'_> {
+    fn drop(&mut self) {
+        if let Some((session, applied)) = self.session.take() {
+            self.pool.idle.lock().unwrap().push((session, applied, Instant::now()));
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio_postgres::{Client, Config, NoTls, Row};
+use tracing::{error, info, warn};
+
+use super::WarehouseMapper;
+use crate::querylog::QueryLog;
+use crate::throttle::RateLimiter;
+use crate::types::SnowflakeType;
+use crate::upgrade::FORMAT_VERSION;
+use crate::{ColumnInfo, DatabaseInfo, ForeignKeyInfo, IdentityInfo, IntervalInfo, SnowflakeMapperError, TableInfo, TimeZoneHandling};
+
+/// Schemas PostgreSQL manages itself
+const SYSTEM_SCHEMAS: &str = "('pg_catalog', 'information_schema', 'pg_toast')";
+
+/// PostgreSQL's instants, shown in the session's zone. That's Snowflake's TIMESTAMP_LTZ, not
+/// the TIMESTAMP_TZ that Snowflake's alias of the same name stands for.
+const TIMESTAMPTZ: &str = "timestamp with time zone";
+
+/// The Snowflake type of a column PostgreSQL reports. Snowflake itself reports
+/// `TIMESTAMP_TZ` and `NUMBER`, never these lowercase names, so older snapshots can be
+/// upgraded with this whatever warehouse they came from. An unconstrained `numeric` keeps
+/// any number of fraction digits, which no NUMBER can, so it becomes a FLOAT.
+pub(crate) fn snowflake_type(column: &ColumnInfo) -> SnowflakeType {
+    match SnowflakeType::from_column(column) {
+        SnowflakeType::TimestampTz { precision } if column.data_type == TIMESTAMPTZ => SnowflakeType::TimestampLtz { precision },
+        SnowflakeType::Number { .. } if column.data_type == "numeric" && column.numeric_precision.is_none() => SnowflakeType::Float,
+        parsed => parsed,
+    }
+}
+
+/// Time zone handling of a column PostgreSQL reports, see [`snowflake_type`]
+pub(crate) fn time_zone(data_type: &str) -> Option<TimeZoneHandling> {
+    if data_type == TIMESTAMPTZ {
+        Some(TimeZoneHandling::Local)
+    } else {
+        TimeZoneHandling::of(data_type)
+    }
+}
+
+/// Maps a PostgreSQL server. Catalogs in PostgreSQL are per database, so a separate
+/// connection is opened for each database being mapped.
+pub struct PostgresMapper {
+    config: Config,
+    client: Option<Client>,
+    query_log: Option<Arc<QueryLog>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl PostgresMapper {
+    /// `connection` is a libpq-style connection string or a `postgres://` URL
+    pub fn new(connection: &str) -> Result<Self> {
+        let config = Config::from_str(connection)
+            .map_err(|e| SnowflakeMapperError::ConnectionError(format!("Invalid PostgreSQL connection string: {}", e)))?;
+        Ok(Self { config, client: None, query_log: None, rate_limiter: None })
+    }
+
+    /// Throttle queries through `limiter`
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Record every executed query, redacting the connection password
+    pub fn with_query_log(mut self, log: Arc<QueryLog>) -> Self {
+        if let Some(password) = self.config.get_password() {
+            log.add_secret(&String::from_utf8_lossy(password));
+        }
+        self.query_log = Some(log);
+        self
+    }
+
+    /// Open every session with `default_transaction_read_only`, so the server rejects writes
+    pub fn with_enforce_readonly(mut self, enforce_readonly: bool) -> Self {
+        if enforce_readonly {
+            self.config.options("-c default_transaction_read_only=on");
+        }
+        self
+    }
+
+    async fn open(config: &Config) -> Result<Client> {
+        let (client, connection) = config
+            .connect(NoTls)
+            .await
+            .map_err(|e| SnowflakeMapperError::ConnectionError(e.to_string()))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("PostgreSQL connection error: {}", e);
+            }
+        });
+        Ok(client)
+    }
+
+    async fn query(&self, client: &Client, sql: &str, description: &str) -> Result<Vec<Row>> {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        let started = Instant::now();
+        let result = client.query(sql, &[]).await;
+        if let Some(log) = &self.query_log {
+            let outcome = result.as_ref().map(Vec::len).map_err(|e| e.to_string());
+            log.record("postgres", sql, started.elapsed(), outcome, false);
+        }
+        result.map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to {}: {}", description, e)).into())
+    }
+
+    fn get_value(row: &Row, column: &str) -> Result<Option<String>> {
+        row.try_get(column).map_err(|e| {
+            SnowflakeMapperError::ColumnError {
+                column: column.to_string(),
+                message: e.to_string(),
+            }
+            .into()
+        })
+    }
+
+    fn get_string(row: &Row, column: &str) -> Result<String> {
+        Ok(Self::get_value(row, column)?.unwrap_or_default())
+    }
+
+    fn get_i64(row: &Row, column: &str) -> Result<Option<i64>> {
+        row.try_get(column).map_err(|e| {
+            SnowflakeMapperError::ColumnError {
+                column: column.to_string(),
+                message: e.to_string(),
+            }
+            .into()
+        })
+    }
+
+    fn get_i32(row: &Row, column: &str) -> Result<Option<i32>> {
+        row.try_get(column).map_err(|e| {
+            SnowflakeMapperError::ColumnError {
+                column: column.to_string(),
+                message: e.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+#[async_trait]
+impl WarehouseMapper for PostgresMapper {
+    async fn connect(&mut self) -> Result<()> {
+        if self.client.is_none() {
+            self.client = Some(Self::open(&self.config).await?);
+            info!("Connected to PostgreSQL");
+        }
+        Ok(())
+    }
+
+    async fn get_all_databases(&mut self) -> Result<Vec<DatabaseInfo>> {
+        self.connect().await?;
+        let client = self.client.as_ref().expect("connected above");
+        let rows = self.query(
+            client,
+            "SELECT d.datname::text AS name, pg_get_userbyid(d.datdba)::text AS owner
+             FROM pg_database d
+             WHERE NOT d.datistemplate AND d.datallowconn
+             ORDER BY d.datname",
+            "list databases",
+        )
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(DatabaseInfo {
+                    name: Self::get_string(row, "name")?,
+                    created_on: String::new(),
+                    owner: Self::get_string(row, "owner")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_tables_for_database(&mut self, database: &str) -> Result<Vec<TableInfo>> {
+        let mut config = self.config.clone();
+        config.dbname(database);
+        let client = Self::open(&config).await?;
+
+        let column_rows = self.query(
+            &client,
+            &format!(
+                "SELECT c.table_schema::text, c.table_name::text, c.column_name::text, c.data_type::text,
+                        c.is_nullable::text, c.character_maximum_length::int, c.numeric_precision::int,
+                        c.numeric_scale::int, c.ordinal_position::int, c.column_default::text,
+                        c.collation_name::text, c.is_identity::text, c.identity_start::bigint,
+                        c.identity_increment::bigint, c.datetime_precision::int, c.interval_type::text,
+                        c.interval_precision::int,
+                        col_description(format('%I.%I', c.table_schema, c.table_name)::regclass, c.ordinal_position::int) AS comment
+                 FROM information_schema.columns c
+                 WHERE c.table_schema NOT IN {}
+                 ORDER BY c.table_schema, c.table_name, c.ordinal_position",
+                SYSTEM_SCHEMAS
+            ),
+            &format!("get columns for database {}", database),
+        )
+        .await?;
+
+        let mut tables: Vec<TableInfo> = Vec::new();
+        for row in &column_rows {
+            let schema_name = Self::get_string(row, "table_schema")?;
+            let table_name = Self::get_string(row, "table_name")?;
+            if tables
+                .last()
+                .is_none_or(|t| t.schema_name != schema_name || t.table_name != table_name)
+            {
+                tables.push(TableInfo {
+                    format_version: FORMAT_VERSION,
+                    database_name: database.to_string(),
+                    schema_name,
+                    table_name,
+                    table_type: None,
+                    comment: None,
+                    columns: Vec::new(),
+                    primary_key: Vec::new(),
+                    foreign_keys: Vec::new(),
+                    clustering_key: None,
+                    automatic_clustering: None,
+                    search_optimization: None,
+                    retention_time_in_days: None,
+                    change_tracking: None,
+                    is_transient: None,
+                    is_temporary: None,
+                    created: None,
+                    last_altered: None,
+                    last_accessed: None,
+                    usage: None,
+                    owners: Vec::new(),
+                    roles: Vec::new(),
+                    custom: BTreeMap::new(),
+                    provenance: None,
+                });
+            }
+            if let Some(table) = tables.last_mut() {
+                let data_type = Self::get_string(row, "data_type")?;
+                let is_interval = data_type.eq_ignore_ascii_case("interval");
+                let mut column = ColumnInfo {
+                    name: Self::get_string(row, "column_name")?,
+                    time_zone: time_zone(&data_type),
+                    data_type,
+                    snowflake_type: None,
+                    is_nullable: Self::get_string(row, "is_nullable")?.eq_ignore_ascii_case("YES"),
+                    character_maximum_length: Self::get_i32(row, "character_maximum_length")?,
+                    numeric_precision: Self::get_i32(row, "numeric_precision")?,
+                    numeric_scale: Self::get_i32(row, "numeric_scale")?,
+                    comment: Self::get_value(row, "comment")?,
+                    ordinal_position: Self::get_i32(row, "ordinal_position")?,
+                    column_default: Self::get_value(row, "column_default")?,
+                    collation: Self::get_value(row, "collation_name")?,
+                    identity: if Self::get_string(row, "is_identity")?.eq_ignore_ascii_case("YES") {
+                        Some(IdentityInfo {
+                            start: Self::get_i64(row, "identity_start")?,
+                            increment: Self::get_i64(row, "identity_increment")?,
+                        })
+                    } else {
+                        None
+                    },
+                    variant_shape: None,
+                    datetime_precision: Self::get_i32(row, "datetime_precision")?,
+                    interval: if is_interval {
+                        Some(IntervalInfo {
+                            fields: Self::get_value(row, "interval_type")?,
+                            precision: Self::get_i32(row, "interval_precision")?,
+                        })
+                    } else {
+                        None
+                    },
+                };
+                let snowflake_type = snowflake_type(&column);
+                if snowflake_type == SnowflakeType::Float && column.data_type == "numeric" {
+                    warn!(
+                        "Column {}.{}.{}.{} is a numeric without precision; mapping it to FLOAT, which keeps about 15 significant digits",
+                        database, table.schema_name, table.table_name, column.name
+                    );
+                }
+                column.snowflake_type = Some(snowflake_type);
+                table.columns.push(column);
+            }
+        }
+
+        let table_rows = self.query(
+            &client,
+            &format!(
+                "SELECT t.table_schema::text, t.table_name::text, t.table_type::text,
+                        obj_description(format('%I.%I', t.table_schema, t.table_name)::regclass, 'pg_class') AS comment
+                 FROM information_schema.tables t
+                 WHERE t.table_schema NOT IN {}",
+                SYSTEM_SCHEMAS
+            ),
+            &format!("get table details for database {}", database),
+        )
+        .await?;
+        let mut details = HashMap::new();
+        for row in &table_rows {
+            details.insert(
+                (Self::get_string(row, "table_schema")?, Self::get_string(row, "table_name")?),
+                (Self::get_value(row, "table_type")?, Self::get_value(row, "comment")?),
+            );
+        }
+
+        let key_rows = self.query(
+            &client,
+            &format!(
+                "SELECT tc.table_schema::text, tc.table_name::text, tc.constraint_name::text,
+                        tc.constraint_type::text, kcu.column_name::text,
+                        ref.table_catalog::text AS referenced_database, ref.table_schema::text AS referenced_schema,
+                        ref.table_name::text AS referenced_table, ref.column_name::text AS referenced_column
+                 FROM information_schema.table_constraints tc
+                 JOIN information_schema.key_column_usage kcu
+                   ON kcu.constraint_schema = tc.constraint_schema AND kcu.constraint_name = tc.constraint_name
+                 LEFT JOIN information_schema.referential_constraints rc
+                   ON rc.constraint_schema = tc.constraint_schema AND rc.constraint_name = tc.constraint_name
+                 LEFT JOIN information_schema.key_column_usage ref
+                   ON ref.constraint_schema = rc.unique_constraint_schema
+                  AND ref.constraint_name = rc.unique_constraint_name
+                  AND ref.ordinal_position = kcu.position_in_unique_constraint
+                 WHERE tc.constraint_type IN ('PRIMARY KEY', 'FOREIGN KEY') AND tc.table_schema NOT IN {}
+                 ORDER BY tc.table_schema, tc.table_name, tc.constraint_name, kcu.ordinal_position",
+                SYSTEM_SCHEMAS
+            ),
+            &format!("get constraints for database {}", database),
+        )
+        .await?;
+
+        let mut primary_keys: HashMap<(String, String), Vec<String>> = HashMap::new();
+        let mut foreign_keys: HashMap<(String, String), Vec<ForeignKeyInfo>> = HashMap::new();
+        for row in &key_rows {
+            let key = (Self::get_string(row, "table_schema")?, Self::get_string(row, "table_name")?);
+            let column = Self::get_string(row, "column_name")?;
+            if Self::get_string(row, "constraint_type")? == "PRIMARY KEY" {
+                primary_keys.entry(key).or_default().push(column);
+                continue;
+            }
+
+            let name = Self::get_string(row, "constraint_name")?;
+            let keys = foreign_keys.entry(key).or_default();
+            if keys.last().is_none_or(|fk| fk.name != name) {
+                keys.push(ForeignKeyInfo {
+                    name,
+                    columns: Vec::new(),
+                    referenced_database: Self::get_string(row, "referenced_database")?,
+                    referenced_schema: Self::get_string(row, "referenced_schema")?,
+                    referenced_table: Self::get_string(row, "referenced_table")?,
+                    referenced_columns: Vec::new(),
+                });
+            }
+            if let Some(fk) = keys.last_mut() {
+                fk.columns.push(column);
+                fk.referenced_columns.push(Self::get_string(row, "referenced_column")?);
+            }
+        }
+
+        for table in &mut tables {
+            let key = (table.schema_name.clone(), table.table_name.clone());
+            if let Some((table_type, comment)) = details.remove(&key) {
+                table.table_type = table_type;
+                table.comment = comment;
+            }
+            table.primary_key = primary_keys.remove(&key).unwrap_or_default();
+            table.foreign_keys = foreign_keys.remove(&key).unwrap_or_default();
+        }
+
+        Ok(tables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(data_type: &str, numeric_precision: Option<i32>) -> ColumnInfo {
+        serde_json::from_value(serde_json::json!({
+            "name": "C",
+            "data_type": data_type,
+            "is_nullable": true,
+            "numeric_precision": numeric_precision,
+        }))
+        .expect("columns deserialize")
+    }
+
+    #[test]
+    fn postgres_names_map_to_what_they_mean() {
+        assert_eq!(snowflake_type(&column("timestamp with time zone", None)), SnowflakeType::TimestampLtz { precision: 9 });
+        assert_eq!(time_zone("timestamp with time zone"), Some(TimeZoneHandling::Local));
+        assert_eq!(snowflake_type(&column("TIMESTAMP WITH TIME ZONE", None)), SnowflakeType::TimestampTz { precision: 9 });
+        assert_eq!(snowflake_type(&column("numeric", None)), SnowflakeType::Float);
+        assert_eq!(snowflake_type(&column("numeric", Some(10))), SnowflakeType::Number { precision: 10, scale: 0 });
+    }
+}
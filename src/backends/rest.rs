@@ -0,0 +1,420 @@
+//! Sessions over Snowflake's REST API, for logins the connector can't make.
+//!
+//! The connector only sends a password or a key pair. Logging in with an MFA passcode, or
+//! with a SAML response from Okta, takes fields it doesn't send, so such logins, and the
+//! queries of the session they open, go through the login and query requests Snowflake's own
+//! drivers use. Results are read in Snowflake's JSON result format, including the chunks
+//! large results are split into.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::fixture::QueryRow;
+use super::snowflake::SnowflakeConfig;
+use crate::SnowflakeMapperError;
+
+/// Reported to Snowflake as the client, which shows in its login and query history
+const CLIENT_APP_ID: &str = "snowflake-mapper";
+
+/// Snowflake is still running the query; its result is fetched from `getResultUrl`
+const QUERY_IN_PROGRESS: &[&str] = &["333333", "333334"];
+
+/// The session token expired; a new one is requested with the master token
+const SESSION_EXPIRED: &str = "390112";
+
+/// Pause between polls for the result of a running query
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What a login sends besides the user name and password
+#[derive(Debug, Clone)]
+pub struct RestLogin {
+    base_url: String,
+    account: String,
+    username: String,
+    password: String,
+    warehouse: String,
+    database: Option<String>,
+    role: Option<String>,
+    passcode: Option<String>,
+    /// Okta that checks the password instead of Snowflake, e.g. `https://company.okta.com`
+    okta_url: Option<String>,
+}
+
+/// A logged-in session. Clones share it, so several callers can query one session at once;
+/// Snowflake allows that, and it's the only way to use a passcode that works just once.
+#[derive(Clone)]
+pub struct RestSession {
+    http: reqwest::Client,
+    base_url: String,
+    tokens: Arc<Mutex<Tokens>>,
+    /// Numbers the session's queries in the order they were sent
+    sequence: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Clone)]
+struct Tokens {
+    session: String,
+    master: String,
+}
+
+/// Body of every Snowflake REST response
+#[derive(Debug, Deserialize)]
+struct Response {
+    #[serde(default)]
+    success: bool,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    data: Value,
+}
+
+impl Response {
+    /// Snowflake's message and error code
+    fn failure(&self) -> String {
+        format!("{} ({})", self.message.as_deref().unwrap_or("no message"), self.code.as_deref().unwrap_or("no code"))
+    }
+}
+
+/// The result of a query: column names, the first rows and the chunks holding the rest
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryData {
+    #[serde(default)]
+    rowtype: Vec<RowType>,
+    #[serde(default)]
+    rowset: Vec<Vec<Option<String>>>,
+    #[serde(default)]
+    chunks: Vec<Chunk>,
+    #[serde(default)]
+    chunk_headers: Option<Map<String, Value>>,
+    #[serde(default)]
+    qrmk: Option<String>,
+    #[serde(default)]
+    query_result_format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RowType {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chunk {
+    url: String,
+}
+
+impl RestLogin {
+    /// A login for `config`, querying on `warehouse`. Connects to `config.host` when it is set,
+    /// whatever its domain.
+    pub fn new(config: &SnowflakeConfig, warehouse: &str) -> Self {
+        let base_url = match &config.host {
+            Some(host) => format!("https://{}", host.trim_start_matches("https://").trim_end_matches('/')),
+            None => format!("https://{}.snowflakecomputing.com", config.account),
+        };
+        Self {
+            base_url,
+            // The account name is the first part of the identifier, e.g. XY12345 of xy12345.eu-west-1
+            account: config.account.split('.').next().unwrap_or_default().to_uppercase(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            warehouse: warehouse.to_string(),
+            database: config.database.clone(),
+            role: config.role.clone(),
+            passcode: config.mfa_passcode.clone(),
+            okta_url: config.okta_url.clone(),
+        }
+    }
+
+    /// Log in, waiting at most `timeout` for each request
+    pub async fn login(&self, timeout: Duration) -> Result<RestSession> {
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(format!("{}/{}", CLIENT_APP_ID, env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| SnowflakeMapperError::ConnectionError(format!("Failed to create HTTP client: {}", e)))?;
+
+        let mut data = self.client_fields();
+        if let Some(okta_url) = &self.okta_url {
+            let saml = self.okta_saml_response(&http, okta_url).await?;
+            data.insert("AUTHENTICATOR".to_string(), json!(okta_url));
+            data.insert("RAW_SAML_RESPONSE".to_string(), json!(saml));
+        } else {
+            data.insert("PASSWORD".to_string(), json!(self.password));
+        }
+        if let Some(passcode) = &self.passcode {
+            data.insert("EXT_AUTHN_DUO_METHOD".to_string(), json!("passcode"));
+            data.insert("PASSCODE".to_string(), json!(passcode));
+        }
+        let mut query = vec![("warehouse", self.warehouse.clone()), ("requestId", request_id())];
+        query.extend(self.database.clone().map(|database| ("databaseName", database)));
+        query.extend(self.role.clone().map(|role| ("roleName", role)));
+        let response: Response = send(
+            http.post(format!("{}/session/v1/login-request", self.base_url)).query(&query).json(&json!({ "data": data })),
+            "log in to Snowflake",
+        )
+        .await?;
+        if !response.success {
+            let message = format!("Snowflake rejected the login of {}: {}", self.username, response.failure());
+            return Err(SnowflakeMapperError::ConnectionError(message).into());
+        }
+        let token = |name: &str| response.data.get(name).and_then(Value::as_str).map(str::to_string);
+        let (Some(session), Some(master)) = (token("token"), token("masterToken")) else {
+            return Err(SnowflakeMapperError::ConnectionError("Snowflake's login response carries no session token".to_string()).into());
+        };
+        Ok(RestSession {
+            http,
+            base_url: self.base_url.clone(),
+            tokens: Arc::new(Mutex::new(Tokens { session, master })),
+            sequence: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// The SAML response Okta issues for the user, the way Snowflake's drivers get it:
+    /// Snowflake names the Okta endpoints, Okta's authn API trades the password for a one-time
+    /// token, and the token buys the SAML form that posts back to Snowflake. Users Okta asks
+    /// for MFA can't log in this way.
+    async fn okta_saml_response(&self, http: &reqwest::Client, okta_url: &str) -> Result<String> {
+        let okta_error = |message: String| SnowflakeMapperError::ConnectionError(format!("Okta login through {} failed: {}", okta_url, message));
+        let mut data = self.client_fields();
+        data.insert("AUTHENTICATOR".to_string(), json!(okta_url));
+        let request = http
+            .post(format!("{}/session/authenticator-request", self.base_url))
+            .query(&[("requestId", request_id())])
+            .json(&json!({ "data": data }));
+        let response = send(request, "ask Snowflake for the Okta endpoints").await?;
+        if !response.success {
+            return Err(okta_error(format!("Snowflake refused it: {}", response.failure())).into());
+        }
+        let url = |name: &str| response.data.get(name).and_then(Value::as_str).map(str::to_string);
+        let (Some(token_url), Some(sso_url)) = (url("tokenUrl"), url("ssoUrl")) else {
+            return Err(okta_error("Snowflake named no Okta endpoints".to_string()).into());
+        };
+        // The password goes to the token URL, so it must be the Okta the user asked for
+        if let Some(url) = [&token_url, &sso_url].into_iter().find(|url| !same_origin(url, okta_url)) {
+            return Err(okta_error(format!("Snowflake's Okta endpoint {} isn't on {}; check the account's SAML integration", url, okta_url)).into());
+        }
+
+        let authn = http
+            .post(&token_url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .json(&json!({ "username": self.username, "password": self.password }))
+            .send()
+            .await
+            .map_err(|e| okta_error(e.to_string()))?;
+        if authn.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(okta_error(format!("authentication failed for {}; check the username and password", self.username)).into());
+        }
+        let authn: Value = authn.error_for_status().map_err(|e| okta_error(e.to_string()))?.json().await.map_err(|e| okta_error(e.to_string()))?;
+        let Some(token) = ["sessionToken", "cookieToken"].into_iter().find_map(|name| authn.get(name).and_then(Value::as_str)) else {
+            let status = authn.get("status").and_then(Value::as_str).unwrap_or("no status");
+            return Err(okta_error(format!("Okta issued no session token ({}); users Okta asks for MFA can't log in this way", status)).into());
+        };
+
+        let saml = http
+            .get(&sso_url)
+            .query(&[("RelayState", "/some/deep/link"), ("onetimetoken", token)])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| okta_error(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| okta_error(e.to_string()))?;
+        // A form posting anywhere but this account means the SAML response was meant for
+        // another service provider
+        match form_action(&saml) {
+            Some(action) if same_origin(&action, &self.base_url) => Ok(saml),
+            action => Err(okta_error(format!(
+                "the SAML response posts to {} rather than {}; check the account's SAML integration",
+                action.as_deref().unwrap_or("nowhere"),
+                self.base_url
+            ))
+            .into()),
+        }
+    }
+
+    /// Fields every authentication request identifies the client and user with
+    fn client_fields(&self) -> Map<String, Value> {
+        [
+            ("CLIENT_APP_ID", json!(CLIENT_APP_ID)),
+            ("CLIENT_APP_VERSION", json!(env!("CARGO_PKG_VERSION"))),
+            ("ACCOUNT_NAME", json!(self.account)),
+            ("LOGIN_NAME", json!(self.username)),
+            ("CLIENT_ENVIRONMENT", json!({ "APPLICATION": CLIENT_APP_ID, "OS": std::env::consts::OS })),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+    }
+}
+
+impl RestSession {
+    /// Run `sql` and return its rows keyed by lower-cased column name
+    pub async fn query(&self, sql: &str) -> Result<Vec<QueryRow>> {
+        let submitted = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let body = json!({ "sqlText": sql, "asyncExec": false, "sequenceId": sequence, "querySubmissionTime": submitted });
+        let mut response = self.authorized(|http, url| http.post(format!("{}/queries/v1/query-request", url)).json(&body)).await?;
+        while response.code.as_deref().is_some_and(|code| QUERY_IN_PROGRESS.contains(&code)) {
+            let Some(result_url) = response.data.get("getResultUrl").and_then(Value::as_str).map(str::to_string) else {
+                let message = format!("Snowflake is running the query but gave no URL for its result: {}", response.failure());
+                return Err(SnowflakeMapperError::QueryError(message).into());
+            };
+            tokio::time::sleep(POLL_INTERVAL).await;
+            response = self.authorized(|http, url| http.get(format!("{}{}", url, result_url))).await?;
+        }
+        if !response.success {
+            return Err(SnowflakeMapperError::QueryError(response.failure()).into());
+        }
+
+        let mut data: QueryData = serde_json::from_value(response.data)
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Unexpected result from Snowflake: {}", e)))?;
+        if let Some(format) = data.query_result_format.as_deref().filter(|format| !format.eq_ignore_ascii_case("json")) {
+            return Err(SnowflakeMapperError::QueryError(format!("Snowflake returned a {} result; only JSON results can be read", format)).into());
+        }
+        let mut rows = std::mem::take(&mut data.rowset);
+        for chunk in &data.chunks {
+            rows.extend(self.download_chunk(chunk, &data).await?);
+        }
+        let columns: Vec<String> = data.rowtype.iter().map(|column| column.name.to_lowercase()).collect();
+        Ok(rows.into_iter().map(|row| columns.iter().cloned().zip(row).collect()).collect())
+    }
+
+    /// Send a request with the session token, renewing the token once if it has expired
+    async fn authorized(&self, request: impl Fn(&reqwest::Client, &str) -> reqwest::RequestBuilder) -> Result<Response> {
+        let token = self.tokens.lock().unwrap().session.clone();
+        let response = self.send_with_token(&request, &token).await?;
+        if response.code.as_deref() != Some(SESSION_EXPIRED) {
+            return Ok(response);
+        }
+        let token = self.renew(&token).await?;
+        self.send_with_token(&request, &token).await
+    }
+
+    async fn send_with_token(&self, request: &impl Fn(&reqwest::Client, &str) -> reqwest::RequestBuilder, token: &str) -> Result<Response> {
+        let builder = request(&self.http, &self.base_url)
+            .query(&[("requestId", request_id())])
+            .header(reqwest::header::ACCEPT, "application/snowflake")
+            .header(reqwest::header::AUTHORIZATION, format!("Snowflake Token=\"{}\"", token));
+        send(builder, "query Snowflake").await
+    }
+
+    /// Trade the master token for a new session token, unless another caller already has
+    async fn renew(&self, expired: &str) -> Result<String> {
+        let tokens = self.tokens.lock().unwrap().clone();
+        if tokens.session != expired {
+            return Ok(tokens.session);
+        }
+        let request = self
+            .http
+            .post(format!("{}/session/token-request", self.base_url))
+            .query(&[("requestId", request_id())])
+            .header(reqwest::header::AUTHORIZATION, format!("Snowflake Token=\"{}\"", tokens.master))
+            .json(&json!({ "oldSessionToken": expired, "requestType": "RENEW" }));
+        let response: Response = send(request, "renew the Snowflake session").await?;
+        let renewed = response.data.get("sessionToken").and_then(Value::as_str).filter(|_| response.success);
+        let Some(session) = renewed.map(str::to_string) else {
+            return Err(SnowflakeMapperError::ConnectionError(format!("Failed to renew the Snowflake session: {}", response.failure())).into());
+        };
+        let master = response.data.get("masterToken").and_then(Value::as_str).map_or(tokens.master, str::to_string);
+        *self.tokens.lock().unwrap() = Tokens { session: session.clone(), master };
+        Ok(session)
+    }
+
+    /// Rows of one result chunk, stored as rows separated by commas without the enclosing
+    /// brackets. Chunks are encrypted with the query result key `qrmk` unless Snowflake sends
+    /// headers of its own.
+    async fn download_chunk(&self, chunk: &Chunk, data: &QueryData) -> Result<Vec<Vec<Option<String>>>> {
+        let mut request = self.http.get(&chunk.url);
+        match (&data.chunk_headers, &data.qrmk) {
+            (Some(headers), _) if !headers.is_empty() => {
+                for (name, value) in headers {
+                    request = request.header(name.as_str(), value.as_str().unwrap_or_default());
+                }
+            }
+            (_, Some(qrmk)) => {
+                request = request
+                    .header("x-amz-server-side-encryption-customer-algorithm", "AES256")
+                    .header("x-amz-server-side-encryption-customer-key", qrmk.as_str());
+            }
+            _ => {}
+        }
+        let chunk_error = |e: String| SnowflakeMapperError::QueryError(format!("Failed to download a result chunk: {}", e));
+        let response = request.send().await.and_then(reqwest::Response::error_for_status).map_err(|e| chunk_error(e.to_string()))?;
+        let body = response.text().await.map_err(|e| chunk_error(e.to_string()))?;
+        Ok(serde_json::from_str(&format!("[{}]", body)).map_err(|e| chunk_error(e.to_string()))?)
+    }
+}
+
+/// Send `request` and parse Snowflake's response; HTTP errors are reported with `action`
+async fn send(request: reqwest::RequestBuilder, action: &str) -> Result<Response> {
+    let failed = |e: String| SnowflakeMapperError::ConnectionError(format!("Failed to {}: {}", action, e));
+    let response = request.send().await.and_then(reqwest::Response::error_for_status).map_err(|e| failed(e.to_string()))?;
+    Ok(response.json().await.map_err(|e| failed(e.to_string()))?)
+}
+
+/// Whether two URLs have the same scheme, host and port
+fn same_origin(url: &str, other: &str) -> bool {
+    match (reqwest::Url::parse(url), reqwest::Url::parse(other)) {
+        (Ok(url), Ok(other)) => url.origin() == other.origin(),
+        _ => false,
+    }
+}
+
+/// Where the first form of an HTML page posts to, with HTML character references decoded
+fn form_action(html: &str) -> Option<String> {
+    static ACTION: OnceLock<Regex> = OnceLock::new();
+    static REFERENCE: OnceLock<Regex> = OnceLock::new();
+    let action = ACTION.get_or_init(|| Regex::new(r#"(?is)<form[^>]*\saction="([^"]*)""#).expect("valid regex"));
+    let reference = REFERENCE.get_or_init(|| Regex::new(r"&(#[xX][0-9a-fA-F]+|#[0-9]+|amp|quot|apos|lt|gt);").expect("valid regex"));
+    let encoded = action.captures(html)?.get(1)?.as_str();
+    let decoded = reference.replace_all(encoded, |captures: &regex::Captures| {
+        let name = &captures[1];
+        let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => name.strip_prefix('#').and_then(|decimal| decimal.parse().ok()),
+        };
+        let named = match name {
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            _ => code.and_then(char::from_u32),
+        };
+        named.map_or_else(|| captures[0].to_string(), String::from)
+    });
+    Some(decoded.into_owned())
+}
+
+/// A random UUID, which Snowflake expects to identify each request
+fn request_id() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saml_forms_post_back_to_their_decoded_action() {
+        let html = r#"<html><body><form method="post" action="https&#x3a;&#x2f;&#x2f;xy12345.snowflakecomputing.com&#x2f;fed&#x2f;login?a=1&amp;b=2">"#;
+        let action = form_action(html).expect("the form has an action");
+        assert_eq!(action, "https://xy12345.snowflakecomputing.com/fed/login?a=1&b=2");
+        assert!(same_origin(&action, "https://xy12345.snowflakecomputing.com"));
+        assert!(!same_origin(&action, "https://xy12345.snowflakecomputing.com:8443"));
+        assert!(!same_origin("https://evil.example.com/fed/login", "https://xy12345.snowflakecomputing.com"));
+        assert_eq!(form_action("<html>no form</html>"), None);
+    }
+}
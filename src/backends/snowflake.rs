@@ -0,0 +1,1347 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use super::fixture::{FixtureStore, QueryRow};
+use super::pool::{QueryAccounting, SessionPool};
+use super::WarehouseMapper;
+use crate::formats::quote_identifier;
+use crate::guardrails::{check_read_only, QueryBudget};
+use crate::hooks::{self, SqlHooks};
+use crate::progress::{ProgressEvent, ProgressListener};
+use crate::querylog::QueryLog;
+use crate::account::{AccountParameter, ResourceMonitor};
+use crate::replication::{DatabaseReplication, ReplicationGroup};
+use crate::retry::{self, RetryPolicy};
+use crate::shares::{ShareInfo, SharedObject};
+use crate::throttle::RateLimiter;
+use crate::types::SnowflakeType;
+use crate::upgrade::FORMAT_VERSION;
+use crate::users::{UserInfo, UserSource};
+use crate::variant;
+use crate::visibility::AccountObject;
+use crate::{
+    ColumnInfo, DatabaseInfo, ForeignKeyInfo, IdentityInfo, RoleInfo, SchemaInfo, SnowflakeMapperError, TableInfo, TableUsage, TimeZoneHandling,
+    WarehouseInfo,
+};
+
+/// Table-level attributes from information_schema.tables, merged into `TableInfo`
+struct TableDetails {
+    table_type: Option<String>,
+    comment: Option<String>,
+    clustering_key: Option<String>,
+    automatic_clustering: Option<bool>,
+    retention_time_in_days: Option<i32>,
+    is_transient: Option<bool>,
+    created: Option<String>,
+    last_altered: Option<String>,
+}
+
+/// Table properties only `SHOW TABLES` exposes
+struct ShownTable {
+    search_optimization: Option<bool>,
+    change_tracking: Option<bool>,
+}
+
+/// Snowflake-specific session operations on top of the generic mapper interface
+#[async_trait]
+pub trait SnowflakeOperations: WarehouseMapper {
+    async fn set_warehouse(&mut self, warehouse: &str) -> Result<()>;
+    async fn set_role(&mut self, role: &str) -> Result<()>;
+    async fn list_warehouses(&mut self) -> Result<Vec<WarehouseInfo>>;
+    async fn list_schemas(&mut self, database: &str) -> Result<Vec<SchemaInfo>>;
+    async fn list_roles(&mut self) -> Result<Vec<RoleInfo>>;
+    async fn get_account_parameters(&mut self) -> Result<Vec<AccountParameter>>;
+    async fn list_resource_monitors(&mut self) -> Result<Vec<ResourceMonitor>>;
+    async fn list_users(&mut self, source: UserSource) -> Result<Vec<UserInfo>>;
+    async fn list_shares(&mut self) -> Result<Vec<ShareInfo>>;
+    async fn describe_share(&mut self, share: &str) -> Result<Vec<SharedObject>>;
+    async fn list_replication_groups(&mut self) -> Result<Vec<ReplicationGroup>>;
+    async fn list_replication_group_databases(&mut self, group: &str) -> Result<Vec<String>>;
+    async fn list_replication_databases(&mut self) -> Result<Vec<DatabaseReplication>>;
+    /// Every live database, schema and table in SNOWFLAKE.ACCOUNT_USAGE, regardless of grants
+    async fn list_account_usage_objects(&mut self) -> Result<Vec<AccountObject>>;
+    /// Schemas and tables of `database` visible to the current role
+    async fn list_visible_objects(&mut self, database: &str) -> Result<Vec<AccountObject>>;
+}
+
+pub struct SnowflakeMapper {
+    pub config: SnowflakeConfig,
+    /// Sessions to query through; opened on connect with a single session unless a shared
+    /// pool was supplied
+    pub sessions: Option<Arc<SessionPool>>,
+    /// Retries for transient failures to open a session
+    pub retry_policy: RetryPolicy,
+    pub fixtures: Option<FixtureMode>,
+    pub progress: Option<Arc<dyn ProgressListener>>,
+    /// Query ACCOUNT_USAGE for `last_accessed`; slow and needs the SNOWFLAKE database privileges
+    pub access_history: bool,
+    /// Rows sampled per table to infer the shape of semi-structured columns; `None` skips sampling
+    pub variant_sample: Option<u32>,
+    /// Days of ACCOUNT_USAGE history to count reads over for `usage`; `None` skips it
+    pub usage_window_days: Option<u32>,
+    /// User SQL whose results are attached under `custom`
+    pub sql_hooks: Option<Arc<SqlHooks>>,
+    /// Columns above which a database's columns are fetched in chunks; `None` fetches them
+    /// in one query
+    pub shard_columns: Option<usize>,
+    pub budget: Option<Arc<QueryBudget>>,
+    /// Warehouse used for the mapper's own queries instead of `config.warehouse`
+    pub metadata_warehouse: Option<String>,
+    /// Refuse anything but SHOW/SELECT/DESCRIBE/USE in `run_query`
+    pub enforce_readonly: bool,
+    pub query_log: Option<Arc<QueryLog>>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Retries spent on the database being mapped, shared with the caller's own retries
+    pub retries_spent: AtomicU32,
+}
+
+/// Renders timestamps as RFC 3339 instead of the epoch values the result set carries
+const TIMESTAMP_FORMAT: &str = "'YYYY-MM-DD\"T\"HH24:MI:SS.FF3TZH:TZM'";
+
+/// Queries run per database, reported as steps of `ProgressEvent::DatabaseStep`
+const DATABASE_STEPS: usize = 5;
+
+/// Days of history ACCOUNT_USAGE.ACCESS_HISTORY keeps; bounding the scan by it lets
+/// Snowflake prune old partitions without missing a read
+const ACCESS_HISTORY_RETENTION_DAYS: u32 = 365;
+
+/// `(schema, table)` of an ACCESS_HISTORY `objectName` (`DATABASE.SCHEMA.TABLE`); quoted
+/// identifiers containing dots are not split correctly
+fn table_key_from_object_name(object_name: &str) -> Option<(String, String)> {
+    let mut parts = object_name.splitn(3, '.').skip(1);
+    Some((parts.next()?.to_string(), parts.next()?.to_string()))
+}
+
+/// Whether query results are captured to, or served from, a fixture directory
+pub enum FixtureMode {
+    Record(FixtureStore),
+    Replay(FixtureStore),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SnowflakeConfig {
+    pub account: String,
+    pub username: String,
+    pub password: String,
+    pub warehouse: String,
+    pub database: Option<String>,
+    pub role: Option<String>,
+    /// How long logins wait for a Duo push to be approved; `None` when the user has no MFA
+    pub mfa_timeout: Option<Duration>,
+    /// Duo passcode sent with the login instead of waiting for a push, see
+    /// [`rest`](super::rest)
+    pub mfa_passcode: Option<String>,
+    /// Okta to log in through instead of Snowflake's own password check, e.g.
+    /// `https://company.okta.com`
+    pub okta_url: Option<String>,
+    /// Endpoint to connect to instead of the one derived from `account`, e.g. a PrivateLink host
+    pub host: Option<String>,
+    /// How often idle sessions are pinged so they don't expire; `None` never pings
+    pub keep_alive: Option<Duration>,
+}
+
+impl SnowflakeConfig {
+    /// Account identifier to hand the connector, which always connects to
+    /// `<account>.snowflakecomputing.com`; a custom `host` under that domain is reached by
+    /// passing the part in front of it
+    pub fn connector_account(&self) -> Result<String> {
+        let Some(host) = &self.host else {
+            return Ok(self.account.clone());
+        };
+        let host = host.trim_start_matches("https://").trim_end_matches('/').to_ascii_lowercase();
+        let host = host.strip_suffix(":443").unwrap_or(&host);
+        match host.strip_suffix(".snowflakecomputing.com") {
+            Some(account) if !account.is_empty() => Ok(account.to_string()),
+            _ => Err(SnowflakeMapperError::ConnectionError(format!(
+                "Host {} is not a snowflakecomputing.com endpoint, the only kind the Snowflake connector can reach",
+                host
+            ))
+            .into()),
+        }
+    }
+}
+
+impl SnowflakeMapper {
+    pub fn new(config: SnowflakeConfig) -> Self {
+        Self {
+            config,
+            sessions: None,
+            retry_policy: RetryPolicy::default(),
+            fixtures: None,
+            progress: None,
+            access_history: false,
+            variant_sample: None,
+            usage_window_days: None,
+            sql_hooks: None,
+            shard_columns: None,
+            budget: None,
+            metadata_warehouse: None,
+            enforce_readonly: true,
+            query_log: None,
+            rate_limiter: None,
+            retries_spent: AtomicU32::new(0),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn with_access_history(mut self, access_history: bool) -> Self {
+        self.access_history = access_history;
+        self
+    }
+
+    /// Count the queries and users reading each table over the last `days` days
+    pub fn with_usage_window(mut self, days: Option<u32>) -> Self {
+        self.usage_window_days = days;
+        self
+    }
+
+    /// Run `hooks` for every database and table and attach their results under `custom`
+    pub fn with_sql_hooks(mut self, hooks: Option<Arc<SqlHooks>>) -> Self {
+        self.sql_hooks = hooks;
+        self
+    }
+
+    /// Fetch the columns of databases with more than `columns` columns in chunks of about
+    /// that many, see [`fetch_columns`](Self::fetch_columns)
+    pub fn with_shard_columns(mut self, columns: Option<usize>) -> Self {
+        self.shard_columns = columns;
+        self
+    }
+
+    /// Sample up to `rows` rows of each table with VARIANT / OBJECT / ARRAY columns and infer
+    /// their nested structure
+    pub fn with_variant_sample(mut self, rows: Option<u32>) -> Self {
+        self.variant_sample = rows;
+        self
+    }
+
+    /// Run every query on `warehouse`, typically an X-Small one, so mapping never resumes the default warehouse
+    pub fn with_metadata_warehouse(mut self, warehouse: Option<String>) -> Self {
+        self.metadata_warehouse = warehouse;
+        self
+    }
+
+    pub fn with_enforce_readonly(mut self, enforce_readonly: bool) -> Self {
+        self.enforce_readonly = enforce_readonly;
+        self
+    }
+
+    /// Record every executed query, redacting the connection password
+    pub fn with_query_log(mut self, log: Arc<QueryLog>) -> Self {
+        log.add_secret(&self.config.password);
+        self.query_log = Some(log);
+        self
+    }
+
+    /// Throttle queries through `limiter`
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Count every query against `budget`, failing once it is exhausted
+    pub fn with_query_budget(mut self, budget: Arc<QueryBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Query through `pool`, shared with other mappers, instead of a session of its own
+    pub fn with_session_pool(mut self, pool: Arc<SessionPool>) -> Self {
+        self.sessions = Some(pool);
+        self
+    }
+
+    pub fn with_fixtures(mut self, fixtures: FixtureMode) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    /// Unlike `set_warehouse`, never falls back to another warehouse: that could be the large
+    /// one the override exists to avoid
+    async fn use_metadata_warehouse(&mut self, warehouse: &str) -> Result<()> {
+        let warehouses = self.list_warehouses().await?;
+        if !warehouses.iter().any(|w| w.name.eq_ignore_ascii_case(warehouse)) {
+            return Err(SnowflakeMapperError::ConnectionError(format!(
+                "Metadata warehouse '{}' not found or not accessible",
+                warehouse
+            ))
+            .into());
+        }
+        self.run_query(&format!("USE WAREHOUSE \"{}\"", warehouse))
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to set metadata warehouse: {}", e)))?;
+        info!("Using metadata warehouse: {}", warehouse);
+        Ok(())
+    }
+
+    fn report_step(&self, database: &str, completed: usize, message: String) {
+        if let Some(progress) = &self.progress {
+            progress.on_event(&ProgressEvent::DatabaseStep {
+                database: database.to_string(),
+                completed,
+                total: DATABASE_STEPS,
+                message,
+            });
+        }
+    }
+
+    fn is_replaying(&self) -> bool {
+        matches!(self.fixtures, Some(FixtureMode::Replay(_)))
+    }
+
+    /// Central query dispatch: runs `query` on the session (or replays it from fixtures)
+    /// and returns rows keyed by lower-cased column name. Every query must go through here
+    /// so read-only enforcement and the query budget can't be bypassed.
+    async fn run_query(&self, query: &str) -> Result<Vec<QueryRow>> {
+        if self.enforce_readonly {
+            check_read_only(query)?;
+        }
+        if let Some(budget) = &self.budget {
+            budget.acquire()?;
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+
+        let started = Instant::now();
+        let result = self.execute_query(query).await;
+        if let Some(log) = &self.query_log {
+            let outcome = result.as_ref().map(Vec::len).map_err(|e| e.to_string());
+            log.record("snowflake", query, started.elapsed(), outcome, self.is_replaying());
+        }
+        result
+    }
+
+    async fn execute_query(&self, query: &str) -> Result<Vec<QueryRow>> {
+        if let Some(FixtureMode::Replay(store)) = &self.fixtures {
+            return store.load(query);
+        }
+
+        let sessions = self.sessions.as_ref().context("Not connected to Snowflake")?;
+        let converted = sessions.acquire().await?.query(query).await?;
+        if query.trim_start().get(..4).is_some_and(|verb| verb.eq_ignore_ascii_case("USE ")) {
+            sessions.remember_setup(query);
+        }
+
+        if let Some(FixtureMode::Record(store)) = &self.fixtures {
+            store.save(query, &converted)?;
+        }
+        Ok(converted)
+    }
+
+    async fn ensure_connected(&mut self) -> Result<()> {
+        if self.sessions.is_none() && !self.is_replaying() {
+            let warehouse = self.metadata_warehouse.clone().unwrap_or_else(|| self.config.warehouse.clone());
+            let pool = SessionPool::new(&self.config, &warehouse, 1)?;
+            // Log in now so bad credentials fail on connect rather than on the first query
+            let mut retries = 0;
+            loop {
+                match pool.acquire().await {
+                    Ok(_) => break,
+                    Err(e) if self.retry_policy.should_retry(&e, retries) => {
+                        retries += 1;
+                        warn!("Failed to open a Snowflake session ({:?}), retry {} of {}: {:#}", retry::classify(&e), retries, self.retry_policy.max_retries, e);
+                        tokio::time::sleep(self.retry_policy.delay_before(retries)).await;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            let pool = Arc::new(pool);
+            if let Some(interval) = self.config.keep_alive {
+                pool.keep_alive(
+                    interval,
+                    QueryAccounting {
+                        budget: self.budget.clone(),
+                        rate_limiter: self.rate_limiter.clone(),
+                        query_log: self.query_log.clone(),
+                    },
+                );
+            }
+            self.sessions = Some(pool);
+        }
+        Ok(())
+    }
+
+    fn get_value_from_row(row: &QueryRow, column: &str) -> Result<String> {
+        match row.get(column) {
+            Some(Some(value)) => Ok(value.clone()),
+            Some(None) => Ok(String::new()),
+            None => Err(SnowflakeMapperError::ColumnError {
+                column: column.to_string(),
+                message: "column not present in result".to_string(),
+            }.into())
+        }
+    }
+
+    /// A row of `database_name`, `schema_name`, `table_name` and `owner`
+    fn account_object_from_row(row: &QueryRow) -> Result<AccountObject> {
+        Ok(AccountObject {
+            database: Self::get_value_from_row(row, "database_name")?,
+            schema: Self::get_optional_value_from_row(row, "schema_name")?,
+            table: Self::get_optional_value_from_row(row, "table_name")?,
+            owner: Self::get_optional_value_from_row(row, "owner")?,
+        })
+    }
+
+    fn get_optional_value_from_row(row: &QueryRow, column: &str) -> Result<Option<String>> {
+        Self::get_value_from_row(row, column).map(|value| (!value.is_empty()).then_some(value))
+    }
+
+    /// A comma-separated `SHOW` column, such as the consumers of a share
+    fn get_list_from_row(row: &QueryRow, column: &str) -> Result<Vec<String>> {
+        Ok(Self::get_optional_value_from_row(row, column)?
+            .map(|value| value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+            .unwrap_or_default())
+    }
+
+    fn get_bool_from_row(row: &QueryRow, column: &str) -> Result<bool> {
+        Ok(Self::get_value_from_row(row, column)?.eq_ignore_ascii_case("true"))
+    }
+
+    fn get_i32_from_row(row: &QueryRow, column: &str) -> Result<Option<i32>> {
+        match Self::get_value_from_row(row, column) {
+            Ok(value) if !value.is_empty() => {
+                value.parse()
+                    .map(Some)
+                    .map_err(|e| SnowflakeMapperError::ColumnError {
+                        column: column.to_string(),
+                        message: format!("Failed to parse as i32: {}", e),
+                    }.into())
+            },
+            Ok(_) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_identity_from_row(row: &QueryRow) -> Result<Option<IdentityInfo>> {
+        if !Self::get_value_from_row(row, "is_identity")?.eq_ignore_ascii_case("YES") {
+            return Ok(None);
+        }
+        let parse = |column: &str| -> Result<Option<i64>> {
+            Self::get_optional_value_from_row(row, column)?
+                .map(|value| {
+                    value.parse().map_err(|e| SnowflakeMapperError::ColumnError {
+                        column: column.to_string(),
+                        message: format!("Failed to parse as i64: {}", e),
+                    })
+                })
+                .transpose()
+                .map_err(Into::into)
+        };
+        Ok(Some(IdentityInfo {
+            start: parse("identity_start")?,
+            increment: parse("identity_increment")?,
+        }))
+    }
+
+    async fn get_table_details(&self, database: &str) -> Result<HashMap<(String, String), TableDetails>> {
+        let query = format!(
+            "SELECT table_schema, table_name, table_type, comment, clustering_key, auto_clustering_on,
+             retention_time, is_transient,
+             TO_VARCHAR(created, {format}) AS created, TO_VARCHAR(last_altered, {format}) AS last_altered
+             FROM {}.information_schema.tables",
+            database,
+            format = TIMESTAMP_FORMAT
+        );
+        let rows = self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to get table details for database {}: {}", database, e)))?;
+
+        let mut details = HashMap::new();
+        for row in rows {
+            details.insert(
+                (Self::get_value_from_row(&row, "table_schema")?, Self::get_value_from_row(&row, "table_name")?),
+                TableDetails {
+                    table_type: Self::get_optional_value_from_row(&row, "table_type")?,
+                    comment: Self::get_optional_value_from_row(&row, "comment")?,
+                    clustering_key: Self::get_optional_value_from_row(&row, "clustering_key")?,
+                    automatic_clustering: Self::get_optional_value_from_row(&row, "auto_clustering_on")?
+                        .map(|value| value.eq_ignore_ascii_case("YES")),
+                    retention_time_in_days: Self::get_i32_from_row(&row, "retention_time")?,
+                    is_transient: Self::get_optional_value_from_row(&row, "is_transient")?
+                        .map(|value| value.eq_ignore_ascii_case("YES")),
+                    created: Self::get_optional_value_from_row(&row, "created")?,
+                    last_altered: Self::get_optional_value_from_row(&row, "last_altered")?,
+                },
+            );
+        }
+        Ok(details)
+    }
+
+    /// Most recent read of each table in ACCOUNT_USAGE.ACCESS_HISTORY (Enterprise edition, lags up to 3 hours)
+    async fn get_last_accessed(&self, database: &str) -> Result<HashMap<(String, String), String>> {
+        let query = format!(
+            "SELECT obj.value:\"objectName\"::string AS object_name, TO_VARCHAR(MAX(query_start_time), {}) AS last_accessed
+             FROM snowflake.account_usage.access_history, LATERAL FLATTEN(base_objects_accessed) obj
+             WHERE obj.value:\"objectDomain\"::string = 'Table'
+               AND SPLIT_PART(obj.value:\"objectName\"::string, '.', 1) = '{}'
+               AND query_start_time >= DATEADD(day, -{}, CURRENT_TIMESTAMP())
+             GROUP BY 1",
+            TIMESTAMP_FORMAT,
+            database.replace('\'', "''"),
+            ACCESS_HISTORY_RETENTION_DAYS
+        );
+        let rows = self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to get access history for database {}: {}", database, e)))?;
+
+        let mut accessed = HashMap::new();
+        for row in rows {
+            if let Some(key) = table_key_from_object_name(&Self::get_value_from_row(&row, "object_name")?) {
+                accessed.insert(key, Self::get_value_from_row(&row, "last_accessed")?);
+            }
+        }
+        Ok(accessed)
+    }
+
+    /// Queries and distinct users reading each table or view over the last `days` days, from
+    /// ACCOUNT_USAGE.ACCESS_HISTORY (Enterprise edition, lags up to 3 hours)
+    async fn get_table_usage(&self, database: &str, days: u32) -> Result<HashMap<(String, String), TableUsage>> {
+        let query = format!(
+            "SELECT obj.value:\"objectName\"::string AS object_name,
+                    COUNT(DISTINCT ah.query_id) AS query_count, COUNT(DISTINCT ah.user_name) AS distinct_users
+             FROM snowflake.account_usage.access_history ah, LATERAL FLATTEN(ah.base_objects_accessed) obj
+             WHERE obj.value:\"objectDomain\"::string IN ('Table', 'View', 'Materialized view')
+               AND SPLIT_PART(obj.value:\"objectName\"::string, '.', 1) = '{}'
+               AND ah.query_start_time >= DATEADD(day, -{}, CURRENT_TIMESTAMP())
+             GROUP BY 1",
+            database.replace('\'', "''"),
+            days
+        );
+        let rows = self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to get usage for database {}: {}", database, e)))?;
+
+        let count = |row: &QueryRow, column: &str| -> Result<u64> {
+            Self::get_value_from_row(row, column)?.parse().map_err(|e| {
+                SnowflakeMapperError::ColumnError { column: column.to_string(), message: format!("Failed to parse as u64: {}", e) }.into()
+            })
+        };
+        let mut usage = HashMap::new();
+        for row in rows {
+            if let Some(key) = table_key_from_object_name(&Self::get_value_from_row(&row, "object_name")?) {
+                usage.insert(
+                    key,
+                    TableUsage {
+                        window_days: days,
+                        query_count: count(&row, "query_count")?,
+                        distinct_users: count(&row, "distinct_users")?,
+                    },
+                );
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Infer the shape of the semi-structured columns of each base table from a row sample.
+    /// A table that can't be sampled keeps bare types rather than failing the database.
+    async fn infer_variant_shapes(&self, tables: &mut [TableInfo], rows: u32) {
+        for table in tables.iter_mut().filter(|t| t.is_base_table()) {
+            let columns: Vec<usize> = (0..table.columns.len())
+                .filter(|&i| table.columns[i].snowflake_type().is_semi_structured())
+                .collect();
+            if columns.is_empty() {
+                continue;
+            }
+            let selected: Vec<String> = columns
+                .iter()
+                .enumerate()
+                .map(|(n, &i)| format!("TO_JSON({}) AS v{}", quote_identifier(&table.columns[i].name), n))
+                .collect();
+            let query = format!(
+                "SELECT {} FROM {}.{}.{} SAMPLE ({} ROWS)",
+                selected.join(", "),
+                quote_identifier(&table.database_name),
+                quote_identifier(&table.schema_name),
+                quote_identifier(&table.table_name),
+                rows
+            );
+            let sample = match self.run_query(&query).await {
+                Ok(sample) => sample,
+                Err(e) => {
+                    warn!("Failed to sample {}.{}.{}: {}", table.database_name, table.schema_name, table.table_name, e);
+                    continue;
+                }
+            };
+            for (n, &i) in columns.iter().enumerate() {
+                let values: Vec<serde_json::Value> = sample
+                    .iter()
+                    .filter_map(|row| row.get(&format!("v{}", n)).cloned().flatten())
+                    .filter_map(|json| serde_json::from_str(&json).ok())
+                    .collect();
+                table.columns[i].variant_shape = variant::infer(&values).map(Box::new);
+            }
+        }
+    }
+
+    /// Attach the results of user SQL hooks to `tables`. A failing hook is skipped with a
+    /// warning, like variant sampling, so one bad query doesn't lose the database.
+    async fn run_sql_hooks(&self, hooks: &SqlHooks, database: &str, tables: &mut [TableInfo]) {
+        for hook in &hooks.database {
+            let rows = match self.run_query(&hooks::render(&hook.sql, database, None, None)).await {
+                Ok(rows) => rows,
+                Err(e) => {
+                    warn!("SQL hook {} failed for database {}: {}", hook.name, database, e);
+                    continue;
+                }
+            };
+            let mut by_table: HashMap<(String, String), QueryRow> = HashMap::new();
+            for mut row in rows {
+                let (Some(Some(schema)), Some(Some(table))) = (row.remove("schema_name"), row.remove("table_name")) else {
+                    warn!("SQL hook {} must return schema_name and table_name columns; skipping it", hook.name);
+                    break;
+                };
+                by_table.entry((schema, table)).or_insert(row);
+            }
+            for table in tables.iter_mut() {
+                if let Some(row) = by_table.remove(&(table.schema_name.clone(), table.table_name.clone())) {
+                    table.custom.insert(hook.name.clone(), row);
+                }
+            }
+        }
+
+        for table in tables.iter_mut() {
+            for hook in &hooks.table {
+                let query = hooks::render(&hook.sql, database, Some(&table.schema_name), Some(&table.table_name));
+                match self.run_query(&query).await {
+                    Ok(rows) => {
+                        if let Some(row) = rows.into_iter().next() {
+                            table.custom.insert(hook.name.clone(), row);
+                        }
+                    }
+                    Err(e) => warn!("SQL hook {} failed for {}.{}.{}: {}", hook.name, database, table.schema_name, table.table_name, e),
+                }
+            }
+        }
+    }
+
+    /// Column rows of `database`, grouped by table in ordinal order. With `shard_columns`,
+    /// the columns are counted first, and a database with more than that is fetched in
+    /// chunks: runs of whole schemas, or table-name ranges of a schema too large on its own.
+    /// Each chunk is retried on its own, so a timeout doesn't restart the whole database.
+    async fn fetch_columns(&self, database: &str) -> Result<Vec<QueryRow>> {
+        let select = format!(
+            "SELECT table_schema, table_name, column_name, data_type, 
+             is_nullable, character_maximum_length, numeric_precision, numeric_scale, comment,
+             ordinal_position, column_default, collation_name, is_identity, identity_start, identity_increment,
+             datetime_precision
+             FROM {}.information_schema.columns",
+            database
+        );
+        let order = "ORDER BY table_schema, table_name, ordinal_position";
+        let Some(limit) = self.shard_columns else {
+            return self.run_query(&format!("{}\n             {}", select, order)).await;
+        };
+
+        let counts = self.column_counts(&format!(
+            "SELECT table_schema AS name, COUNT(*) AS column_count FROM {}.information_schema.columns
+             GROUP BY table_schema ORDER BY table_schema",
+            database
+        ))
+        .await?;
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        if total <= limit {
+            return self.run_query(&format!("{}\n             {}", select, order)).await;
+        }
+
+        // Chunks are fetched in schema and table order, so each table's rows stay together
+        let literal = |name: &str| format!("'{}'", name.replace('\'', "''"));
+        let mut chunks = Vec::new();
+        for schemas in pack(counts, limit) {
+            let [(schema, count)] = schemas.as_slice() else {
+                let names: Vec<String> = schemas.iter().map(|(schema, _)| literal(schema)).collect();
+                chunks.push(format!("table_schema IN ({})", names.join(", ")));
+                continue;
+            };
+            if *count <= limit {
+                chunks.push(format!("table_schema = {}", literal(schema)));
+                continue;
+            }
+            let tables = self.column_counts(&format!(
+                "SELECT table_name AS name, COUNT(*) AS column_count FROM {}.information_schema.columns
+                 WHERE table_schema = {} GROUP BY table_name ORDER BY table_name",
+                database,
+                literal(schema)
+            ))
+            .await?;
+            for range in pack(tables, limit) {
+                let (first, last) = (&range[0].0, &range[range.len() - 1].0);
+                chunks.push(format!("table_schema = {} AND table_name BETWEEN {} AND {}", literal(schema), literal(first), literal(last)));
+            }
+        }
+        info!("Fetching {} columns of {} in {} chunks", total, database, chunks.len());
+
+        let mut rows = Vec::with_capacity(total);
+        for chunk in chunks {
+            rows.extend(self.run_query_with_retries(&format!("{}\n             WHERE {}\n             {}", select, chunk, order)).await?);
+        }
+        Ok(rows)
+    }
+
+    /// `(name, column_count)` rows of a counting query
+    async fn column_counts(&self, query: &str) -> Result<Vec<(String, usize)>> {
+        let rows = self.run_query_with_retries(query).await?;
+        rows.iter()
+            .map(|row| {
+                let count = Self::get_value_from_row(row, "column_count")?;
+                let count = count.parse().map_err(|e| SnowflakeMapperError::ColumnError {
+                    column: "column_count".to_string(),
+                    message: format!("invalid count {}: {}", count, e),
+                })?;
+                Ok((Self::get_value_from_row(row, "name")?, count))
+            })
+            .collect()
+    }
+
+    /// Run `query`, retrying failures the retry policy allows while the query budget lasts.
+    /// Retries count towards [`retries_spent`](Self::retries_spent), so they share one limit
+    /// with the retries of the whole database.
+    async fn run_query_with_retries(&self, query: &str) -> Result<Vec<QueryRow>> {
+        loop {
+            match self.run_query(query).await {
+                Err(e)
+                    if self.retry_policy.should_retry(&e, self.retries_spent.load(Ordering::Relaxed))
+                        && !self.budget.as_ref().is_some_and(|b| b.is_exhausted()) =>
+                {
+                    let retries = self.retries_spent.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!("Query failed ({:?}), retry {} of {}: {:#}", retry::classify(&e), retries, self.retry_policy.max_retries, e);
+                    tokio::time::sleep(self.retry_policy.delay_before(retries)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Search optimization and change tracking are only exposed by `SHOW TABLES`; search
+    /// optimization only on editions that support it
+    async fn get_shown_tables(&self, database: &str) -> Result<HashMap<(String, String), ShownTable>> {
+        let query = format!("SHOW TABLES IN DATABASE \"{}\"", database);
+        let rows = self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to show tables for database {}: {}", database, e)))?;
+
+        let flag = |row: &QueryRow, column: &str| match row.get(column) {
+            Some(Some(status)) => Some(status.eq_ignore_ascii_case("ON")),
+            _ => None,
+        };
+        let mut shown = HashMap::new();
+        for row in rows {
+            shown.insert(
+                (Self::get_value_from_row(&row, "schema_name")?, Self::get_value_from_row(&row, "name")?),
+                ShownTable {
+                    search_optimization: flag(&row, "search_optimization"),
+                    change_tracking: flag(&row, "change_tracking"),
+                },
+            );
+        }
+        Ok(shown)
+    }
+
+    async fn get_primary_keys(&self, database: &str) -> Result<HashMap<(String, String), Vec<String>>> {
+        let query = format!("SHOW PRIMARY KEYS IN DATABASE \"{}\"", database);
+        let rows = self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to get primary keys for database {}: {}", database, e)))?;
+
+        let mut keyed: HashMap<(String, String), Vec<(i32, String)>> = HashMap::new();
+        for row in rows {
+            keyed
+                .entry((Self::get_value_from_row(&row, "schema_name")?, Self::get_value_from_row(&row, "table_name")?))
+                .or_default()
+                .push((
+                    Self::get_i32_from_row(&row, "key_sequence")?.unwrap_or_default(),
+                    Self::get_value_from_row(&row, "column_name")?,
+                ));
+        }
+
+        Ok(keyed
+            .into_iter()
+            .map(|(table, mut columns)| {
+                columns.sort();
+                (table, columns.into_iter().map(|(_, column)| column).collect())
+            })
+            .collect())
+    }
+
+    async fn get_foreign_keys(&self, database: &str) -> Result<HashMap<(String, String), Vec<ForeignKeyInfo>>> {
+        let query = format!("SHOW IMPORTED KEYS IN DATABASE \"{}\"", database);
+        let rows = self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to get foreign keys for database {}: {}", database, e)))?;
+
+        // Multi-column keys arrive as one row per column, ordered by key_sequence
+        let mut keyed: HashMap<(String, String), Vec<ForeignKeyInfo>> = HashMap::new();
+        let mut rows_with_sequence = Vec::new();
+        for row in rows {
+            rows_with_sequence.push((Self::get_i32_from_row(&row, "key_sequence")?.unwrap_or_default(), row));
+        }
+        rows_with_sequence.sort_by_key(|(sequence, _)| *sequence);
+
+        for (_, row) in rows_with_sequence {
+            let table = (Self::get_value_from_row(&row, "fk_schema_name")?, Self::get_value_from_row(&row, "fk_table_name")?);
+            let name = Self::get_value_from_row(&row, "fk_name")?;
+            let keys = keyed.entry(table).or_default();
+            let index = match keys.iter().position(|fk| fk.name == name) {
+                Some(index) => index,
+                None => {
+                    keys.push(ForeignKeyInfo {
+                        name,
+                        columns: Vec::new(),
+                        referenced_database: Self::get_value_from_row(&row, "pk_database_name")?,
+                        referenced_schema: Self::get_value_from_row(&row, "pk_schema_name")?,
+                        referenced_table: Self::get_value_from_row(&row, "pk_table_name")?,
+                        referenced_columns: Vec::new(),
+                    });
+                    keys.len() - 1
+                }
+            };
+            keys[index].columns.push(Self::get_value_from_row(&row, "fk_column_name")?);
+            keys[index].referenced_columns.push(Self::get_value_from_row(&row, "pk_column_name")?);
+        }
+        Ok(keyed)
+    }
+}
+
+#[async_trait]
+impl WarehouseMapper for SnowflakeMapper {
+    async fn connect(&mut self) -> Result<()> {
+        self.ensure_connected().await?;
+        if self.is_replaying() {
+            // Session setup isn't part of the catalog; fixtures only need the mapping queries
+            return Ok(());
+        }
+        
+        // Store values before using them to avoid borrowing issues
+        let warehouse = self.config.warehouse.clone();
+        let role = self.config.role.clone();
+        
+        // Set warehouse immediately after connection
+        match self.metadata_warehouse.clone() {
+            Some(metadata_warehouse) => self.use_metadata_warehouse(&metadata_warehouse).await?,
+            None => self.set_warehouse(&warehouse).await?,
+        }
+        if let Some(role) = role {
+            self.set_role(&role).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_all_databases(&mut self) -> Result<Vec<DatabaseInfo>> {
+        self.ensure_connected().await?;
+        let rows = self.run_query("SHOW DATABASES")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list databases: {}", e)))?;
+        
+        let mut databases = Vec::new();
+        for row in rows {
+            databases.push(DatabaseInfo {
+                name: Self::get_value_from_row(&row, "name")?,
+                created_on: Self::get_value_from_row(&row, "created_on")?,
+                owner: Self::get_value_from_row(&row, "owner")?,
+            });
+        }
+        Ok(databases)
+    }
+
+    fn set_progress_listener(&mut self, listener: Arc<dyn ProgressListener>) {
+        self.progress = Some(listener);
+    }
+
+    fn set_retries_spent(&mut self, retries: u32) {
+        self.retries_spent.store(retries, Ordering::Relaxed);
+    }
+
+    fn retries_spent(&self) -> u32 {
+        self.retries_spent.load(Ordering::Relaxed)
+    }
+
+    async fn get_warehouses(&mut self) -> Result<Vec<WarehouseInfo>> {
+        self.ensure_connected().await?;
+        self.list_warehouses().await
+    }
+
+    async fn get_tables_for_database(&mut self, database: &str) -> Result<Vec<TableInfo>> {
+        self.ensure_connected().await?;
+        let rows = self.fetch_columns(database)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to get tables for database {}: {}", database, e)))?;
+
+        let mut tables: Vec<TableInfo> = Vec::new();
+        let mut current_table: Option<TableInfo> = None;
+
+        for row in rows {
+            let schema_name = Self::get_value_from_row(&row, "table_schema")?;
+            let table_name = Self::get_value_from_row(&row, "table_name")?;
+
+            if current_table.as_ref().is_none_or(|t| {
+                t.schema_name != schema_name || t.table_name != table_name
+            }) {
+                if let Some(table) = current_table.take() {
+                    tables.push(table);
+                }
+                current_table = Some(TableInfo {
+                    format_version: FORMAT_VERSION,
+                    database_name: database.to_string(),
+                    schema_name,
+                    table_name,
+                    table_type: None,
+                    comment: None,
+                    columns: Vec::new(),
+                    primary_key: Vec::new(),
+                    foreign_keys: Vec::new(),
+                    clustering_key: None,
+                    automatic_clustering: None,
+                    search_optimization: None,
+                    retention_time_in_days: None,
+                    change_tracking: None,
+                    is_transient: None,
+                    is_temporary: None,
+                    created: None,
+                    last_altered: None,
+                    last_accessed: None,
+                    usage: None,
+                    owners: Vec::new(),
+                    roles: Vec::new(),
+                    custom: BTreeMap::new(),
+                    provenance: None,
+                });
+            }
+
+            if let Some(table) = current_table.as_mut() {
+                let data_type = Self::get_value_from_row(&row, "data_type")?;
+                let mut column = ColumnInfo {
+                    name: Self::get_value_from_row(&row, "column_name")?,
+                    time_zone: TimeZoneHandling::of(&data_type),
+                    data_type,
+                    snowflake_type: None,
+                    is_nullable: Self::get_value_from_row(&row, "is_nullable")?.eq_ignore_ascii_case("YES"),
+                    character_maximum_length: Self::get_i32_from_row(&row, "character_maximum_length")?,
+                    numeric_precision: Self::get_i32_from_row(&row, "numeric_precision")?,
+                    numeric_scale: Self::get_i32_from_row(&row, "numeric_scale")?,
+                    comment: Self::get_optional_value_from_row(&row, "comment")?,
+                    ordinal_position: Self::get_i32_from_row(&row, "ordinal_position")?,
+                    column_default: Self::get_optional_value_from_row(&row, "column_default")?,
+                    collation: Self::get_optional_value_from_row(&row, "collation_name")?,
+                    identity: Self::get_identity_from_row(&row)?,
+                    variant_shape: None,
+                    datetime_precision: Self::get_i32_from_row(&row, "datetime_precision")?,
+                    interval: None,
+                };
+                column.snowflake_type = Some(SnowflakeType::from_column(&column));
+                table.columns.push(column);
+            }
+        }
+
+        if let Some(table) = current_table.take() {
+            tables.push(table);
+        }
+        let schemas = tables.iter().map(|t| &t.schema_name).collect::<HashSet<_>>().len();
+        let counts = format!("{} schemas, {} tables", schemas, tables.len());
+        self.report_step(database, 1, counts.clone());
+
+        let mut details = self.get_table_details(database).await?;
+        self.report_step(database, 2, counts.clone());
+        let mut primary_keys = self.get_primary_keys(database).await?;
+        self.report_step(database, 3, counts.clone());
+        let mut foreign_keys = self.get_foreign_keys(database).await?;
+        self.report_step(database, 4, counts.clone());
+        let mut shown_tables = self.get_shown_tables(database).await?;
+        self.report_step(database, 5, counts);
+        let mut last_accessed = if self.access_history {
+            self.get_last_accessed(database).await?
+        } else {
+            HashMap::new()
+        };
+        let mut usage = match self.usage_window_days {
+            Some(days) => Some((days, self.get_table_usage(database, days).await?)),
+            None => None,
+        };
+        for table in &mut tables {
+            let key = (table.schema_name.clone(), table.table_name.clone());
+            if let Some(details) = details.remove(&key) {
+                table.is_temporary = details.table_type.as_deref().map(|t| t.contains("TEMPORARY"));
+                table.table_type = details.table_type;
+                table.comment = details.comment;
+                table.clustering_key = details.clustering_key;
+                table.automatic_clustering = details.automatic_clustering;
+                table.retention_time_in_days = details.retention_time_in_days;
+                table.is_transient = details.is_transient;
+                table.created = details.created;
+                table.last_altered = details.last_altered;
+            }
+            table.last_accessed = last_accessed.remove(&key);
+            // Tables nobody read are reported with zero counts, not left out
+            if let Some((days, usage)) = &mut usage {
+                table.usage = Some(usage.remove(&key).unwrap_or(TableUsage { window_days: *days, query_count: 0, distinct_users: 0 }));
+            }
+            if let Some(shown) = shown_tables.remove(&key) {
+                table.search_optimization = shown.search_optimization;
+                table.change_tracking = shown.change_tracking;
+            }
+            table.primary_key = primary_keys.remove(&key).unwrap_or_default();
+            table.foreign_keys = foreign_keys.remove(&key).unwrap_or_default();
+        }
+        if let Some(rows) = self.variant_sample {
+            self.infer_variant_shapes(&mut tables, rows).await;
+        }
+        if let Some(hooks) = &self.sql_hooks {
+            self.run_sql_hooks(hooks, database, &mut tables).await;
+        }
+
+        Ok(tables)
+    }
+}
+
+#[async_trait]
+impl SnowflakeOperations for SnowflakeMapper {
+    async fn set_warehouse(&mut self, warehouse: &str) -> Result<()> {
+        info!("Setting warehouse to: {}", warehouse);
+        
+        // Try to get list of warehouses first
+        info!("Listing available warehouses...");
+        let warehouses = self.list_warehouses().await?;
+        let warehouse_names: Vec<String> = warehouses.iter().map(|w| w.name.clone()).collect();
+        info!("Available warehouses: {:?}", warehouse_names);
+        
+        // Use specified warehouse or fall back to COMPUTE_WH
+        let target_warehouse = if warehouse_names.iter().any(|w| w.eq_ignore_ascii_case(warehouse)) {
+            warehouse.to_string()
+        } else {
+            warn!("Warehouse '{}' not found, falling back to COMPUTE_WH", warehouse);
+            "COMPUTE_WH".to_string()
+        };
+
+        let query = format!("USE WAREHOUSE \"{}\"", target_warehouse);
+        info!("Executing query: {}", query);
+        self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to set warehouse: {}", e)))?;
+        info!("Successfully set warehouse to: {}", target_warehouse);
+        Ok(())
+    }
+
+    async fn set_role(&mut self, role: &str) -> Result<()> {
+        info!("Setting role to: {}", role);
+        let query = format!("USE ROLE \"{}\"", role);
+        info!("Executing query: {}", query);
+        self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to set role: {}", e)))?;
+        info!("Successfully set role to: {}", role);
+        Ok(())
+    }
+
+    async fn list_warehouses(&mut self) -> Result<Vec<WarehouseInfo>> {
+        info!("Listing warehouses...");
+        let rows = self.run_query("SHOW WAREHOUSES")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list warehouses: {}", e)))?;
+
+        let mut warehouses = Vec::new();
+        for row in rows {
+            warehouses.push(WarehouseInfo {
+                format_version: FORMAT_VERSION,
+                name: Self::get_value_from_row(&row, "name")?,
+                size: Self::get_value_from_row(&row, "size")?,
+                state: Self::get_value_from_row(&row, "state")?,
+                type_: Self::get_value_from_row(&row, "type")?,
+                auto_suspend: Self::get_i32_from_row(&row, "auto_suspend")?,
+                auto_resume: Self::get_optional_value_from_row(&row, "auto_resume")?
+                    .map(|value| value.eq_ignore_ascii_case("true")),
+                min_cluster_count: Self::get_i32_from_row(&row, "min_cluster_count")?,
+                max_cluster_count: Self::get_i32_from_row(&row, "max_cluster_count")?,
+                scaling_policy: Self::get_optional_value_from_row(&row, "scaling_policy")?,
+            });
+        }
+        info!("Found {} warehouses", warehouses.len());
+        Ok(warehouses)
+    }
+
+    async fn list_schemas(&mut self, database: &str) -> Result<Vec<SchemaInfo>> {
+        let rows = self.run_query(&format!("SHOW SCHEMAS IN DATABASE {}", quote_identifier(database)))
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list schemas in {}: {}", database, e)))?;
+
+        let mut schemas = Vec::new();
+        for row in rows {
+            schemas.push(SchemaInfo {
+                name: Self::get_value_from_row(&row, "name")?,
+                database_name: Self::get_value_from_row(&row, "database_name")?,
+                created_on: Self::get_value_from_row(&row, "created_on")?,
+                owner: Self::get_value_from_row(&row, "owner")?,
+                comment: Self::get_optional_value_from_row(&row, "comment")?.filter(|c| !c.is_empty()),
+            });
+        }
+        Ok(schemas)
+    }
+
+    async fn list_roles(&mut self) -> Result<Vec<RoleInfo>> {
+        let rows = self.run_query("SHOW ROLES")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list roles: {}", e)))?;
+
+        let mut roles = Vec::new();
+        for row in rows {
+            roles.push(RoleInfo {
+                name: Self::get_value_from_row(&row, "name")?,
+                created_on: Self::get_value_from_row(&row, "created_on")?,
+                owner: Self::get_value_from_row(&row, "owner")?,
+                comment: Self::get_optional_value_from_row(&row, "comment")?.filter(|c| !c.is_empty()),
+            });
+        }
+        Ok(roles)
+    }
+
+    async fn get_account_parameters(&mut self) -> Result<Vec<AccountParameter>> {
+        let rows = self.run_query("SHOW PARAMETERS IN ACCOUNT")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to show account parameters: {}", e)))?;
+
+        let mut parameters = Vec::new();
+        for row in rows {
+            parameters.push(AccountParameter {
+                key: Self::get_value_from_row(&row, "key")?,
+                value: Self::get_optional_value_from_row(&row, "value")?.unwrap_or_default(),
+                default: Self::get_optional_value_from_row(&row, "default")?.unwrap_or_default(),
+                level: Self::get_optional_value_from_row(&row, "level")?.unwrap_or_default(),
+                description: Self::get_optional_value_from_row(&row, "description")?.filter(|d| !d.is_empty()),
+            });
+        }
+        Ok(parameters)
+    }
+
+    async fn list_resource_monitors(&mut self) -> Result<Vec<ResourceMonitor>> {
+        let rows = self.run_query("SHOW RESOURCE MONITORS")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list resource monitors: {}", e)))?;
+
+        let optional = |row: &QueryRow, column: &str| {
+            Self::get_optional_value_from_row(row, column).map(|value| value.filter(|v| !v.is_empty()))
+        };
+        let mut monitors = Vec::new();
+        for row in rows {
+            monitors.push(ResourceMonitor {
+                name: Self::get_value_from_row(&row, "name")?,
+                credit_quota: optional(&row, "credit_quota")?,
+                used_credits: optional(&row, "used_credits")?,
+                remaining_credits: optional(&row, "remaining_credits")?,
+                level: optional(&row, "level")?,
+                frequency: optional(&row, "frequency")?,
+                start_time: optional(&row, "start_time")?,
+                end_time: optional(&row, "end_time")?,
+                notify_at: optional(&row, "notify_at")?,
+                suspend_at: optional(&row, "suspend_at")?,
+                suspend_immediately_at: optional(&row, "suspend_immediately_at")?,
+                owner: optional(&row, "owner")?,
+                comment: optional(&row, "comment")?,
+            });
+        }
+        Ok(monitors)
+    }
+
+    async fn list_users(&mut self, source: UserSource) -> Result<Vec<UserInfo>> {
+        let query = match source {
+            UserSource::Show => "SHOW USERS",
+            UserSource::AccountUsage => {
+                "SELECT name, login_name, display_name, email, type, disabled, default_role, default_warehouse,
+                 has_password, has_rsa_public_key, created_on, last_success_login, owner, comment
+                 FROM snowflake.account_usage.users
+                 WHERE deleted_on IS NULL
+                 ORDER BY name"
+            }
+        };
+        let rows = self.run_query(query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list users: {}", e)))?;
+
+        let flag = |row: &QueryRow, column: &str| {
+            Self::get_optional_value_from_row(row, column).map(|value| value.is_some_and(|v| v.eq_ignore_ascii_case("true")))
+        };
+        let mut users = Vec::new();
+        for row in rows {
+            users.push(UserInfo {
+                name: Self::get_value_from_row(&row, "name")?,
+                login_name: Self::get_optional_value_from_row(&row, "login_name")?,
+                display_name: Self::get_optional_value_from_row(&row, "display_name")?,
+                email: Self::get_optional_value_from_row(&row, "email")?,
+                // Accounts without user types don't report the column
+                type_: Self::get_optional_value_from_row(&row, "type").unwrap_or(None),
+                disabled: flag(&row, "disabled")?,
+                default_role: Self::get_optional_value_from_row(&row, "default_role")?,
+                default_warehouse: Self::get_optional_value_from_row(&row, "default_warehouse")?,
+                has_password: flag(&row, "has_password")?,
+                has_rsa_public_key: flag(&row, "has_rsa_public_key")?,
+                created_on: Self::get_optional_value_from_row(&row, "created_on")?,
+                last_success_login: Self::get_optional_value_from_row(&row, "last_success_login")?,
+                owner: Self::get_optional_value_from_row(&row, "owner")?,
+                comment: Self::get_optional_value_from_row(&row, "comment")?,
+            });
+        }
+        Ok(users)
+    }
+
+    /// Shares without their objects; see [`describe_share`](Self::describe_share)
+    async fn list_shares(&mut self) -> Result<Vec<ShareInfo>> {
+        let rows = self.run_query("SHOW SHARES")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list shares: {}", e)))?;
+
+        let mut shares = Vec::new();
+        for row in rows {
+            shares.push(ShareInfo {
+                name: Self::get_value_from_row(&row, "name")?,
+                kind: Self::get_value_from_row(&row, "kind")?,
+                database_name: Self::get_optional_value_from_row(&row, "database_name")?,
+                consumers: Self::get_list_from_row(&row, "to")?,
+                // Only reported by accounts with listings enabled
+                listing: Self::get_optional_value_from_row(&row, "listing_global_name").unwrap_or(None),
+                created_on: Self::get_optional_value_from_row(&row, "created_on")?,
+                owner: Self::get_optional_value_from_row(&row, "owner")?,
+                comment: Self::get_optional_value_from_row(&row, "comment")?,
+                objects: Vec::new(),
+            });
+        }
+        Ok(shares)
+    }
+
+    async fn describe_share(&mut self, share: &str) -> Result<Vec<SharedObject>> {
+        let rows = self.run_query(&format!("DESCRIBE SHARE {}", share))
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to describe share {}: {}", share, e)))?;
+
+        let mut objects = Vec::new();
+        for row in rows {
+            objects.push(SharedObject {
+                kind: Self::get_value_from_row(&row, "kind")?,
+                name: Self::get_value_from_row(&row, "name")?,
+                shared_on: Self::get_optional_value_from_row(&row, "shared_on")?,
+            });
+        }
+        Ok(objects)
+    }
+
+    /// Groups without their databases; see
+    /// [`list_replication_group_databases`](Self::list_replication_group_databases)
+    async fn list_replication_groups(&mut self) -> Result<Vec<ReplicationGroup>> {
+        let rows = self.run_query("SHOW REPLICATION GROUPS")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list replication groups: {}", e)))?;
+
+        let mut groups = Vec::new();
+        for row in rows {
+            groups.push(ReplicationGroup {
+                name: Self::get_value_from_row(&row, "name")?,
+                type_: Self::get_value_from_row(&row, "type")?,
+                account: format!(
+                    "{}.{}",
+                    Self::get_value_from_row(&row, "organization_name")?,
+                    Self::get_value_from_row(&row, "account_name")?
+                ),
+                is_primary: Self::get_bool_from_row(&row, "is_primary")?,
+                primary: Self::get_optional_value_from_row(&row, "primary")?,
+                object_types: Self::get_list_from_row(&row, "object_types")?,
+                allowed_accounts: Self::get_list_from_row(&row, "allowed_accounts")?,
+                replication_schedule: Self::get_optional_value_from_row(&row, "replication_schedule")?,
+                secondary_state: Self::get_optional_value_from_row(&row, "secondary_state")?,
+                next_scheduled_refresh: Self::get_optional_value_from_row(&row, "next_scheduled_refresh")?,
+                owner: Self::get_optional_value_from_row(&row, "owner")?,
+                comment: Self::get_optional_value_from_row(&row, "comment")?,
+                databases: Vec::new(),
+            });
+        }
+        Ok(groups)
+    }
+
+    async fn list_replication_group_databases(&mut self, group: &str) -> Result<Vec<String>> {
+        let rows = self.run_query(&format!("SHOW DATABASES IN REPLICATION GROUP \"{}\"", group))
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list databases in {}: {}", group, e)))?;
+        rows.iter().map(|row| Self::get_value_from_row(row, "name")).collect()
+    }
+
+    async fn list_replication_databases(&mut self) -> Result<Vec<DatabaseReplication>> {
+        let rows = self.run_query("SHOW REPLICATION DATABASES")
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list replication databases: {}", e)))?;
+
+        let mut databases = Vec::new();
+        for row in rows {
+            databases.push(DatabaseReplication {
+                name: Self::get_value_from_row(&row, "name")?,
+                account: format!(
+                    "{}.{}",
+                    Self::get_value_from_row(&row, "organization_name")?,
+                    Self::get_value_from_row(&row, "account_name")?
+                ),
+                region: Self::get_optional_value_from_row(&row, "snowflake_region")?,
+                is_primary: Self::get_bool_from_row(&row, "is_primary")?,
+                primary: Self::get_optional_value_from_row(&row, "primary")?,
+                replication_allowed_to: Self::get_list_from_row(&row, "replication_allowed_to_accounts")?,
+                failover_allowed_to: Self::get_list_from_row(&row, "failover_allowed_to_accounts")?,
+                created_on: Self::get_optional_value_from_row(&row, "created_on")?,
+                comment: Self::get_optional_value_from_row(&row, "comment")?,
+            });
+        }
+        Ok(databases)
+    }
+
+    async fn list_account_usage_objects(&mut self) -> Result<Vec<AccountObject>> {
+        // Temporary tables belong to other sessions and never show up in information_schema
+        let query = "SELECT database_name, NULL AS schema_name, NULL AS table_name, database_owner AS owner
+             FROM snowflake.account_usage.databases WHERE deleted IS NULL
+             UNION ALL
+             SELECT catalog_name, schema_name, NULL, schema_owner
+             FROM snowflake.account_usage.schemata WHERE deleted IS NULL AND schema_name <> 'INFORMATION_SCHEMA'
+             UNION ALL
+             SELECT table_catalog, table_schema, table_name, table_owner
+             FROM snowflake.account_usage.tables
+             WHERE deleted IS NULL AND table_schema <> 'INFORMATION_SCHEMA' AND table_type <> 'TEMPORARY TABLE'";
+        let rows = self.run_query(query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list objects in ACCOUNT_USAGE: {}", e)))?;
+        rows.iter().map(Self::account_object_from_row).collect()
+    }
+
+    async fn list_visible_objects(&mut self, database: &str) -> Result<Vec<AccountObject>> {
+        let query = format!(
+            "SELECT catalog_name AS database_name, schema_name, NULL AS table_name, schema_owner AS owner
+             FROM {db}.information_schema.schemata WHERE schema_name <> 'INFORMATION_SCHEMA'
+             UNION ALL
+             SELECT table_catalog, table_schema, table_name, table_owner
+             FROM {db}.information_schema.tables
+             WHERE table_schema <> 'INFORMATION_SCHEMA' AND table_type <> 'TEMPORARY TABLE'",
+            db = database
+        );
+        let rows = self.run_query(&query)
+            .await
+            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list objects in {}: {}", database, e)))?;
+        rows.iter().map(Self::account_object_from_row).collect()
+    }
+}
+
+/// Split `counts` into runs, in order, whose counts add up to at most `limit`; an entry over
+/// the limit gets a run of its own
+fn pack(counts: Vec<(String, usize)>, limit: usize) -> Vec<Vec<(String, usize)>> {
+    let mut runs: Vec<Vec<(String, usize)>> = Vec::new();
+    let mut size = 0;
+    for (name, count) in counts {
+        match runs.last_mut() {
+            Some(run) if size + count <= limit => run.push((name, count)),
+            _ => {
+                size = 0;
+                runs.push(vec![(name, count)]);
+            }
+        }
+        size += count;
+    }
+    runs
+}
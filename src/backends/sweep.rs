@@ -0,0 +1,137 @@
+//! Mapping under several roles, for accounts where no single role can see every database.
+//!
+//! Each role has a connection of its own. Databases are mapped by every role that lists
+//! them, and the tables they see are merged, recording in `roles` which roles saw each one.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::backends::WarehouseMapper;
+use crate::progress::ProgressListener;
+use crate::{DatabaseInfo, SnowflakeMapperError, TableInfo, WarehouseInfo};
+
+pub struct RoleSweep {
+    /// A client connecting as each role, in `--roles` order
+    members: Vec<(String, Box<dyn WarehouseMapper>)>,
+    /// Indexes of the members that list each database, once listed
+    visible: Option<HashMap<String, Vec<usize>>>,
+    /// Retries spent on the database being mapped, carried from one role to the next
+    retries_spent: u32,
+}
+
+impl RoleSweep {
+    pub fn new(members: Vec<(String, Box<dyn WarehouseMapper>)>) -> Self {
+        Self { members, visible: None, retries_spent: 0 }
+    }
+
+    /// Databases listed by any role, in the order they were first listed
+    async fn list_databases(&mut self) -> Result<Vec<DatabaseInfo>> {
+        let mut databases: Vec<DatabaseInfo> = Vec::new();
+        let mut visible: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, (_, member)) in self.members.iter_mut().enumerate() {
+            for database in member.get_all_databases().await? {
+                let roles = visible.entry(database.name.clone()).or_default();
+                if roles.is_empty() {
+                    databases.push(database);
+                }
+                roles.push(index);
+            }
+        }
+        for database in &databases {
+            let roles: Vec<&str> = visible[&database.name].iter().map(|&index| self.members[index].0.as_str()).collect();
+            if roles.len() < self.members.len() {
+                info!("Database {} is only visible to {}", database.name, roles.join(", "));
+            }
+        }
+        self.visible = Some(visible);
+        Ok(databases)
+    }
+}
+
+#[async_trait]
+impl WarehouseMapper for RoleSweep {
+    async fn connect(&mut self) -> Result<()> {
+        for (role, member) in &mut self.members {
+            member
+                .connect()
+                .await
+                .map_err(|e| SnowflakeMapperError::ConnectionError(format!("Connecting as role {} failed: {}", role, e)))?;
+        }
+        Ok(())
+    }
+
+    async fn get_all_databases(&mut self) -> Result<Vec<DatabaseInfo>> {
+        self.list_databases().await
+    }
+
+    async fn get_tables_for_database(&mut self, database: &str) -> Result<Vec<TableInfo>> {
+        // Workers other than the first, and runs given `--databases`, haven't listed yet
+        if self.visible.is_none() {
+            self.list_databases().await?;
+        }
+        let roles = self.visible.as_ref().and_then(|visible| visible.get(database)).cloned().unwrap_or_default();
+        if roles.is_empty() {
+            return Err(SnowflakeMapperError::QueryError(format!("No role in --roles can see database {}", database)).into());
+        }
+
+        // A role that fails is skipped as long as another one maps the database
+        let mut tables: Vec<TableInfo> = Vec::new();
+        let mut failed = 0;
+        let attempted = roles.len();
+        for index in roles {
+            let (role, member) = &mut self.members[index];
+            member.set_retries_spent(self.retries_spent);
+            let fetched = member.get_tables_for_database(database).await;
+            self.retries_spent = self.retries_spent.max(member.retries_spent());
+            let fetched = match fetched {
+                Ok(fetched) => fetched,
+                // The last role's error is kept, with its kind, for when every role fails
+                Err(e) if failed + 1 == attempted => {
+                    return Err(e.context(format!("Every role in --roles failed to map database {}", database)));
+                }
+                Err(e) => {
+                    warn!("Mapping {} as role {} failed; skipping the role: {:#}", database, role, e);
+                    failed += 1;
+                    continue;
+                }
+            };
+            for table in fetched {
+                match tables.iter_mut().find(|t| t.schema_name == table.schema_name && t.table_name == table.table_name) {
+                    Some(seen) => seen.roles.push(role.clone()),
+                    None => tables.push(TableInfo { roles: vec![role.clone()], ..table }),
+                }
+            }
+        }
+        Ok(tables)
+    }
+
+    fn set_progress_listener(&mut self, listener: Arc<dyn ProgressListener>) {
+        for (_, member) in &mut self.members {
+            member.set_progress_listener(Arc::clone(&listener));
+        }
+    }
+
+    fn set_retries_spent(&mut self, retries: u32) {
+        self.retries_spent = retries;
+    }
+
+    fn retries_spent(&self) -> u32 {
+        self.retries_spent
+    }
+
+    /// Warehouses visible to any role
+    async fn get_warehouses(&mut self) -> Result<Vec<WarehouseInfo>> {
+        let mut warehouses: Vec<WarehouseInfo> = Vec::new();
+        for (_, member) in &mut self.members {
+            for warehouse in member.get_warehouses().await? {
+                if !warehouses.iter().any(|w| w.name == warehouse.name) {
+                    warehouses.push(warehouse);
+                }
+            }
+        }
+        Ok(warehouses)
+    }
+}
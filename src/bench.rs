@@ -0,0 +1,75 @@
+//! `bench`: how long each phase of a map run takes under different `--jobs` and
+//! `--shard-columns` settings, to pick them for an account.
+
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::output;
+
+/// Timings of one map run, in milliseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchRun {
+    pub jobs: u32,
+    pub shard_columns: Option<u64>,
+    pub databases: usize,
+    pub tables: usize,
+    pub queries: usize,
+    /// Opening a connection per worker
+    pub connect_ms: f64,
+    /// `SHOW DATABASES`
+    pub list_databases_ms: f64,
+    /// Fetching every database, workers running in parallel
+    pub fetch_ms: f64,
+    /// Fetching a single database, on average and at the slowest
+    pub mean_database_ms: f64,
+    pub slowest_database: Option<String>,
+    pub slowest_database_ms: f64,
+    /// Serializing the catalog files, and writing them
+    pub serialize_ms: f64,
+    pub write_ms: f64,
+    pub total_ms: f64,
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// One row per run, with the fastest marked
+pub fn render_text(runs: &[BenchRun]) -> String {
+    let fastest = runs.iter().map(|run| run.total_ms).fold(f64::INFINITY, f64::min);
+    let rows: Vec<[String; 11]> = runs
+        .iter()
+        .map(|run| {
+            [
+                run.jobs.to_string(),
+                run.shard_columns.map_or("-".to_string(), |columns| columns.to_string()),
+                format_ms(run.connect_ms),
+                format_ms(run.list_databases_ms),
+                format_ms(run.fetch_ms),
+                format_ms(run.mean_database_ms),
+                match &run.slowest_database {
+                    Some(database) => format!("{} ({})", format_ms(run.slowest_database_ms), database),
+                    None => "-".to_string(),
+                },
+                format_ms(run.serialize_ms),
+                format_ms(run.write_ms),
+                if run.total_ms == fastest && runs.len() > 1 { format!("{} *", format_ms(run.total_ms)) } else { format_ms(run.total_ms) },
+                run.queries.to_string(),
+            ]
+        })
+        .collect();
+    output::render_table(
+        ["JOBS", "SHARD COLUMNS", "CONNECT", "SHOW DATABASES", "FETCH", "PER DATABASE", "SLOWEST", "SERIALIZE", "WRITE", "TOTAL", "QUERIES"],
+        &rows,
+    )
+}
+
+fn format_ms(ms: f64) -> String {
+    if ms >= 1000.0 {
+        format!("{:.2}s", ms / 1000.0)
+    } else if ms >= 10.0 {
+        format!("{:.0}ms", ms)
+    } else {
+        format!("{:.1}ms", ms)
+    }
+}
@@ -0,0 +1,318 @@
+//! Programmatic construction of a Snowflake mapper for library users.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::backends::fixture::FixtureStore;
+use crate::backends::pool::{QueryAccounting, SessionPool};
+use crate::backends::snowflake::{FixtureMode, SnowflakeConfig, SnowflakeMapper};
+use crate::backends::WarehouseMapper;
+use crate::guardrails::QueryBudget;
+use crate::hooks::SqlHooks;
+use crate::querylog::QueryLog;
+use crate::retry::RetryPolicy;
+use crate::throttle::RateLimiter;
+use crate::SnowflakeMapperError;
+
+/// Role used when SNOWFLAKE_ROLE isn't set
+pub const DEFAULT_ROLE: &str = "SALES";
+
+/// Builds a [`SnowflakeMapper`] without going through the CLI.
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use snowflake_mapper::{SnowflakeMapperBuilder, WarehouseMapper};
+///
+/// let mut mapper = SnowflakeMapperBuilder::new()
+///     .account("xy12345.eu-west-1")
+///     .username("MAPPER")
+///     .password("secret")
+///     .warehouse("XS_WH")
+///     .connect()
+///     .await?;
+/// let tables = mapper.map_database("SALES").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SnowflakeMapperBuilder {
+    account: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    warehouse: Option<String>,
+    database: Option<String>,
+    role: Option<String>,
+    mfa_timeout: Option<Duration>,
+    mfa_passcode: Option<String>,
+    okta_url: Option<String>,
+    host: Option<String>,
+    keep_alive: Option<Duration>,
+    retry_policy: RetryPolicy,
+    record_fixtures: Option<PathBuf>,
+    access_history: bool,
+    variant_sample: Option<u32>,
+    usage_window_days: Option<u32>,
+    sql_hooks: Option<Arc<SqlHooks>>,
+    shard_columns: Option<usize>,
+    budget: Option<Arc<QueryBudget>>,
+    metadata_warehouse: Option<String>,
+    allow_writes: bool,
+    query_log: Option<Arc<QueryLog>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    session_pool: Option<Arc<SessionPool>>,
+}
+
+impl SnowflakeMapperBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from the `SNOWFLAKE_*` environment variables the CLI uses
+    pub fn from_env() -> Result<Self> {
+        let required = |name: &str| {
+            std::env::var(name).map_err(|_| SnowflakeMapperError::MissingEnvVar(name.to_string()))
+        };
+        Ok(Self {
+            account: Some(required("SNOWFLAKE_ACCOUNT")?),
+            username: Some(required("SNOWFLAKE_USERNAME")?),
+            password: Some(required("SNOWFLAKE_PASSWORD")?),
+            warehouse: Some(required("SNOWFLAKE_WAREHOUSE")?),
+            database: std::env::var("SNOWFLAKE_DATABASE").ok(),
+            role: Some(std::env::var("SNOWFLAKE_ROLE").unwrap_or_else(|_| DEFAULT_ROLE.to_string())),
+            host: std::env::var("SNOWFLAKE_HOST").ok(),
+            mfa_passcode: std::env::var("SNOWFLAKE_MFA_PASSCODE").ok(),
+            ..Self::default()
+        })
+    }
+
+    pub fn account(mut self, account: impl Into<String>) -> Self {
+        self.account = Some(account.into());
+        self
+    }
+
+    pub fn username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn warehouse(mut self, warehouse: impl Into<String>) -> Self {
+        self.warehouse = Some(warehouse.into());
+        self
+    }
+
+    /// Run the mapper's queries on this warehouse instead of `warehouse`
+    pub fn metadata_warehouse(mut self, warehouse: impl Into<String>) -> Self {
+        self.metadata_warehouse = Some(warehouse.into());
+        self
+    }
+
+    /// Default database for the session
+    pub fn database(mut self, database: impl Into<String>) -> Self {
+        self.database = Some(database.into());
+        self
+    }
+
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    /// Connect to `host`, e.g. `xy12345.us-east-1.privatelink.snowflakecomputing.com`, rather
+    /// than the host derived from the account identifier
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retry_policy.max_retries = retries;
+        self
+    }
+
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_policy.delay = delay;
+        self
+    }
+
+    /// Which failures to retry and how; replaces `retries` and `retry_delay`
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Fill `last_accessed` from ACCOUNT_USAGE.ACCESS_HISTORY
+    pub fn access_history(mut self, enabled: bool) -> Self {
+        self.access_history = enabled;
+        self
+    }
+
+    /// Infer the structure of VARIANT / OBJECT / ARRAY columns from a sample of this many rows per table
+    pub fn infer_variant(mut self, sample_rows: u32) -> Self {
+        self.variant_sample = Some(sample_rows);
+        self
+    }
+
+    /// Fill `usage` with the queries and users reading each table over the last `days` days
+    pub fn include_usage(mut self, days: u32) -> Self {
+        self.usage_window_days = Some(days);
+        self
+    }
+
+    /// Fetch the columns of databases with more than `columns` columns in chunks, by schema
+    /// or by table-name range, instead of in one query
+    pub fn shard_columns(mut self, columns: usize) -> Self {
+        self.shard_columns = Some(columns);
+        self
+    }
+
+    /// Run user SQL for every database and table and attach the results under `custom`
+    pub fn sql_hooks(mut self, hooks: Arc<SqlHooks>) -> Self {
+        self.sql_hooks = Some(hooks);
+        self
+    }
+
+    /// Limit the queries and runtime of the mapper; share one budget across mappers to limit a whole run
+    pub fn query_budget(mut self, budget: Arc<QueryBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Wait up to `timeout` for the user to approve a Duo push on each login, for users with
+    /// mandatory MFA
+    pub fn mfa_timeout(mut self, timeout: Duration) -> Self {
+        self.mfa_timeout = Some(timeout);
+        self
+    }
+
+    /// Log in with a Duo `passcode` rather than a push, for users with mandatory MFA. A
+    /// passcode works once, so every session of the mapper shares that one login.
+    pub fn mfa_passcode(mut self, passcode: impl Into<String>) -> Self {
+        self.mfa_passcode = Some(passcode.into());
+        self
+    }
+
+    /// Log in through Okta at `url`, e.g. `https://company.okta.com`, which checks the
+    /// username and password instead of Snowflake
+    pub fn okta(mut self, url: impl Into<String>) -> Self {
+        self.okta_url = Some(url.into());
+        self
+    }
+
+    /// Ping idle sessions every `interval` so long runs don't lose them to Snowflake's idle
+    /// session timeout
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Read-only enforcement is on unless this is set to `false`
+    pub fn enforce_readonly(mut self, enforce: bool) -> Self {
+        self.allow_writes = !enforce;
+        self
+    }
+
+    /// Append every executed query to `log`
+    pub fn query_log(mut self, log: Arc<QueryLog>) -> Self {
+        self.query_log = Some(log);
+        self
+    }
+
+    /// Throttle queries; share one limiter across mappers to throttle a whole run
+    pub fn rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Query through a pool shared with other mappers, see [`build_session_pool`](Self::build_session_pool)
+    pub fn session_pool(mut self, pool: Arc<SessionPool>) -> Self {
+        self.session_pool = Some(pool);
+        self
+    }
+
+    /// A pool of up to `max_sessions` sessions for this configuration, for mappers that map in
+    /// parallel to share
+    pub fn build_session_pool(&self, max_sessions: usize) -> Result<Arc<SessionPool>> {
+        let config = self.clone().config()?;
+        let warehouse = self.metadata_warehouse.as_deref().unwrap_or(&config.warehouse);
+        let pool = Arc::new(SessionPool::new(&config, warehouse, max_sessions)?);
+        if let Some(interval) = config.keep_alive {
+            pool.keep_alive(
+                interval,
+                QueryAccounting {
+                    budget: self.budget.clone(),
+                    rate_limiter: self.rate_limiter.clone(),
+                    query_log: self.query_log.clone(),
+                },
+            );
+        }
+        Ok(pool)
+    }
+
+    /// Save every query result to `dir` so the run can be replayed offline later
+    pub fn record_fixtures(mut self, dir: &Path) -> Self {
+        self.record_fixtures = Some(dir.to_path_buf());
+        self
+    }
+
+    fn config(self) -> Result<SnowflakeConfig> {
+        let required = |value: Option<String>, name: &str| {
+            value.ok_or_else(|| SnowflakeMapperError::MissingConfig(name.to_string()))
+        };
+        Ok(SnowflakeConfig {
+            account: required(self.account, "account")?,
+            username: required(self.username, "username")?,
+            password: required(self.password, "password")?,
+            warehouse: required(self.warehouse, "warehouse")?,
+            database: self.database,
+            role: self.role,
+            mfa_timeout: self.mfa_timeout,
+            mfa_passcode: self.mfa_passcode,
+            okta_url: self.okta_url,
+            host: self.host,
+            keep_alive: self.keep_alive,
+        })
+    }
+
+    /// Build the mapper without connecting
+    pub fn build(self) -> Result<SnowflakeMapper> {
+        let mut mapper = SnowflakeMapper::new(self.clone().config()?)
+            .with_retry_policy(self.retry_policy)
+            .with_access_history(self.access_history)
+            .with_variant_sample(self.variant_sample)
+            .with_usage_window(self.usage_window_days)
+            .with_sql_hooks(self.sql_hooks)
+            .with_shard_columns(self.shard_columns)
+            .with_metadata_warehouse(self.metadata_warehouse)
+            .with_enforce_readonly(!self.allow_writes);
+        if let Some(log) = self.query_log {
+            mapper = mapper.with_query_log(log);
+        }
+        if let Some(limiter) = self.rate_limiter {
+            mapper = mapper.with_rate_limiter(limiter);
+        }
+        if let Some(budget) = self.budget {
+            mapper = mapper.with_query_budget(budget);
+        }
+        if let Some(dir) = self.record_fixtures {
+            mapper = mapper.with_fixtures(FixtureMode::Record(FixtureStore::new(&dir)));
+        }
+        if let Some(pool) = self.session_pool {
+            mapper = mapper.with_session_pool(pool);
+        }
+        Ok(mapper)
+    }
+
+    /// Build the mapper and open its session
+    pub async fn connect(self) -> Result<SnowflakeMapper> {
+        let mut mapper = self.build()?;
+        mapper.connect().await?;
+        Ok(mapper)
+    }
+}
@@ -0,0 +1,121 @@
+//! Loading catalogs written by previous runs.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::account::ACCOUNT_FILE;
+use crate::failures::FAILURES_FILE;
+use crate::formats::topo::LOAD_ORDER_FILE;
+use crate::replication::REPLICATION_FILE;
+use crate::shares::SHARES_FILE;
+use crate::upgrade;
+use crate::users::USERS_FILE;
+use crate::{SnowflakeMapperError, TableInfo, WarehouseInfo};
+
+/// Warehouse inventory written by each mapping run
+pub const WAREHOUSES_FILE: &str = "warehouses.json";
+
+/// Files written next to the per-database catalogs that don't hold tables
+const ARTIFACT_FILES: &[&str] = &[FAILURES_FILE, ACCOUNT_FILE, WAREHOUSES_FILE, USERS_FILE, SHARES_FILE, REPLICATION_FILE, LOAD_ORDER_FILE];
+
+/// Load every table from a snapshot.
+///
+/// `path` may be an output directory (all `*.json` / `*.ndjson` files are read, in path
+/// order, including those in subdirectories written with `--path-template`) or a single
+/// file, such as a merged catalog captured from stdout. Records written by older releases
+/// are upgraded to the current format as they load. A table found in two files of a
+/// directory is an error, since the directory then holds several snapshots.
+pub fn load_snapshot(path: &Path) -> Result<Vec<TableInfo>> {
+    let files = if path.is_dir() {
+        snapshot_files(path)?.into_iter().filter(|file| !is_artifact_file(file)).collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut tables = Vec::new();
+    let mut seen: HashMap<(String, String, String), PathBuf> = HashMap::new();
+    for file in files {
+        for table in load_snapshot_file(&file)? {
+            let key = (table.database_name.clone(), table.schema_name.clone(), table.table_name.clone());
+            if let Some(first) = seen.get(&key).filter(|first| **first != file) {
+                return Err(SnowflakeMapperError::SnapshotError(format!(
+                    "{}.{}.{} is in both {} and {}; a directory holding several snapshots, such as the per-role \
+                     directories of a --roles run, has to be read one snapshot at a time",
+                    key.0,
+                    key.1,
+                    key.2,
+                    first.display(),
+                    file.display()
+                ))
+                .into());
+            }
+            seen.insert(key, file.clone());
+            tables.push(table);
+        }
+    }
+    Ok(tables)
+}
+
+/// Every `*.json` / `*.ndjson` file in a snapshot directory and its subdirectories, catalog
+/// and account-level alike, in path order
+pub fn snapshot_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Failed to read {}: {}", dir.display(), e)))?;
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.is_dir() {
+                pending.push(path);
+            } else if matches!(path.extension().and_then(|ext| ext.to_str()), Some("json" | "ndjson")) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `path` is one of the files written next to the catalog that don't hold tables
+pub fn is_artifact_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| ARTIFACT_FILES.iter().any(|artifact| name == *artifact))
+}
+
+fn load_snapshot_file(path: &Path) -> Result<Vec<TableInfo>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Failed to read {}: {}", path.display(), e)))?;
+    let parse_error = |e: serde_json::Error| SnowflakeMapperError::SnapshotError(format!("Failed to parse {}: {}", path.display(), e));
+
+    let records: Vec<Value> = if path.extension().is_some_and(|ext| ext == "ndjson") {
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| parse_error(e).into()))
+            .collect::<Result<_>>()?
+    } else {
+        serde_json::from_str(&contents).map_err(parse_error)?
+    };
+    records
+        .into_iter()
+        .map(|record| Ok(serde_json::from_value(upgrade::upgrade_record(record, path)?).map_err(parse_error)?))
+        .collect()
+}
+
+/// The warehouse inventory of a snapshot directory, if that run wrote one
+pub fn load_warehouses(path: &Path) -> Result<Option<Vec<WarehouseInfo>>> {
+    let file = path.join(WAREHOUSES_FILE);
+    if !file.is_file() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&file)
+        .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Failed to read {}: {}", file.display(), e)))?;
+    let parse_error = |e: serde_json::Error| SnowflakeMapperError::SnapshotError(format!("Failed to parse {}: {}", file.display(), e));
+    let records: Vec<Value> = serde_json::from_str(&contents).map_err(parse_error)?;
+    let warehouses = records
+        .into_iter()
+        .map(|record| Ok(serde_json::from_value(upgrade::upgrade_record(record, &file)?).map_err(parse_error)?))
+        .collect::<Result<_>>()?;
+    Ok(Some(warehouses))
+}
@@ -0,0 +1,201 @@
+//! Human-readable changelog of schema evolution, for posting to stakeholders after a release.
+//!
+//! Builds on [`crate::diff`]: each change becomes one sentence, grouped under a heading per
+//! database and schema, with warehouse changes in their own group at the end.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::diff::SchemaChange;
+use crate::{ColumnInfo, TableInfo, WarehouseInfo};
+
+/// Group heading for warehouse changes, which belong to no schema
+const WAREHOUSES_GROUP: &str = "Warehouses";
+
+/// One Markdown section for the changes between two snapshots: a `##` heading, a one-line
+/// summary, then a `###` heading and bullet list per schema
+pub fn render_release(title: &str, changes: &[SchemaChange]) -> String {
+    let mut out = format!("## {}\n\n", title);
+    if changes.is_empty() {
+        out.push_str("No schema changes.\n");
+        return out;
+    }
+    let _ = writeln!(out, "{}\n", summary(changes));
+
+    let mut schemas: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut warehouses = Vec::new();
+    for change in changes {
+        match group(change) {
+            Some(group) => schemas.entry(group).or_default().push(bullet(change)),
+            None => warehouses.push(bullet(change)),
+        }
+    }
+    let groups = schemas.into_iter().chain((!warehouses.is_empty()).then(|| (WAREHOUSES_GROUP.to_string(), warehouses)));
+    for (heading, sentences) in groups {
+        let _ = writeln!(out, "### {}\n", heading);
+        for sentence in sentences {
+            let _ = writeln!(out, "- {}", sentence);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `DATABASE.SCHEMA` for schema changes, `None` for warehouse changes
+fn group(change: &SchemaChange) -> Option<String> {
+    change.table().map(|(database, schema, _)| format!("{}.{}", database, schema))
+}
+
+/// Counts per kind of change, e.g. `2 tables added, 1 column dropped.`
+fn summary(changes: &[SchemaChange]) -> String {
+    let count = |matches: fn(&SchemaChange) -> bool| changes.iter().filter(|c| matches(c)).count();
+    let counts = [
+        (count(|c| matches!(c, SchemaChange::TableAdded { .. })), "table", "added"),
+        (count(|c| matches!(c, SchemaChange::TableRemoved { .. })), "table", "dropped"),
+        (count(|c| matches!(c, SchemaChange::ColumnAdded { .. })), "column", "added"),
+        (count(|c| matches!(c, SchemaChange::ColumnRemoved { .. })), "column", "dropped"),
+        (count(|c| matches!(c, SchemaChange::ColumnChanged { .. })), "column", "changed"),
+        (
+            count(|c| {
+                matches!(
+                    c,
+                    SchemaChange::WarehouseAdded { .. } | SchemaChange::WarehouseRemoved { .. } | SchemaChange::WarehouseChanged { .. }
+                )
+            }),
+            "warehouse",
+            "changed",
+        ),
+    ];
+    let parts: Vec<String> = counts
+        .iter()
+        .filter(|(n, _, _)| *n > 0)
+        .map(|(n, noun, verb)| format!("{} {}{} {}", n, noun, if *n == 1 { "" } else { "s" }, verb))
+        .collect();
+    format!("{}.", parts.join(", "))
+}
+
+/// The sentence for `change`, flagged in bold when it is breaking
+fn bullet(change: &SchemaChange) -> String {
+    if change.is_breaking() {
+        format!("**Breaking:** {}", sentence(change))
+    } else {
+        sentence(change)
+    }
+}
+
+fn sentence(change: &SchemaChange) -> String {
+    match change {
+        SchemaChange::TableAdded { table } => {
+            let columns = table.columns.len();
+            format!(
+                "Added {} `{}` ({} column{})",
+                kind(table),
+                table.qualified_name(),
+                columns,
+                if columns == 1 { "" } else { "s" }
+            )
+        }
+        SchemaChange::TableRemoved { table } => format!("Dropped {} `{}`", kind(table), table.qualified_name()),
+        SchemaChange::ColumnAdded { database, schema, table, column } => {
+            format!("Added column `{}` to `{}.{}.{}` ({})", column.name, database, schema, table, describe(column))
+        }
+        SchemaChange::ColumnRemoved { database, schema, table, column } => {
+            format!("Dropped column `{}` from `{}.{}.{}`", column.name, database, schema, table)
+        }
+        SchemaChange::ColumnChanged { database, schema, table, before, after } => format!(
+            "Changed column `{}` on `{}.{}.{}`: {}",
+            after.name,
+            database,
+            schema,
+            table,
+            column_changes(before, after).join(", ")
+        ),
+        SchemaChange::WarehouseAdded { warehouse } => {
+            format!("Added warehouse `{}` ({} {})", warehouse.name, warehouse.size, warehouse.type_)
+        }
+        SchemaChange::WarehouseRemoved { warehouse } => format!("Dropped warehouse `{}`", warehouse.name),
+        SchemaChange::WarehouseChanged { before, after } => {
+            format!("Reconfigured warehouse `{}`: {}", after.name, warehouse_changes(before, after).join(", "))
+        }
+    }
+}
+
+/// `view` for any kind of view, `table` otherwise
+fn kind(table: &TableInfo) -> &'static str {
+    match &table.table_type {
+        Some(table_type) if table_type.to_uppercase().contains("VIEW") => "view",
+        _ => "table",
+    }
+}
+
+/// e.g. `VARCHAR(255), nullable, default 'n/a'`
+fn describe(column: &ColumnInfo) -> String {
+    let mut description = format!("{}, {}", column.sql_type(), if column.is_nullable { "nullable" } else { "not null" });
+    if let Some(default) = &column.column_default {
+        let _ = write!(description, ", default {}", default);
+    }
+    description
+}
+
+fn column_changes(before: &ColumnInfo, after: &ColumnInfo) -> Vec<String> {
+    let mut changes = Vec::new();
+    if before.sql_type() != after.sql_type() {
+        changes.push(format!("type {} → {}", before.sql_type(), after.sql_type()));
+    }
+    if before.is_nullable != after.is_nullable {
+        changes.push(if after.is_nullable { "now nullable" } else { "now not null" }.to_string());
+    }
+    match (&before.column_default, &after.column_default) {
+        (None, Some(default)) => changes.push(format!("default set to {}", default)),
+        (Some(_), None) => changes.push("default removed".to_string()),
+        (Some(old), Some(new)) if old != new => changes.push(format!("default {} → {}", old, new)),
+        _ => {}
+    }
+    match (&before.comment, &after.comment) {
+        (None, Some(_)) => changes.push("comment added".to_string()),
+        (Some(_), None) => changes.push("comment removed".to_string()),
+        (Some(old), Some(new)) if old != new => changes.push("comment updated".to_string()),
+        _ => {}
+    }
+    if before.collation != after.collation {
+        let collation = |c: &Option<String>| c.clone().unwrap_or_else(|| "none".to_string());
+        changes.push(format!("collation {} → {}", collation(&before.collation), collation(&after.collation)));
+    }
+    if before.identity != after.identity {
+        changes.push(match after.identity {
+            Some(_) if before.identity.is_none() => "now an identity column".to_string(),
+            Some(_) => "identity start or increment changed".to_string(),
+            None => "no longer an identity column".to_string(),
+        });
+    }
+    changes
+}
+
+fn warehouse_changes(before: &WarehouseInfo, after: &WarehouseInfo) -> Vec<String> {
+    let mut changes = Vec::new();
+    if before.size != after.size {
+        changes.push(format!("size {} → {}", before.size, after.size));
+    }
+    if before.type_ != after.type_ {
+        changes.push(format!("type {} → {}", before.type_, after.type_));
+    }
+    if before.auto_suspend != after.auto_suspend {
+        let suspend = |s: Option<i32>| s.map_or("never".to_string(), |s| format!("{}s", s));
+        changes.push(format!("auto-suspend {} → {}", suspend(before.auto_suspend), suspend(after.auto_suspend)));
+    }
+    if before.auto_resume != after.auto_resume {
+        changes.push(if after.auto_resume == Some(false) { "auto-resume off" } else { "auto-resume on" }.to_string());
+    }
+    if (before.min_cluster_count, before.max_cluster_count) != (after.min_cluster_count, after.max_cluster_count) {
+        let clusters = |w: &WarehouseInfo| match (w.min_cluster_count, w.max_cluster_count) {
+            (Some(min), Some(max)) => format!("{}-{}", min, max),
+            _ => "unknown".to_string(),
+        };
+        changes.push(format!("clusters {} → {}", clusters(before), clusters(after)));
+    }
+    if before.scaling_policy != after.scaling_policy {
+        let policy = |p: &Option<String>| p.clone().unwrap_or_else(|| "unknown".to_string());
+        changes.push(format!("scaling policy {} → {}", policy(&before.scaling_policy), policy(&after.scaling_policy)));
+    }
+    changes
+}
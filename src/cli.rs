@@ -0,0 +1,955 @@
+//! Command-line arguments.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use crate::backends::Backend;
+use crate::merge::OnConflict;
+use crate::formats::atlas::AtlasModel;
+use crate::mock::MockTarget;
+use crate::retry::{ErrorClass, RetryPolicy};
+use crate::schema::Document;
+use crate::users::UserSource;
+use crate::{TableInfo, TableKind};
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "snowflake-mapper",
+    about = "A tool to fetch and map Snowflake database schemas",
+    version,
+    author
+)]
+pub struct Args {
+    /// Mapping options, used when no subcommand is given
+    #[command(flatten)]
+    pub map: MapArgs,
+
+    /// Hide the progress bar and only log warnings and errors
+    #[arg(long, short = 'q', global = true)]
+    pub quiet: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Map databases into snapshot files (the default when no subcommand is given)
+    Map(Box<MapArgs>),
+    /// List objects in the account without mapping them
+    List(ListArgs),
+    /// Snapshot account parameters and resource monitors into `account.json`
+    Account(AccountArgs),
+    /// Inventory users and service accounts into `users.json`
+    Users(UsersArgs),
+    /// Inventory inbound and outbound shares and their objects into `shares.json`
+    Shares(SharesArgs),
+    /// Capture replication and failover groups and database replication into `replication.json`
+    Replication(ReplicationArgs),
+    /// Report databases, schemas and tables in ACCOUNT_USAGE that the current role can't see
+    AuditVisibility(AuditVisibilityArgs),
+    /// Time each phase of a map run under several `--jobs` and `--shard-columns` settings
+    Bench(BenchArgs),
+    /// Show the schema changes between two snapshots
+    Diff(DiffArgs),
+    /// Write a Markdown changelog of the schema changes across a series of snapshots
+    Changelog(ChangelogArgs),
+    /// Re-render an existing snapshot in another output format
+    Export(ExportArgs),
+    /// Rewrite snapshots written by older releases in the current format
+    Upgrade(UpgradeArgs),
+    /// Merge snapshots from separate runs into one catalog, detecting conflicting tables
+    Merge(MergeArgs),
+    /// Render a snapshot into a browsable static HTML site
+    Docs(DocsArgs),
+    /// Generate a Flyway or Liquibase migration from a snapshot, or from the diff between two
+    Migrations(MigrationsArgs),
+    /// Create empty local SQLite databases, or a DuckDB script, with every table of a snapshot
+    Mock(MockArgs),
+    /// Generate dbt or SQLMesh staging models for every table of a snapshot
+    Scaffold(ScaffoldArgs),
+    /// List tables in a snapshot that haven't been altered or read recently
+    Stale(StaleArgs),
+    /// Evaluate a JSONPath expression against a snapshot
+    Query(QueryArgs),
+    /// List every table and view affected by renaming or dropping a column
+    Impact(ImpactArgs),
+    /// Summarize a snapshot: tables per schema, column counts, data types, comment coverage
+    Stats(StatsArgs),
+    /// Report tables whose column sets are nearly identical, e.g. forgotten backups
+    Duplicates(DuplicatesArgs),
+    /// Print the JSON Schema of an output document, for validation and code generation
+    Schema(SchemaArgs),
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+    /// Write man pages for the command and every subcommand
+    Man(ManArgs),
+    /// Print the database names in a snapshot, for dynamic shell completion
+    #[command(hide = true)]
+    CompleteDatabases(CompleteDatabasesArgs),
+}
+
+/// How to reach the warehouse, shared by every subcommand that connects
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConnectionArgs {
+    /// Warehouse backend to map
+    #[arg(long, value_enum, default_value_t = Backend::Snowflake)]
+    pub backend: Backend,
+
+    /// Replay Snowflake query results from a fixture directory instead of connecting
+    #[arg(long)]
+    pub fixture: Option<PathBuf>,
+
+    /// With `--fixture`, run against Snowflake and record every query result into the directory
+    #[arg(long, requires = "fixture")]
+    pub record: bool,
+
+    /// Number of retries for failed operations
+    #[arg(short, long, default_value = "3")]
+    pub retries: u32,
+
+    /// Delay in seconds before the first retry
+    #[arg(long, default_value = "5")]
+    pub retry_delay: u64,
+
+    /// Factor the retry delay grows by after each retry; 1 keeps it constant
+    #[arg(long, default_value = "2", value_parser = parse_positive_f64)]
+    pub retry_backoff: f64,
+
+    /// Longest delay between retries, e.g. `1m`
+    #[arg(long, default_value = "60s", value_parser = humantime::parse_duration)]
+    pub retry_max_delay: std::time::Duration,
+
+    /// Error classes that are retried; permission and not-found errors never succeed on a retry
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "transient,unknown")]
+    pub retry_on: Vec<ErrorClass>,
+
+    /// Run all mapping queries on this (typically X-Small) warehouse instead of SNOWFLAKE_WAREHOUSE
+    #[arg(long)]
+    pub metadata_warehouse: Option<String>,
+
+    /// Refuse to run anything but SHOW/SELECT/DESCRIBE/USE; pass `--enforce-readonly=false` to disable
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub enforce_readonly: bool,
+
+    /// Append every executed query, with timing and row count, to this file as JSON lines
+    #[arg(long, value_name = "FILE")]
+    pub log_queries: Option<PathBuf>,
+
+    /// Abort once this many queries have been issued
+    #[arg(long)]
+    pub max_queries: Option<usize>,
+
+    /// Abort once the run has taken this long, e.g. `15m`
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub max_runtime: Option<std::time::Duration>,
+
+    /// Limit queries per second across all connections
+    #[arg(long, value_parser = parse_positive_f64)]
+    pub max_qps: Option<f64>,
+
+    /// For users with mandatory MFA: wait up to this long, e.g. `2m`, for the Duo push sent
+    /// on each Snowflake login to be approved
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub mfa_timeout: Option<std::time::Duration>,
+
+    /// For users with mandatory MFA: a Duo passcode to send with the login instead of
+    /// waiting for a push; overrides `SNOWFLAKE_MFA_PASSCODE`
+    #[arg(long, conflicts_with = "mfa_timeout")]
+    pub mfa_passcode: Option<String>,
+
+    /// Who checks SNOWFLAKE_USERNAME and SNOWFLAKE_PASSWORD
+    #[arg(long, value_enum, default_value_t = AuthMethod::Password)]
+    pub auth: AuthMethod,
+
+    /// Okta URL for `--auth okta`, e.g. `https://company.okta.com`
+    #[arg(long, required_if_eq("auth", "okta"), requires = "auth", conflicts_with = "mfa_passcode")]
+    pub okta_url: Option<String>,
+
+    /// Run `SELECT 1` on Snowflake sessions idle this long, e.g. `10m`, so they don't time out
+    /// while a long database keeps them waiting
+    #[arg(long, value_parser = parse_positive_duration)]
+    pub keep_alive: Option<std::time::Duration>,
+
+    /// Snowflake host to connect to, e.g. a PrivateLink endpoint
+    /// `xy12345.us-east-1.privatelink.snowflakecomputing.com`; overrides `SNOWFLAKE_HOST`
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Role to connect as; overrides `SNOWFLAKE_ROLE`
+    #[arg(long)]
+    pub role: Option<String>,
+}
+
+/// How the mapper logs in to Snowflake
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Snowflake checks the password
+    Password,
+    /// Okta checks the password and vouches for the user with a SAML response, for accounts
+    /// that log in through Okta
+    Okta,
+}
+
+impl ConnectionArgs {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.retries,
+            delay: std::time::Duration::from_secs(self.retry_delay),
+            backoff: self.retry_backoff,
+            max_delay: self.retry_max_delay,
+            retry_on: self.retry_on.clone(),
+        }
+    }
+}
+
+/// Where and how mapped tables are written
+#[derive(clap::Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// Output directory for the JSON files, or `-` to write the merged catalog to stdout
+    #[arg(short, long, visible_alias = "output", default_value = "output")]
+    pub output_dir: PathBuf,
+
+    /// Emit newline-delimited JSON (one table per line) instead of a pretty-printed array
+    #[arg(long)]
+    pub ndjson: bool,
+
+    /// Path of each JSON file within the output directory, with `{account}`, `{database}`,
+    /// `{schema}`, `{date}` and `{run_id}` placeholders, e.g. `{account}/{database}/{schema}.json`
+    #[arg(long, value_name = "TEMPLATE")]
+    pub path_template: Option<String>,
+
+    /// Output format: json, template, markdown, xlsx, dot, terraform, avro, amundsen, atlas,
+    /// cube, lookml, topo-order, or the name of a `snowflake-mapper-format-<name>` plugin on PATH
+    #[arg(long, default_value = "json")]
+    pub format: OutputFormat,
+
+    /// Extra argument passed to a format plugin (repeatable)
+    #[arg(long = "plugin-arg", allow_hyphen_values = true)]
+    pub plugin_args: Vec<String>,
+
+    /// File extension for plugin output; defaults to the format name
+    #[arg(long)]
+    pub plugin_extension: Option<String>,
+
+    /// Handlebars template file used with `--format template`
+    #[arg(long, required_if_eq("format", "template"))]
+    pub template: Option<PathBuf>,
+
+    /// Render the template once for the whole catalog instead of once per database
+    #[arg(long)]
+    pub template_catalog: bool,
+
+    /// Atlas types for `--format atlas` and `--push atlas`: the built-in hive ones, or custom
+    /// snowflake ones that keep schemas as entities
+    #[arg(long, value_enum, default_value_t = AtlasModel::Hive)]
+    pub atlas_types: AtlasModel,
+
+    /// Cluster name Atlas qualified names end in (`<database>.<schema>.<table>@<cluster>`)
+    #[arg(long, default_value = "snowflake")]
+    pub atlas_cluster: String,
+
+    /// Earlier snapshot; with `--format markdown`, tables and columns that are new, changed
+    /// or removed since it are marked
+    #[arg(long, value_name = "SNAPSHOT")]
+    pub changes_since: Option<PathBuf>,
+
+    /// Drop columns whose names match these globs (comma-separated, case-insensitive), e.g.
+    /// `*_ssn,password*`, from every output
+    #[arg(long, value_delimiter = ',', value_name = "PATTERN")]
+    pub exclude_columns: Vec<String>,
+
+    /// Only write these kinds of tables and views (comma-separated), e.g. `base,view` to
+    /// leave out transient and temporary tables; everything by default
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "TYPE")]
+    pub table_types: Vec<TableKind>,
+
+    /// JSON file of naming rules (prefix stripping, regex rewrites, casing) applied to schema,
+    /// table and column names in the output
+    #[arg(long, value_name = "FILE")]
+    pub naming: Option<PathBuf>,
+
+    /// JSON file of ownership rules mapping table patterns to teams; each table's owners are
+    /// recorded in the output
+    #[arg(long, value_name = "FILE")]
+    pub owners: Option<PathBuf>,
+
+    /// Replace database, schema, table and column names with deterministic pseudonyms and
+    /// drop comments and defaults, for sharing snapshots outside the company
+    #[arg(long)]
+    pub redact: bool,
+
+    /// Secret mixed into `--redact` pseudonyms so they can't be matched against guessed names
+    #[arg(long, requires = "redact")]
+    pub redact_salt: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MapArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    /// Specific databases to process (comma-separated). If not provided, all accessible databases will be processed
+    #[arg(short, long, value_delimiter = ',')]
+    pub databases: Option<Vec<String>>,
+
+    /// Publish generated schemas to an external system after mapping each database
+    #[arg(long, value_enum)]
+    pub push: Option<PushTarget>,
+
+    /// Atlas URL for `--push atlas` (defaults to ATLAS_URL)
+    #[arg(long)]
+    pub atlas_url: Option<String>,
+
+    /// Schema registry URL for `--push schema-registry` (defaults to SCHEMA_REGISTRY_URL)
+    #[arg(long)]
+    pub schema_registry_url: Option<String>,
+
+    /// Prefix for schema registry subjects, which are named `<prefix><database>.<schema>.<table>`
+    #[arg(long, default_value = "")]
+    pub subject_prefix: String,
+
+    /// Skip tables that fail to process, listing them in `failures.json` in the output directory
+    #[arg(long)]
+    pub skip_failed_tables: bool,
+
+    /// Map into a staging directory next to the output directory, and replace the output
+    /// directory with it only once the whole run succeeds
+    #[arg(long, conflicts_with = "retry_failures")]
+    pub atomic_snapshot: bool,
+
+    /// With `--atomic-snapshot`, replace the output directory even when
+    /// `--skip-failed-tables` skipped databases; by default the previous snapshot is kept
+    #[arg(long, requires = "atomic_snapshot")]
+    pub replace_partial_snapshot: bool,
+
+    /// Map under each of these roles (Snowflake only), for accounts where no single role can
+    /// see every database; each role's catalog goes to a subdirectory named after it unless
+    /// `--merge-roles` is given
+    #[arg(long, value_delimiter = ',', conflicts_with = "role")]
+    pub roles: Vec<String>,
+
+    /// Merge the catalogs of `--roles` into one, listing in each table's `roles` the roles
+    /// that could see it
+    #[arg(long, requires = "roles")]
+    pub merge_roles: bool,
+
+    /// Only map the databases listed in a `failures.json` from an earlier run
+    #[arg(long, value_name = "FILE", conflicts_with = "databases")]
+    pub retry_failures: Option<PathBuf>,
+
+    /// Fill `last_accessed` from SNOWFLAKE.ACCOUNT_USAGE.ACCESS_HISTORY (Enterprise edition; slow)
+    #[arg(long)]
+    pub access_history: bool,
+
+    /// Fill `usage` with the number of queries and distinct users reading each table over
+    /// `--usage-window`, from SNOWFLAKE.ACCOUNT_USAGE.ACCESS_HISTORY (Enterprise edition; slow)
+    #[arg(long)]
+    pub include_usage: bool,
+
+    /// How far back `--include-usage` counts, e.g. `30d`; rounded up to whole days
+    #[arg(long, default_value = "30d", value_parser = humantime::parse_duration, requires = "include_usage")]
+    pub usage_window: std::time::Duration,
+
+    /// Sample rows of tables with VARIANT / OBJECT / ARRAY columns and record each column's
+    /// nested structure as `variant_shape` (Snowflake only; reads table data)
+    #[arg(long)]
+    pub infer_variant: bool,
+
+    /// Rows sampled per table for `--infer-variant`
+    #[arg(long, default_value = "1000", requires = "infer_variant")]
+    pub sample: u32,
+
+    /// Count each database's columns first and fetch databases with more than this many in
+    /// chunks of schemas or table-name ranges, each retried on its own (Snowflake only)
+    #[arg(long, value_name = "COLUMNS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub shard_columns: Option<u64>,
+
+    /// JSON file of SQL hooks run per database or per table; their results are attached to
+    /// each table under `custom` (Snowflake only)
+    #[arg(long, value_name = "FILE")]
+    pub hooks: Option<PathBuf>,
+
+    /// Print the expected number of queries and credit usage class, then exit without mapping
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Wait this long after each database before starting the next one, e.g. `10s`
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub pause_between_databases: Option<std::time::Duration>,
+
+    /// Give up on a database that takes longer than this to fetch, e.g. `15m`, retries
+    /// included; with `--skip-failed-tables` the run records it as failed and moves on
+    #[arg(long, value_parser = parse_positive_duration)]
+    pub database_timeout: Option<std::time::Duration>,
+
+    /// Number of databases to map in parallel, each on its own connection
+    #[arg(long, short = 'j', default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    pub jobs: u32,
+
+    /// Don't draw the progress bar
+    #[arg(long)]
+    pub no_progress: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ListArgs {
+    /// What to list
+    #[arg(value_enum)]
+    pub object: ListObject,
+
+    /// Database whose schemas to list, required for `list schemas`
+    #[arg(long, required_if_eq("object", "schemas"))]
+    pub database: Option<String>,
+
+    /// Print JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListObject {
+    /// Databases visible to the current role
+    Databases,
+    /// Schemas in `--database` (Snowflake only)
+    Schemas,
+    /// Warehouses visible to the current role (Snowflake only)
+    Warehouses,
+    /// Roles visible to the current role (Snowflake only)
+    Roles,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct AccountArgs {
+    /// Directory to write `account.json` into, or `-` for stdout
+    #[arg(short, long, visible_alias = "output", default_value = "output")]
+    pub output_dir: PathBuf,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct UsersArgs {
+    /// Directory to write `users.json` into, or `-` for stdout
+    #[arg(short, long, visible_alias = "output", default_value = "output")]
+    pub output_dir: PathBuf,
+
+    /// Read users with SHOW USERS or from ACCOUNT_USAGE
+    #[arg(long, value_enum, default_value_t = UserSource::Show)]
+    pub source: UserSource,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SharesArgs {
+    /// Directory to write `shares.json` into, or `-` for stdout
+    #[arg(short, long, visible_alias = "output", default_value = "output")]
+    pub output_dir: PathBuf,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReplicationArgs {
+    /// Directory to write `replication.json` into, or `-` for stdout
+    #[arg(short, long, visible_alias = "output", default_value = "output")]
+    pub output_dir: PathBuf,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct AuditVisibilityArgs {
+    /// Compare against the tables of a snapshot instead of what the role sees now
+    #[arg(long, value_name = "SNAPSHOT")]
+    pub snapshot: Option<PathBuf>,
+
+    /// Print the report as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+
+    /// Exit with an error if any object is hidden, after printing the report
+    #[arg(long)]
+    pub fail_on_hidden: bool,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BenchArgs {
+    /// Databases to map in every run (comma-separated); all accessible databases by default
+    #[arg(short, long, value_delimiter = ',')]
+    pub databases: Option<Vec<String>>,
+
+    /// `--jobs` settings to compare
+    #[arg(long, short = 'j', value_delimiter = ',', default_value = "1,4", value_parser = clap::value_parser!(u32).range(1..))]
+    pub jobs: Vec<u32>,
+
+    /// `--shard-columns` settings to compare, besides fetching each database in one go
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',', value_parser = clap::value_parser!(u64).range(1..))]
+    pub shard_columns: Vec<u64>,
+
+    /// Print the timings as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DiffArgs {
+    /// Earlier snapshot directory (or merged catalog file)
+    pub old: PathBuf,
+
+    /// Later snapshot directory (or merged catalog file)
+    pub new: PathBuf,
+
+    /// Print the changes as JSON instead of one line per change
+    #[arg(long)]
+    pub json: bool,
+
+    /// Print an RFC 6902 JSON Patch turning the older merged catalog into the newer one
+    #[arg(long, conflicts_with_all = ["json", "merge_patch"])]
+    pub json_patch: bool,
+
+    /// Print an RFC 7386 JSON Merge Patch between the catalogs keyed by table and column name
+    #[arg(long, conflicts_with = "json")]
+    pub merge_patch: bool,
+
+    /// Exit with an error if any change of this kind is found, after printing the changes
+    #[arg(long, value_enum)]
+    pub fail_on: Option<FailOn>,
+
+    /// JSON file of ownership rules; adds each change's owning teams to `--json` output
+    #[arg(long, value_name = "FILE")]
+    pub owners: Option<PathBuf>,
+
+    /// Send each owning team its changes on Slack, through the team's webhook in `--owners`
+    #[arg(long, requires = "owners")]
+    pub notify: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailOn {
+    /// Dropped tables or columns, narrowed types, tightened nullability
+    Breaking,
+    /// Any change at all
+    Any,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ChangelogArgs {
+    /// Snapshot directories (or merged catalog files), oldest first; each one after the first
+    /// gets a section listing its changes since the one before
+    #[arg(required = true, num_args = 2..)]
+    pub snapshots: Vec<PathBuf>,
+
+    /// Top-level heading
+    #[arg(long, default_value = "Changelog")]
+    pub title: String,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// Snapshot directory (or merged catalog file) written by a previous run
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct UpgradeArgs {
+    /// Snapshot directories (or single catalog files) to upgrade in place
+    #[arg(required = true)]
+    pub snapshots: Vec<PathBuf>,
+
+    /// Only report out-of-date files, failing if there are any
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MergeArgs {
+    /// Snapshot directories (or merged catalog files) to combine, in order
+    #[arg(required = true, num_args = 2..)]
+    pub snapshots: Vec<PathBuf>,
+
+    /// What to do with a table or warehouse whose structure differs between snapshots
+    #[arg(long, value_enum, default_value_t = OnConflict::Error)]
+    pub on_conflict: OnConflict,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DocsArgs {
+    /// Snapshot directory (or merged catalog file) written by a previous run
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Directory to write the site into
+    #[arg(short, long, default_value = "docs")]
+    pub out: PathBuf,
+
+    /// Earlier snapshot; tables and columns that are new, changed or removed since it are
+    /// marked
+    #[arg(long, value_name = "SNAPSHOT")]
+    pub changes_since: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MigrationsArgs {
+    /// Snapshot directory (or merged catalog file) to migrate to
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Earlier snapshot; when given, only the changes since it are migrated
+    #[arg(long)]
+    pub previous: Option<PathBuf>,
+
+    /// Migration tool to generate for
+    #[arg(long, value_enum, default_value_t = MigrationTool::Flyway)]
+    pub tool: MigrationTool,
+
+    /// Directory to write the migration into
+    #[arg(short, long, default_value = "migrations")]
+    pub out: PathBuf,
+
+    /// Migration version; defaults to the current Unix timestamp
+    #[arg(long)]
+    pub migration_version: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ScaffoldArgs {
+    /// Snapshot directory (or merged catalog file) to generate models for
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Project layout to generate
+    #[arg(long, value_enum, default_value_t = ScaffoldTool::Dbt)]
+    pub tool: ScaffoldTool,
+
+    /// Project directory; models are written under `models/staging`
+    #[arg(short, long, default_value = ".")]
+    pub out: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MockArgs {
+    /// Snapshot directory (or merged catalog file) to reproduce
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Local database, or DuckDB script, to create
+    #[arg(long, value_enum)]
+    pub target: MockTarget,
+
+    /// DuckDB database file or DDL script to write, or directory for the SQLite or CSV files
+    #[arg(short, long)]
+    pub out: PathBuf,
+
+    /// DuckDB CLI that `--target duckdb` runs to create the database file
+    #[arg(long, value_name = "PATH", default_value = "duckdb")]
+    pub duckdb_cli: PathBuf,
+
+    /// Database to reproduce; required when the snapshot holds several
+    #[arg(long)]
+    pub database: Option<String>,
+
+    /// Fill every table with this many rows of fake data matching its column types
+    #[arg(long, value_name = "ROWS")]
+    pub with_fake_data: Option<usize>,
+
+    /// Seed for `--with-fake-data`; the same seed always generates the same rows
+    #[arg(long, default_value = "0", requires = "with_fake_data")]
+    pub seed: u64,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct StaleArgs {
+    /// Snapshot directory (or merged catalog file) written by a previous run
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Report tables with no activity within this period, e.g. `180d` or `26weeks`
+    #[arg(long, default_value = "180d", value_parser = humantime::parse_duration)]
+    pub older_than: std::time::Duration,
+
+    /// Print the report as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct QueryArgs {
+    /// JSONPath (RFC 9535) expression over the merged catalog, e.g.
+    /// `$[*].columns[?@.data_type == 'VARIANT'].name`
+    pub expression: String,
+
+    /// Snapshot directory (or merged catalog file) written by a previous run
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Print each match with its table and path instead of just the value
+    #[arg(long)]
+    pub locate: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ImpactArgs {
+    /// Column name to assess, matched case-insensitively
+    #[arg(long)]
+    pub column: String,
+
+    /// Snapshot directory (or merged catalog file) written by a previous run
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Print the report as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct StatsArgs {
+    /// Snapshot directory (or merged catalog file) written by a previous run
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Number of widest tables to list
+    #[arg(long, default_value = "10")]
+    pub top: usize,
+
+    /// Print the report as JSON instead of tables
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DuplicatesArgs {
+    /// Snapshot directory (or merged catalog file) written by a previous run
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+
+    /// Minimum share of column names two tables have in common (0 to 1)
+    #[arg(long, default_value = "0.8", value_parser = parse_fraction)]
+    pub threshold: f64,
+
+    /// Ignore tables with fewer columns than this
+    #[arg(long, default_value = "3")]
+    pub min_columns: usize,
+
+    /// Print the report as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SchemaArgs {
+    /// Document to describe
+    #[arg(value_enum, default_value_t = Document::Catalog)]
+    pub document: Document,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CompletionsArgs {
+    /// Shell to generate the script for
+    #[arg(value_enum)]
+    pub shell: clap_complete::Shell,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ManArgs {
+    /// Directory to write the man pages into
+    #[arg(short, long, default_value = "man")]
+    pub out: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CompleteDatabasesArgs {
+    /// Snapshot directory (or merged catalog file) to read database names from
+    #[arg(short, long, default_value = "output")]
+    pub input: PathBuf,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationTool {
+    /// Versioned SQL migration (`V<version>__<description>.sql`)
+    Flyway,
+    /// XML changelog
+    Liquibase,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaffoldTool {
+    /// Sources YAML and `{{ source() }}` staging models
+    Dbt,
+    /// `MODEL` staging views and `external_models.yaml`
+    Sqlmesh,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One JSON document per database
+    Json,
+    /// Render through a user-provided Handlebars template
+    Template,
+    /// One Markdown data dictionary per database
+    Markdown,
+    /// One Excel workbook per database, with a sheet per schema
+    Xlsx,
+    /// One Graphviz graph per database, clustered by schema with foreign key edges
+    Dot,
+    /// Terraform resources for the Snowflake provider, plus an import script per database
+    Terraform,
+    /// One Avro record schema (`.avsc`) per table
+    Avro,
+    /// `table.csv`, `column.csv` and `table_owner.csv` for Amundsen's databuilder, covering
+    /// every database
+    Amundsen,
+    /// One Apache Atlas entity document per database
+    Atlas,
+    /// One Cube data model per database, with a cube per table
+    Cube,
+    /// One file of LookML views per database
+    Lookml,
+    /// Every table in foreign key dependency order, covering every database
+    TopoOrder,
+    /// Rendered by an external `snowflake-mapper-format-<name>` executable
+    Plugin(String),
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "json" => OutputFormat::Json,
+            "template" => OutputFormat::Template,
+            "markdown" => OutputFormat::Markdown,
+            "xlsx" => OutputFormat::Xlsx,
+            "dot" => OutputFormat::Dot,
+            "terraform" => OutputFormat::Terraform,
+            "avro" => OutputFormat::Avro,
+            "amundsen" => OutputFormat::Amundsen,
+            "atlas" => OutputFormat::Atlas,
+            "cube" => OutputFormat::Cube,
+            "lookml" => OutputFormat::Lookml,
+            "topo-order" => OutputFormat::TopoOrder,
+            _ => OutputFormat::Plugin(value.to_string()),
+        })
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushTarget {
+    /// Register Avro schemas with a Confluent Schema Registry, checking compatibility first
+    SchemaRegistry,
+    /// Create or update Apache Atlas entities for every table, in the `--atlas-types` model
+    Atlas,
+}
+
+fn parse_positive_f64(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(parsed) if parsed > 0.0 && parsed.is_finite() => Ok(parsed),
+        Ok(_) => Err("must be a positive number".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_positive_duration(value: &str) -> Result<std::time::Duration, String> {
+    match humantime::parse_duration(value) {
+        Ok(parsed) if !parsed.is_zero() => Ok(parsed),
+        Ok(_) => Err("must be longer than zero".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(parsed) if (0.0..=1.0).contains(&parsed) => Ok(parsed),
+        Ok(_) => Err("must be between 0 and 1".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl Args {
+    /// The subcommand to run; without one, the top-level flags map databases as before
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Map(Box::new(self.map)))
+    }
+
+    /// Whether the command writes data to stdout, so logs have to go to stderr
+    pub fn writes_to_stdout(&self) -> bool {
+        match &self.command {
+            None => self.map.writes_to_stdout(),
+            Some(Command::Map(map)) => map.writes_to_stdout(),
+            Some(Command::Export(export)) => export.output.writes_to_stdout(),
+            Some(Command::Merge(merge)) => merge.output.writes_to_stdout(),
+            Some(Command::Account(account)) => account.output_dir.as_os_str() == "-",
+            Some(Command::Users(users)) => users.output_dir.as_os_str() == "-",
+            Some(Command::Shares(shares)) => shares.output_dir.as_os_str() == "-",
+            Some(Command::Replication(replication)) => replication.output_dir.as_os_str() == "-",
+            Some(
+                Command::List(_)
+                | Command::AuditVisibility(_)
+                | Command::Bench(_)
+                | Command::Diff(_)
+                | Command::Changelog(_)
+                | Command::Stale(_)
+                | Command::Query(_)
+                | Command::Impact(_)
+                | Command::Stats(_)
+                | Command::Duplicates(_)
+                | Command::Schema(_)
+                | Command::Completions(_)
+                | Command::CompleteDatabases(_),
+            ) => true,
+            Some(Command::Docs(_) | Command::Migrations(_) | Command::Mock(_) | Command::Scaffold(_) | Command::Man(_) | Command::Upgrade(_)) => false,
+        }
+    }
+}
+
+impl MapArgs {
+    /// `--usage-window` in whole days, when usage is included
+    pub fn usage_window_days(&self) -> Option<u32> {
+        self.include_usage.then(|| self.usage_window.as_secs().div_ceil(86_400).max(1) as u32)
+    }
+
+    pub fn shows_progress(&self, quiet: bool) -> bool {
+        !(self.no_progress || quiet)
+    }
+
+    pub fn writes_to_stdout(&self) -> bool {
+        self.output.writes_to_stdout() || self.dry_run
+    }
+}
+
+impl OutputArgs {
+    /// Whether output goes to stdout (`--output -`) rather than to files
+    pub fn writes_to_stdout(&self) -> bool {
+        self.output_dir.as_os_str() == "-"
+    }
+
+    /// Whether `table` is one of the `--table-types` to write
+    pub fn keeps_table(&self, table: &TableInfo) -> bool {
+        self.table_types.is_empty() || self.table_types.contains(&table.kind())
+    }
+}
@@ -0,0 +1,74 @@
+//! Shell completion scripts and man pages.
+//!
+//! Completion scripts are generated from the clap definition. For bash and fish they are
+//! extended to complete `--databases`/`--database` with the database names in the last
+//! snapshot, by calling back into the hidden `complete-databases` subcommand.
+
+use anyhow::Result;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::Path;
+
+use crate::catalog;
+use crate::cli::Args;
+use crate::SnowflakeMapperError;
+
+const BIN_NAME: &str = "snowflake-mapper";
+
+/// Write the completion script for `shell`. The script is generated in memory first: clap
+/// panics when writing fails, e.g. on a closed pipe, where `out` returns the error.
+pub fn write_completions(shell: Shell, out: &mut dyn Write) -> Result<()> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Args::command(), BIN_NAME, &mut script);
+    match shell {
+        Shell::Bash => script.extend_from_slice(BASH_DATABASES.as_bytes()),
+        Shell::Fish => script.extend_from_slice(FISH_DATABASES.as_bytes()),
+        _ => {}
+    }
+    out.write_all(&script)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Database names in a snapshot, sorted and deduplicated; empty if there is no snapshot
+pub fn snapshot_databases(snapshot: &Path) -> Vec<String> {
+    let mut databases: Vec<String> = catalog::load_snapshot(snapshot)
+        .map(|tables| tables.into_iter().map(|table| table.database_name).collect())
+        .unwrap_or_default();
+    databases.sort();
+    databases.dedup();
+    databases
+}
+
+/// Write a man page for the command and one per subcommand (`snowflake-mapper-<name>.1`)
+pub fn write_man_pages(dir: &Path) -> Result<usize> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let command = Args::command();
+    let pages = 1 + command.get_subcommands().filter(|s| !s.is_hide_set()).count();
+    clap_mangen::generate_to(command, dir)
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to write man pages to {}: {}", dir.display(), e)))?;
+    Ok(pages)
+}
+
+/// Wraps the generated bash completion so database arguments complete from the last snapshot
+const BASH_DATABASES: &str = r#"
+_snowflake_mapper_with_databases() {
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ "$prev" == "-d" || "$prev" == "--databases" || "$prev" == "--database" ]]; then
+        local cur="${COMP_WORDS[COMP_CWORD]##*,}"
+        local head=""
+        [[ "${COMP_WORDS[COMP_CWORD]}" == *,* ]] && head="${COMP_WORDS[COMP_CWORD]%,*},"
+        COMPREPLY=( $(compgen -P "$head" -W "$(snowflake-mapper complete-databases 2>/dev/null)" -- "$cur") )
+        return 0
+    fi
+    _snowflake__mapper "$@"
+}
+complete -F _snowflake_mapper_with_databases -o nosort -o bashdefault -o default snowflake-mapper
+"#;
+
+const FISH_DATABASES: &str = r#"
+complete -c snowflake-mapper -s d -l databases -f -a '(snowflake-mapper complete-databases 2>/dev/null)'
+complete -c snowflake-mapper -n '__fish_seen_subcommand_from list' -l database -f -a '(snowflake-mapper complete-databases 2>/dev/null)'
+"#;
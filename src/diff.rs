@@ -0,0 +1,386 @@
+//! Structural comparison of two catalogs.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::types::{SnowflakeType, DEFAULT_DATETIME_PRECISION};
+use crate::{ColumnInfo, TableInfo, WarehouseInfo};
+
+/// A single difference between an old and a new catalog
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaChange {
+    TableAdded {
+        table: TableInfo,
+    },
+    TableRemoved {
+        table: TableInfo,
+    },
+    ColumnAdded {
+        database: String,
+        schema: String,
+        table: String,
+        column: ColumnInfo,
+    },
+    ColumnRemoved {
+        database: String,
+        schema: String,
+        table: String,
+        column: ColumnInfo,
+    },
+    ColumnChanged {
+        database: String,
+        schema: String,
+        table: String,
+        before: ColumnInfo,
+        after: ColumnInfo,
+    },
+    WarehouseAdded {
+        warehouse: WarehouseInfo,
+    },
+    WarehouseRemoved {
+        warehouse: WarehouseInfo,
+    },
+    /// Sizing or scaling changed
+    WarehouseChanged {
+        before: WarehouseInfo,
+        after: WarehouseInfo,
+    },
+}
+
+/// Whether a change can break existing readers or writers of the object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Dropped objects, narrowed types, tightened nullability
+    Breaking,
+    /// Added objects, widened types, comment and default changes
+    NonBreaking,
+}
+
+/// A change with its severity, as `diff --json` prints it
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[schemars(extend("unevaluatedProperties" = false))]
+pub struct ClassifiedChange<'a> {
+    pub severity: Severity,
+    /// Owning teams, when ownership rules were given
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    #[serde(flatten)]
+    pub change: &'a SchemaChange,
+}
+
+impl SchemaChange {
+    pub fn severity(&self) -> Severity {
+        let breaking = match self {
+            SchemaChange::TableAdded { .. } | SchemaChange::ColumnAdded { .. } | SchemaChange::WarehouseAdded { .. } => false,
+            SchemaChange::TableRemoved { .. } | SchemaChange::ColumnRemoved { .. } | SchemaChange::WarehouseRemoved { .. } => true,
+            SchemaChange::ColumnChanged { before, after, .. } => {
+                type_narrowed(before, after)
+                    || (before.is_nullable && !after.is_nullable)
+                    || before.collation != after.collation
+                    // Inserts that relied on the generated value now fail, or vice versa
+                    || before.identity.is_some() != after.identity.is_some()
+            }
+            // Sizing and scaling don't change what queries can do
+            SchemaChange::WarehouseChanged { .. } => false,
+        };
+        if breaking {
+            Severity::Breaking
+        } else {
+            Severity::NonBreaking
+        }
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.severity() == Severity::Breaking
+    }
+
+    /// Database, schema and table the change belongs to; `None` for warehouse changes
+    pub fn table(&self) -> Option<(&str, &str, &str)> {
+        match self {
+            SchemaChange::TableAdded { table } | SchemaChange::TableRemoved { table } => Some(table_key(table)),
+            SchemaChange::ColumnAdded { database, schema, table, .. }
+            | SchemaChange::ColumnRemoved { database, schema, table, .. }
+            | SchemaChange::ColumnChanged { database, schema, table, .. } => Some((database, schema, table)),
+            SchemaChange::WarehouseAdded { .. } | SchemaChange::WarehouseRemoved { .. } | SchemaChange::WarehouseChanged { .. } => None,
+        }
+    }
+}
+
+/// Whether `after` can hold fewer values than `before`. Any change of type counts, except
+/// between the aliases Snowflake reports for the same type.
+fn type_narrowed(before: &ColumnInfo, after: &ColumnInfo) -> bool {
+    // An unset length is the type's maximum
+    let shrunk = |old: Option<u32>, new: Option<u32>| match (old, new) {
+        (Some(old), Some(new)) => new < old,
+        (None, Some(_)) => true,
+        _ => false,
+    };
+    match compared_types(before, after) {
+        (SnowflakeType::Number { precision: old_precision, scale: old_scale }, SnowflakeType::Number { precision, scale }) => {
+            i64::from(precision) - i64::from(scale) < i64::from(old_precision) - i64::from(old_scale) || scale < old_scale
+        }
+        (SnowflakeType::Varchar { length: old }, SnowflakeType::Varchar { length: new })
+        | (SnowflakeType::Binary { length: old }, SnowflakeType::Binary { length: new }) => shrunk(old, new),
+        (old, new) if std::mem::discriminant(&old) == std::mem::discriminant(&new) => match (old.datetime_precision(), new.datetime_precision()) {
+            (Some(old_precision), Some(precision)) => precision < old_precision,
+            _ => old != new,
+        },
+        _ => true,
+    }
+}
+
+/// Compare two catalogs. Changes follow the order of `new`, with removed tables last.
+pub fn diff_catalogs(old: &[TableInfo], new: &[TableInfo]) -> Vec<SchemaChange> {
+    let old_tables: HashMap<(&str, &str, &str), &TableInfo> = old.iter().map(|t| (table_key(t), t)).collect();
+    let new_keys: std::collections::HashSet<(&str, &str, &str)> = new.iter().map(table_key).collect();
+
+    let mut changes = Vec::new();
+    for table in new {
+        match old_tables.get(&table_key(table)) {
+            None => changes.push(SchemaChange::TableAdded { table: table.clone() }),
+            Some(previous) => diff_columns(previous, table, &mut changes),
+        }
+    }
+    for table in old {
+        if !new_keys.contains(&table_key(table)) {
+            changes.push(SchemaChange::TableRemoved { table: table.clone() });
+        }
+    }
+    changes
+}
+
+/// Compare two warehouse inventories by name, in the same order as [`diff_catalogs`]
+pub fn diff_warehouses(old: &[WarehouseInfo], new: &[WarehouseInfo]) -> Vec<SchemaChange> {
+    let old_warehouses: HashMap<&str, &WarehouseInfo> = old.iter().map(|w| (w.name.as_str(), w)).collect();
+
+    let mut changes = Vec::new();
+    for warehouse in new {
+        match old_warehouses.get(warehouse.name.as_str()) {
+            None => changes.push(SchemaChange::WarehouseAdded { warehouse: warehouse.clone() }),
+            Some(previous) if previous.config_differs(warehouse) => {
+                changes.push(SchemaChange::WarehouseChanged { before: (*previous).clone(), after: warehouse.clone() })
+            }
+            Some(_) => {}
+        }
+    }
+    for warehouse in old {
+        if !new.iter().any(|w| w.name == warehouse.name) {
+            changes.push(SchemaChange::WarehouseRemoved { warehouse: warehouse.clone() });
+        }
+    }
+    changes
+}
+
+fn diff_columns(old: &TableInfo, new: &TableInfo, changes: &mut Vec<SchemaChange>) {
+    let location = || (new.database_name.clone(), new.schema_name.clone(), new.table_name.clone());
+    let old_columns: HashMap<&str, &ColumnInfo> = old.columns.iter().map(|c| (c.name.as_str(), c)).collect();
+
+    for column in &new.columns {
+        let (database, schema, table) = location();
+        match old_columns.get(column.name.as_str()) {
+            None => changes.push(SchemaChange::ColumnAdded { database, schema, table, column: column.clone() }),
+            Some(previous) if column_differs(previous, column) => changes.push(SchemaChange::ColumnChanged {
+                database,
+                schema,
+                table,
+                before: (*previous).clone(),
+                after: column.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for column in &old.columns {
+        if !new.columns.iter().any(|c| c.name == column.name) {
+            let (database, schema, table) = location();
+            changes.push(SchemaChange::ColumnRemoved { database, schema, table, column: column.clone() });
+        }
+    }
+}
+
+/// The types of a column before and after. Snapshots from before fractional second digits
+/// were captured default times and timestamps to 9, so their precision is only compared
+/// when both sides recorded it.
+fn compared_types(before: &ColumnInfo, after: &ColumnInfo) -> (SnowflakeType, SnowflakeType) {
+    let (old, new) = (before.snowflake_type(), after.snowflake_type());
+    if before.datetime_precision.is_some() && after.datetime_precision.is_some() {
+        return (old, new);
+    }
+    (old.with_datetime_precision(DEFAULT_DATETIME_PRECISION), new.with_datetime_precision(DEFAULT_DATETIME_PRECISION))
+}
+
+fn column_differs(old: &ColumnInfo, new: &ColumnInfo) -> bool {
+    let (old_type, new_type) = compared_types(old, new);
+    old_type != new_type
+        || old.is_nullable != new.is_nullable
+        || old.comment != new.comment
+        || old.column_default != new.column_default
+        || old.collation != new.collation
+        || old.identity != new.identity
+}
+
+/// How a table or column differs from an earlier snapshot, as marked in generated docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeMark {
+    New,
+    Changed,
+    Removed,
+}
+
+impl ChangeMark {
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeMark::New => "new",
+            ChangeMark::Changed => "changed",
+            ChangeMark::Removed => "removed",
+        }
+    }
+}
+
+type TableKey = (String, String, String);
+
+/// The changes since an earlier snapshot, looked up by table and column. A table is
+/// changed when any of its columns was added, changed or removed.
+#[derive(Debug, Default)]
+pub struct ChangeMarks {
+    tables: HashMap<TableKey, ChangeMark>,
+    columns: HashMap<(TableKey, String), ChangeMark>,
+    /// Columns dropped from tables that still exist
+    removed_columns: HashMap<TableKey, Vec<ColumnInfo>>,
+    removed_tables: Vec<TableInfo>,
+}
+
+impl ChangeMarks {
+    pub fn new(changes: &[SchemaChange]) -> Self {
+        let mut marks = ChangeMarks::default();
+        let owned = |(database, schema, table): (&str, &str, &str)| (database.to_string(), schema.to_string(), table.to_string());
+        for change in changes {
+            match change {
+                SchemaChange::TableAdded { table } => {
+                    marks.tables.insert(owned(table_key(table)), ChangeMark::New);
+                }
+                SchemaChange::TableRemoved { table } => marks.removed_tables.push(table.clone()),
+                SchemaChange::ColumnAdded { column, .. } | SchemaChange::ColumnChanged { after: column, .. } => {
+                    let key = owned(change.table().expect("column changes belong to a table"));
+                    let mark = if matches!(change, SchemaChange::ColumnAdded { .. }) { ChangeMark::New } else { ChangeMark::Changed };
+                    marks.tables.insert(key.clone(), ChangeMark::Changed);
+                    marks.columns.insert((key, column.name.clone()), mark);
+                }
+                SchemaChange::ColumnRemoved { column, .. } => {
+                    let key = owned(change.table().expect("column changes belong to a table"));
+                    marks.tables.insert(key.clone(), ChangeMark::Changed);
+                    marks.removed_columns.entry(key).or_default().push(column.clone());
+                }
+                SchemaChange::WarehouseAdded { .. } | SchemaChange::WarehouseRemoved { .. } | SchemaChange::WarehouseChanged { .. } => {}
+            }
+        }
+        marks
+    }
+
+    pub fn table(&self, table: &TableInfo) -> Option<ChangeMark> {
+        self.tables.get(&owned_key(table)).copied()
+    }
+
+    pub fn column(&self, table: &TableInfo, column: &str) -> Option<ChangeMark> {
+        self.columns.get(&(owned_key(table), column.to_string())).copied()
+    }
+
+    pub fn removed_columns(&self, table: &TableInfo) -> &[ColumnInfo] {
+        self.removed_columns.get(&owned_key(table)).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn removed_tables(&self) -> &[TableInfo] {
+        &self.removed_tables
+    }
+
+    /// E.g. `2 new, 1 changed and 1 removed tables since the previous snapshot`
+    pub fn summary(&self) -> String {
+        let count = |mark: ChangeMark| self.tables.values().filter(|m| **m == mark).count();
+        format!(
+            "{} new, {} changed and {} removed tables since the previous snapshot",
+            count(ChangeMark::New),
+            count(ChangeMark::Changed),
+            self.removed_tables.len()
+        )
+    }
+}
+
+fn owned_key(table: &TableInfo) -> TableKey {
+    (table.database_name.clone(), table.schema_name.clone(), table.table_name.clone())
+}
+
+fn table_key(table: &TableInfo) -> (&str, &str, &str) {
+    (&table.database_name, &table.schema_name, &table.table_name)
+}
+
+/// One line per change: `+` added, `-` removed, `~` changed; breaking changes are marked
+/// `(breaking)`
+pub fn render_text(changes: &[SchemaChange]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        let line = match change {
+            SchemaChange::TableAdded { table } => {
+                format!("+ table  {}.{}.{}", table.database_name, table.schema_name, table.table_name)
+            }
+            SchemaChange::TableRemoved { table } => {
+                format!("- table  {}.{}.{}", table.database_name, table.schema_name, table.table_name)
+            }
+            SchemaChange::ColumnAdded { database, schema, table, column } => {
+                format!("+ column {}.{}.{}.{} {}", database, schema, table, column.name, column.sql_type())
+            }
+            SchemaChange::ColumnRemoved { database, schema, table, column } => {
+                format!("- column {}.{}.{}.{} {}", database, schema, table, column.name, column.sql_type())
+            }
+            SchemaChange::ColumnChanged { database, schema, table, before, after } => {
+                format!("~ column {}.{}.{}.{} {} -> {}", database, schema, table, after.name, describe(before), describe(after))
+            }
+            SchemaChange::WarehouseAdded { warehouse } => {
+                format!("+ warehouse {} {}", warehouse.name, describe_warehouse(warehouse))
+            }
+            SchemaChange::WarehouseRemoved { warehouse } => {
+                format!("- warehouse {} {}", warehouse.name, describe_warehouse(warehouse))
+            }
+            SchemaChange::WarehouseChanged { before, after } => {
+                format!("~ warehouse {} {} -> {}", after.name, describe_warehouse(before), describe_warehouse(after))
+            }
+        };
+        out.push_str(&line);
+        if change.is_breaking() {
+            out.push_str(" (breaking)");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn describe(column: &ColumnInfo) -> String {
+    let mut description = column.sql_type();
+    if !column.is_nullable {
+        description.push_str(" NOT NULL");
+    }
+    if let Some(default) = &column.column_default {
+        description.push_str(&format!(" DEFAULT {}", default));
+    }
+    description
+}
+
+fn describe_warehouse(warehouse: &WarehouseInfo) -> String {
+    let mut description = format!("{} {}", warehouse.size, warehouse.type_);
+    match warehouse.auto_suspend {
+        Some(seconds) => description.push_str(&format!(" AUTO_SUSPEND {}", seconds)),
+        None => description.push_str(" AUTO_SUSPEND NULL"),
+    }
+    if warehouse.auto_resume == Some(false) {
+        description.push_str(" NO AUTO_RESUME");
+    }
+    if let (Some(min), Some(max)) = (warehouse.min_cluster_count, warehouse.max_cluster_count) {
+        description.push_str(&format!(" CLUSTERS {}-{}", min, max));
+    }
+    if let Some(policy) = &warehouse.scaling_policy {
+        description.push_str(&format!(" SCALING_POLICY {}", policy));
+    }
+    description
+}
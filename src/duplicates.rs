@@ -0,0 +1,75 @@
+//! Likely duplicated or copy-pasted tables, found by column-set similarity.
+//!
+//! Two tables are compared by the Jaccard index of their column names (shared columns over
+//! all distinct columns), so `ORDERS` and an `ORDERS_BACKUP_2022` copy with one column
+//! dropped still score high. Column types are compared separately and reported alongside.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{output, TableInfo};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicatePair {
+    /// `database.schema.table`, the wider of the two (or the first in catalog order)
+    pub table: String,
+    pub duplicate: String,
+    /// Jaccard index of the column names, from 0 to 1
+    pub similarity: f64,
+    pub shared_columns: usize,
+    /// Whether every shared column has the same type in both tables
+    pub same_types: bool,
+}
+
+/// Pairs of tables whose column sets are at least `threshold` similar, most similar first.
+/// Tables with fewer than `min_columns` columns are skipped since small tables match trivially.
+pub fn find_duplicates(tables: &[TableInfo], threshold: f64, min_columns: usize) -> Vec<DuplicatePair> {
+    let mut candidates: Vec<(&TableInfo, HashMap<String, String>)> = tables
+        .iter()
+        .filter(|t| t.columns.len() >= min_columns)
+        .map(|t| (t, t.columns.iter().map(|c| (c.name.to_uppercase(), c.sql_type())).collect()))
+        .collect();
+    // Widest first, so a pair can be abandoned once the size ratio alone rules it out
+    candidates.sort_by_key(|(_, columns)| std::cmp::Reverse(columns.len()));
+
+    let mut pairs = Vec::new();
+    for (i, (table, columns)) in candidates.iter().enumerate() {
+        let names: HashSet<&String> = columns.keys().collect();
+        for (other, other_columns) in &candidates[i + 1..] {
+            // Jaccard can't exceed |smaller| / |larger|
+            if (other_columns.len() as f64) < threshold * columns.len() as f64 {
+                break;
+            }
+            let shared: Vec<&String> = other_columns.keys().filter(|name| names.contains(name)).collect();
+            let union = columns.len() + other_columns.len() - shared.len();
+            let similarity = shared.len() as f64 / union as f64;
+            if similarity >= threshold {
+                pairs.push(DuplicatePair {
+                    table: table.qualified_name(),
+                    duplicate: other.qualified_name(),
+                    similarity,
+                    shared_columns: shared.len(),
+                    same_types: shared.iter().all(|name| columns[*name] == other_columns[*name]),
+                });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    pairs
+}
+
+pub fn render_text(pairs: &[DuplicatePair]) -> String {
+    let rows: Vec<[String; 5]> = pairs
+        .iter()
+        .map(|pair| {
+            [
+                pair.table.clone(),
+                pair.duplicate.clone(),
+                format!("{:.2}", pair.similarity),
+                pair.shared_columns.to_string(),
+                if pair.same_types { "yes" } else { "no" }.to_string(),
+            ]
+        })
+        .collect();
+    output::render_table(["TABLE", "LIKELY DUPLICATE", "SIMILARITY", "SHARED", "SAME TYPES"], &rows)
+}
@@ -0,0 +1,63 @@
+//! Dropping sensitive columns from everything the mapper writes.
+//!
+//! Patterns are case-insensitive globs over column names (`*_SSN`, `password*`). A matched
+//! column disappears along with every other mention of its name: primary key entries,
+//! foreign keys on or referencing it, and clustering keys that use it.
+
+use anyhow::Result;
+use regex::{RegexSet, RegexSetBuilder};
+
+use crate::{SnowflakeMapperError, TableInfo};
+
+pub struct ColumnFilter {
+    patterns: RegexSet,
+}
+
+impl ColumnFilter {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let patterns = RegexSetBuilder::new(patterns.iter().map(|pattern| glob_to_regex(pattern)))
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| SnowflakeMapperError::InvalidExpression(format!("--exclude-columns: {}", e)))?;
+        Ok(Self { patterns })
+    }
+
+    pub fn is_excluded(&self, column: &str) -> bool {
+        self.patterns.is_match(column)
+    }
+
+    pub fn apply(&self, tables: Vec<TableInfo>) -> Vec<TableInfo> {
+        tables.into_iter().map(|table| self.apply_table(table)).collect()
+    }
+
+    fn apply_table(&self, mut table: TableInfo) -> TableInfo {
+        let excluded: Vec<String> =
+            table.columns.iter().filter(|c| self.is_excluded(&c.name)).map(|c| c.name.clone()).collect();
+        table.columns.retain(|c| !self.is_excluded(&c.name));
+        table.primary_key.retain(|column| !self.is_excluded(column));
+        table.foreign_keys.retain(|fk| {
+            !fk.columns.iter().chain(&fk.referenced_columns).any(|column| self.is_excluded(column))
+        });
+        if let Some(key) = &table.clustering_key {
+            let key = key.to_uppercase();
+            if excluded.iter().any(|column| key.contains(&column.to_uppercase())) {
+                table.clustering_key = None;
+            }
+        }
+        table
+    }
+}
+
+/// `*` matches any run of characters and `?` a single one; everything else is literal
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
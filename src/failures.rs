@@ -0,0 +1,78 @@
+//! Manifest of databases skipped with `--skip-failed-tables`, for re-running only those later.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::upgrade::FORMAT_VERSION;
+use crate::SnowflakeMapperError;
+
+/// Written next to the catalog files; `catalog::load_snapshot` skips it
+pub const FAILURES_FILE: &str = "failures.json";
+
+/// Where a database failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureStage {
+    /// Fetching its tables from the warehouse
+    Fetch,
+    /// Publishing its schemas with `--push`
+    Publish,
+    /// Writing its output
+    Write,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Failure {
+    pub database: String,
+    pub stage: FailureStage,
+    pub error: String,
+    /// Attempts made, including retries
+    pub attempts: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct FailureManifest {
+    /// Snapshot structure version, see [`crate::upgrade`]
+    #[serde(default = "crate::upgrade::unversioned")]
+    pub format_version: u32,
+    pub failures: Vec<Failure>,
+}
+
+impl Default for FailureManifest {
+    fn default() -> Self {
+        Self { format_version: FORMAT_VERSION, failures: Vec::new() }
+    }
+}
+
+impl FailureManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Failed to read {}: {}", path.display(), e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Failed to parse {}: {}", path.display(), e)).into())
+    }
+
+    /// Failed databases, in manifest order and without duplicates
+    pub fn databases(&self) -> Vec<String> {
+        let mut databases: Vec<String> = Vec::new();
+        for failure in &self.failures {
+            if !databases.contains(&failure.database) {
+                databases.push(failure.database.clone());
+            }
+        }
+        databases
+    }
+}
+
+/// The manifest goes in the output directory, or the working directory when streaming to stdout
+pub fn manifest_path(output_dir: &Path) -> PathBuf {
+    if output_dir.as_os_str() == "-" {
+        PathBuf::from(FAILURES_FILE)
+    } else {
+        output_dir.join(FAILURES_FILE)
+    }
+}
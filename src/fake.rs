@@ -0,0 +1,246 @@
+//! Type-appropriate fake rows for mock databases.
+//!
+//! Values come from a seeded generator, so a snapshot and a seed always produce the same
+//! data. Nullable columns are null about one time in ten. Key columns (primary keys, and
+//! columns a single-column foreign key refers to) count up from 1 so they stay unique, and
+//! foreign keys draw from the same range, so joins between generated tables find matching
+//! rows.
+
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+use crate::hash::fnv1a;
+use crate::mock::{self, ColumnKind};
+use crate::{ColumnInfo, SnowflakeMapperError, TableInfo};
+
+/// How much fake data to generate
+#[derive(Debug, Clone, Copy)]
+pub struct FakeData {
+    /// Rows per table
+    pub rows: usize,
+    pub seed: u64,
+}
+
+/// One generated value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fake {
+    Null,
+    Integer(i64),
+    /// Exact decimal, kept as text so no precision is lost
+    Decimal(String),
+    Real(f64),
+    Boolean(bool),
+    /// Text, and dates, times and JSON in their text form
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// `(database, schema, table, column)` of a key column
+type KeyColumn = (String, String, String, String);
+
+/// Generates rows for the tables of one catalog
+pub struct Faker {
+    data: FakeData,
+    /// Every key column, with its maximum length when it holds text
+    keys: HashMap<KeyColumn, Option<usize>>,
+}
+
+/// How a column's values are chosen
+enum Source {
+    Random,
+    /// The row number, for key columns
+    Sequence,
+    /// A row number of the referenced table, named for text keys by the referenced column
+    /// and fitted to its length
+    Reference(String, Option<usize>),
+}
+
+impl Faker {
+    /// Fails when a key column can't hold `data.rows` distinct values, such as a
+    /// `NUMBER(2,0)` key for more than 99 rows or a boolean key for more than two
+    pub fn new(tables: &[TableInfo], data: FakeData) -> Result<Self> {
+        let mut keys = HashMap::new();
+        let mut too_small = None;
+        let mut insert = |key: KeyColumn| {
+            let column = tables
+                .iter()
+                .find(|t| t.database_name == key.0 && t.schema_name == key.1 && t.table_name == key.2)
+                .and_then(|t| t.columns.iter().find(|c| c.name == key.3));
+            if let Some(capacity) = column.and_then(key_capacity).filter(|&capacity| capacity < data.rows) {
+                too_small.get_or_insert((key.clone(), capacity));
+            }
+            keys.insert(key, column.and_then(text_length));
+        };
+        for table in tables {
+            for column in &table.primary_key {
+                insert((table.database_name.clone(), table.schema_name.clone(), table.table_name.clone(), column.clone()));
+            }
+            for fk in table.foreign_keys.iter().filter(|fk| fk.referenced_columns.len() == 1) {
+                insert((fk.referenced_database.clone(), fk.referenced_schema.clone(), fk.referenced_table.clone(), fk.referenced_columns[0].clone()));
+            }
+        }
+        if let Some(((database, schema, table, column), capacity)) = too_small {
+            return Err(SnowflakeMapperError::OutputError(format!(
+                "Key column {}.{}.{}.{} holds at most {} distinct values, too few for {} rows; lower --with-fake-data",
+                database, schema, table, column, capacity, data.rows
+            ))
+            .into());
+        }
+        Ok(Self { data, keys })
+    }
+
+    /// The rows for `table`, one value per column in column order
+    pub fn rows(&self, table: &TableInfo) -> Vec<Vec<Fake>> {
+        fake_rows(table, self.data, &self.keys)
+    }
+}
+
+fn fake_rows(table: &TableInfo, data: FakeData, keys: &HashMap<KeyColumn, Option<usize>>) -> Vec<Vec<Fake>> {
+    // Seeded per table, so adding a table to the snapshot doesn't change the others
+    let name = table.qualified_name();
+    let mut rng = StdRng::seed_from_u64(data.seed ^ fnv1a(name.as_bytes()));
+    let key = |database: &str, schema: &str, table: &str, column: &str| {
+        keys.get(&(database.to_string(), schema.to_string(), table.to_string(), column.to_string())).copied()
+    };
+    let sources: Vec<Source> = table
+        .columns
+        .iter()
+        .map(|column| {
+            if key(&table.database_name, &table.schema_name, &table.table_name, &column.name).is_some() {
+                return Source::Sequence;
+            }
+            let reference = table
+                .foreign_keys
+                .iter()
+                .find(|fk| fk.columns.len() == 1 && fk.referenced_columns.len() == 1 && fk.columns[0] == column.name);
+            match reference {
+                Some(fk) => {
+                    let length = key(&fk.referenced_database, &fk.referenced_schema, &fk.referenced_table, &fk.referenced_columns[0]).flatten();
+                    Source::Reference(fk.referenced_columns[0].clone(), length)
+                }
+                None => Source::Random,
+            }
+        })
+        .collect();
+
+    (1..=data.rows)
+        .map(|row| {
+            table
+                .columns
+                .iter()
+                .zip(&sources)
+                .map(|(column, source)| match source {
+                    Source::Sequence => key_value(column, &column.name, text_length(column), row, data.rows),
+                    _ if column.is_nullable && rng.gen_ratio(1, 10) => Fake::Null,
+                    Source::Reference(referenced, length) => key_value(column, referenced, *length, rng.gen_range(1..=data.rows), data.rows),
+                    Source::Random => random_value(column, &mut rng),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Distinct values a key column can hold, see [`key_value`]; `None` when there's no
+/// practical limit
+fn key_capacity(column: &ColumnInfo) -> Option<usize> {
+    // The largest number of `digits` digits, `None` when it doesn't fit a usize anyway
+    let numbers = |digits: u32| 10_usize.checked_pow(digits).map(|bound| bound - 1);
+    match mock::column_kind(column) {
+        ColumnKind::Integer { digits } => numbers(digits),
+        ColumnKind::Decimal { precision, scale } => numbers(precision.saturating_sub(scale)),
+        ColumnKind::Boolean => Some(2),
+        ColumnKind::Time => Some(SECONDS_PER_DAY),
+        // Text keys fall back to bare row numbers, which need as many characters as the last one
+        ColumnKind::Text { length: Some(length) } => numbers(u32::try_from(length).unwrap_or(u32::MAX)),
+        _ => None,
+    }
+}
+
+const SECONDS_PER_DAY: usize = 24 * 60 * 60;
+
+/// The `index`th key value of a column, matching the key column `key_column` it is or
+/// refers to. Text keys are named after the key column when `rows` of them fit in its
+/// `length`, and bare row numbers otherwise, so they are never cut short. Booleans, times
+/// and JSON take values valid for their type; [`Faker::new`] has checked there are enough.
+fn key_value(column: &ColumnInfo, key_column: &str, length: Option<usize>, index: usize, rows: usize) -> Fake {
+    let prefix = format!("{}_", key_column.to_lowercase());
+    let named = length.is_none_or(|length| prefix.chars().count() + rows.to_string().len() <= length);
+    match mock::column_kind(column) {
+        ColumnKind::Integer { .. } => Fake::Integer(index as i64),
+        ColumnKind::Decimal { .. } => Fake::Decimal(index.to_string()),
+        ColumnKind::Double => Fake::Real(index as f64),
+        ColumnKind::Boolean => Fake::Boolean(index.is_multiple_of(2)),
+        ColumnKind::Date => Fake::Text(date(index as i64).format("%Y-%m-%d").to_string()),
+        ColumnKind::Timestamp | ColumnKind::TimestampTz => Fake::Text(date(index as i64).format("%Y-%m-%d 00:00:00").to_string()),
+        ColumnKind::Time => {
+            let second = (index - 1) % SECONDS_PER_DAY;
+            Fake::Text(format!("{:02}:{:02}:{:02}", second / 3600, second / 60 % 60, second % 60))
+        }
+        ColumnKind::Binary => Fake::Blob((index as i64).to_be_bytes().to_vec()),
+        // A bare number is a valid JSON document
+        ColumnKind::Json => Fake::Text(index.to_string()),
+        ColumnKind::Text { .. } => Fake::Text(if named { format!("{}{}", prefix, index) } else { index.to_string() }),
+    }
+}
+
+fn random_value(column: &ColumnInfo, rng: &mut StdRng) -> Fake {
+    match mock::column_kind(column) {
+        ColumnKind::Integer { digits } => Fake::Integer(rng.gen_range(0..max_for(digits.min(6)))),
+        ColumnKind::Decimal { precision, scale } => {
+            // Whole digits are what the full scale leaves; fewer fraction digits always fit
+            let whole_digits = precision.saturating_sub(scale).min(6);
+            let whole = if whole_digits == 0 { 0 } else { rng.gen_range(0..max_for(whole_digits)) };
+            let fraction_digits = scale.min(precision).min(6);
+            if fraction_digits == 0 {
+                Fake::Decimal(whole.to_string())
+            } else {
+                let fraction = rng.gen_range(0..max_for(fraction_digits));
+                Fake::Decimal(format!("{}.{:0width$}", whole, fraction, width = fraction_digits as usize))
+            }
+        }
+        ColumnKind::Double => Fake::Real((rng.gen_range(0.0..10_000.0_f64) * 100.0).round() / 100.0),
+        ColumnKind::Boolean => Fake::Boolean(rng.gen()),
+        ColumnKind::Binary => Fake::Blob((0..8).map(|_| rng.gen()).collect()),
+        ColumnKind::Date => Fake::Text(date(rng.gen_range(0..2000)).format("%Y-%m-%d").to_string()),
+        ColumnKind::Time => Fake::Text(format!("{:02}:{:02}:{:02}", rng.gen_range(0..24), rng.gen_range(0..60), rng.gen_range(0..60))),
+        ColumnKind::Timestamp | ColumnKind::TimestampTz => {
+            let at = date(rng.gen_range(0..2000)).and_hms_opt(rng.gen_range(0..24), rng.gen_range(0..60), rng.gen_range(0..60));
+            Fake::Text(at.expect("valid time").format("%Y-%m-%d %H:%M:%S").to_string())
+        }
+        ColumnKind::Json => Fake::Text(format!("{{\"id\": {}, \"flag\": {}}}", rng.gen_range(0..100_000), rng.gen::<bool>())),
+        ColumnKind::Text { .. } => {
+            let word = WORDS[rng.gen_range(0..WORDS.len())];
+            Fake::Text(fit(format!("{} {}", word, rng.gen_range(0..10_000)), column))
+        }
+    }
+}
+
+const WORDS: &[&str] = &["alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliet"];
+
+/// `10^digits`, the exclusive bound of a whole number with `digits` digits
+fn max_for(digits: u32) -> i64 {
+    10_i64.pow(digits.max(1))
+}
+
+/// Days after 2020-01-01
+fn date(days: i64) -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(2020, 1, 1).expect("valid date") + chrono::Duration::days(days)
+}
+
+/// The maximum length of a text column
+fn text_length(column: &ColumnInfo) -> Option<usize> {
+    match mock::column_kind(column) {
+        ColumnKind::Text { length } => length,
+        _ => None,
+    }
+}
+
+/// Cut text to the column's maximum length
+fn fit(text: String, column: &ColumnInfo) -> String {
+    match text_length(column) {
+        Some(length) => text.chars().take(length).collect(),
+        None => text,
+    }
+}
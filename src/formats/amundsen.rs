@@ -0,0 +1,71 @@
+//! CSV files for Amundsen's databuilder.
+//!
+//! `table.csv` and `column.csv` are the inputs of databuilder's `CsvTableColumnExtractor`,
+//! `table_owner.csv` of `CsvTableOwnerExtractor`. Keys follow databuilder's Snowflake
+//! extractor: the source is `snowflake`, the cluster is the Snowflake database, and names
+//! are lower-cased, so the tables line up with metadata extracted there.
+
+use std::fmt::Write;
+
+use crate::output::csv_field;
+use crate::{TableInfo, TableKind};
+
+const SOURCE: &str = "snowflake";
+
+/// One row per table: `database,cluster,schema,name,description,tags,is_view,description_source`
+pub fn render_tables(tables: &[TableInfo]) -> String {
+    let mut out = String::from("database,cluster,schema,name,description,tags,is_view,description_source\n");
+    for table in tables {
+        let is_view = matches!(table.kind(), TableKind::View | TableKind::Materialized);
+        // No tags, and no description source, so comments show as the table's own description
+        let _ = writeln!(out, "{},{},,{},", key(table), csv_field(table.comment.as_deref().unwrap_or_default()), is_view);
+    }
+    out
+}
+
+/// One row per column: `name,description,col_type,sort_order,database,cluster,schema,table_name`
+pub fn render_columns(tables: &[TableInfo]) -> String {
+    let mut out = String::from("name,description,col_type,sort_order,database,cluster,schema,table_name\n");
+    for table in tables {
+        for (position, column) in table.columns.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{}",
+                csv_field(&column.name.to_lowercase()),
+                csv_field(column.comment.as_deref().unwrap_or_default()),
+                csv_field(&column.sql_type().to_lowercase()),
+                position + 1,
+                key(table),
+            );
+        }
+    }
+    out
+}
+
+/// One row per table with `--owners` teams: `db_name,schema,cluster,table_name,owners`
+pub fn render_owners(tables: &[TableInfo]) -> String {
+    let mut out = String::from("db_name,schema,cluster,table_name,owners\n");
+    for table in tables.iter().filter(|table| !table.owners.is_empty()) {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{}",
+            SOURCE,
+            csv_field(&table.schema_name.to_lowercase()),
+            csv_field(&table.database_name.to_lowercase()),
+            csv_field(&table.table_name.to_lowercase()),
+            csv_field(&table.owners.join(",")),
+        );
+    }
+    out
+}
+
+/// `database,cluster,schema,name` of a table
+fn key(table: &TableInfo) -> String {
+    format!(
+        "{},{},{},{}",
+        SOURCE,
+        csv_field(&table.database_name.to_lowercase()),
+        csv_field(&table.schema_name.to_lowercase()),
+        csv_field(&table.table_name.to_lowercase()),
+    )
+}
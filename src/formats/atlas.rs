@@ -0,0 +1,226 @@
+//! Apache Atlas entities for the tables of a database.
+//!
+//! With the Hive model, tables become the built-in `hive_db`, `hive_table` and
+//! `hive_column` entities, which Atlas understands out of the box; Snowflake schemas are
+//! folded into the table names. The Snowflake model keeps all three levels as
+//! `snowflake_database`, `snowflake_schema`, `snowflake_table` and `snowflake_column`, whose
+//! type definitions have to be registered first ([`typedefs`]).
+//!
+//! Qualified names are lower-cased and end in `@<cluster>`, as Atlas' own hooks write them,
+//! so entities are updated in place when a catalog is sent again.
+
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+use crate::{ColumnInfo, TableInfo, TableKind};
+
+/// File `--format atlas` writes the Snowflake model's type definitions to
+pub const TYPEDEFS_FILE: &str = "atlas_typedefs.json";
+
+/// Entity types tables are described with
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasModel {
+    /// Built-in `hive_db`, `hive_table` and `hive_column` types
+    Hive,
+    /// Custom `snowflake_*` types, keeping schemas as entities of their own
+    Snowflake,
+}
+
+/// `{"entities": [...]}` for `tables`, all from `database`, as accepted by
+/// `POST /api/atlas/v2/entity/bulk`. Entities refer to each other by negative placeholder
+/// guids, so a document has to be sent whole.
+pub fn render_entities(database: &str, tables: &[TableInfo], model: AtlasModel, cluster: &str) -> Value {
+    let mut entities = Entities::default();
+    let (database_type, table_type, column_type) = model.types();
+    let database_name = |name: &str| match model {
+        AtlasModel::Hive => name.to_lowercase(),
+        AtlasModel::Snowflake => name.to_string(),
+    };
+    let database_ref = entities.add(
+        database_type,
+        attributes([
+            ("qualifiedName", json!(format!("{}@{}", database.to_lowercase(), cluster))),
+            ("name", json!(database_name(database))),
+            ("clusterName", json!(cluster)),
+        ]),
+        Map::new(),
+    );
+
+    let mut schemas: Vec<(&str, Value)> = Vec::new();
+    for table in tables {
+        let schema = table.schema_name.as_str();
+        let table_qualified_name = format!("{}.{}.{}", database, schema, table.table_name).to_lowercase();
+        let parent = match model {
+            AtlasModel::Hive => ("db", database_ref.clone()),
+            AtlasModel::Snowflake => {
+                let schema_ref = match schemas.iter().find(|(name, _)| *name == schema) {
+                    Some((_, schema_ref)) => schema_ref.clone(),
+                    None => {
+                        let schema_ref = entities.add(
+                            "snowflake_schema",
+                            attributes([
+                                ("qualifiedName", json!(format!("{}.{}@{}", database, schema, cluster).to_lowercase())),
+                                ("name", json!(schema)),
+                            ]),
+                            relationships([("database", database_ref.clone())]),
+                        );
+                        schemas.push((schema, schema_ref.clone()));
+                        schema_ref
+                    }
+                };
+                ("schema", schema_ref)
+            }
+        };
+        let table_ref = entities.add(
+            table_type,
+            table_attributes(table, model, format!("{}@{}", table_qualified_name, cluster)),
+            relationships([parent]),
+        );
+        for (position, column) in table.columns.iter().enumerate() {
+            entities.add(
+                column_type,
+                column_attributes(column, model, position, format!("{}.{}@{}", table_qualified_name, column.name.to_lowercase(), cluster)),
+                relationships([("table", table_ref.clone())]),
+            );
+        }
+    }
+    json!({ "entities": entities.entities })
+}
+
+impl AtlasModel {
+    /// Type names of databases, tables and columns
+    fn types(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            AtlasModel::Hive => ("hive_db", "hive_table", "hive_column"),
+            AtlasModel::Snowflake => ("snowflake_database", "snowflake_table", "snowflake_column"),
+        }
+    }
+}
+
+fn table_attributes(table: &TableInfo, model: AtlasModel, qualified_name: String) -> Map<String, Value> {
+    let kind = table.kind();
+    let mut attributes = attributes([
+        ("qualifiedName", json!(qualified_name)),
+        ("description", json!(table.comment)),
+    ]);
+    if !table.owners.is_empty() {
+        attributes.insert("owner".to_string(), json!(table.owners.join(",")));
+    }
+    match model {
+        AtlasModel::Hive => {
+            let table_type = match kind {
+                TableKind::View | TableKind::Materialized => "VIRTUAL_VIEW",
+                TableKind::External => "EXTERNAL_TABLE",
+                _ => "MANAGED_TABLE",
+            };
+            attributes.insert("name".to_string(), json!(format!("{}.{}", table.schema_name, table.table_name).to_lowercase()));
+            attributes.insert("comment".to_string(), json!(table.comment));
+            attributes.insert("tableType".to_string(), json!(table_type));
+            attributes.insert("temporary".to_string(), json!(kind == TableKind::Temporary));
+        }
+        AtlasModel::Snowflake => {
+            attributes.insert("name".to_string(), json!(table.table_name));
+            attributes.insert("tableType".to_string(), json!(table.table_type));
+            attributes.insert("clusteringKey".to_string(), json!(table.clustering_key));
+            attributes.insert("retentionTimeInDays".to_string(), json!(table.retention_time_in_days));
+        }
+    }
+    attributes.retain(|_, value| !value.is_null());
+    attributes
+}
+
+fn column_attributes(column: &ColumnInfo, model: AtlasModel, position: usize, qualified_name: String) -> Map<String, Value> {
+    match model {
+        AtlasModel::Hive => attributes([
+            ("qualifiedName", json!(qualified_name)),
+            ("name", json!(column.name.to_lowercase())),
+            ("type", json!(column.sql_type().to_lowercase())),
+            ("comment", json!(column.comment)),
+            ("description", json!(column.comment)),
+            ("position", json!(position)),
+        ]),
+        AtlasModel::Snowflake => attributes([
+            ("qualifiedName", json!(qualified_name)),
+            ("name", json!(column.name)),
+            ("dataType", json!(column.sql_type())),
+            ("isNullable", json!(column.is_nullable)),
+            ("position", json!(position + 1)),
+            ("defaultValue", json!(column.column_default)),
+            ("description", json!(column.comment)),
+        ]),
+    }
+}
+
+/// Entities of one document, numbered with placeholder guids
+#[derive(Default)]
+struct Entities {
+    entities: Vec<Value>,
+}
+
+impl Entities {
+    /// Add an entity, returning a reference to it for other entities' relationships
+    fn add(&mut self, type_name: &str, attributes: Map<String, Value>, relationships: Map<String, Value>) -> Value {
+        let guid = format!("-{}", self.entities.len() + 1);
+        let mut entity = json!({ "typeName": type_name, "guid": guid, "attributes": attributes });
+        if !relationships.is_empty() {
+            entity["relationshipAttributes"] = Value::Object(relationships);
+        }
+        self.entities.push(entity);
+        json!({ "typeName": type_name, "guid": guid })
+    }
+}
+
+/// Attributes without the unset ones
+fn attributes<const N: usize>(pairs: [(&str, Value); N]) -> Map<String, Value> {
+    pairs.into_iter().filter(|(_, value)| !value.is_null()).map(|(key, value)| (key.to_string(), value)).collect()
+}
+
+fn relationships<const N: usize>(pairs: [(&str, Value); N]) -> Map<String, Value> {
+    pairs.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+}
+
+/// Type definitions of the Snowflake model, for `POST /api/atlas/v2/types/typedefs`
+pub fn typedefs() -> Value {
+    let attribute = |name: &str, type_name: &str| {
+        json!({ "name": name, "typeName": type_name, "isOptional": true, "cardinality": "SINGLE", "isUnique": false, "isIndexable": false })
+    };
+    let entity = |name: &str, super_type: &str, attributes: Vec<Value>| {
+        json!({ "name": name, "superTypes": [super_type], "serviceType": "snowflake", "typeVersion": "1.0", "attributeDefs": attributes })
+    };
+    let containment = |parent: &str, children: &str, child: &str, parent_attribute: &str| {
+        json!({
+            "name": format!("{}_{}", parent, children),
+            "serviceType": "snowflake",
+            "typeVersion": "1.0",
+            "relationshipCategory": "COMPOSITION",
+            "propagateTags": "NONE",
+            "endDef1": { "type": parent, "name": children, "isContainer": true, "cardinality": "SET", "isLegacyAttribute": false },
+            "endDef2": { "type": child, "name": parent_attribute, "isContainer": false, "cardinality": "SINGLE", "isLegacyAttribute": false },
+        })
+    };
+    json!({
+        "enumDefs": [],
+        "structDefs": [],
+        "classificationDefs": [],
+        "entityDefs": [
+            entity("snowflake_database", "Asset", vec![attribute("clusterName", "string")]),
+            entity("snowflake_schema", "Asset", vec![]),
+            entity("snowflake_table", "DataSet", vec![
+                attribute("tableType", "string"),
+                attribute("clusteringKey", "string"),
+                attribute("retentionTimeInDays", "int"),
+            ]),
+            entity("snowflake_column", "DataSet", vec![
+                attribute("dataType", "string"),
+                attribute("isNullable", "boolean"),
+                attribute("position", "int"),
+                attribute("defaultValue", "string"),
+            ]),
+        ],
+        "relationshipDefs": [
+            containment("snowflake_database", "schemas", "snowflake_schema", "database"),
+            containment("snowflake_schema", "tables", "snowflake_table", "schema"),
+            containment("snowflake_table", "columns", "snowflake_column", "table"),
+        ],
+    })
+}
@@ -0,0 +1,89 @@
+use serde_json::{json, Value};
+
+use crate::types::SnowflakeType;
+use crate::{ColumnInfo, TableInfo};
+
+/// Avro record schema for a table. Nullable columns become `["null", T]` unions
+/// defaulting to null; semi-structured and geospatial columns are carried as JSON strings.
+pub fn table_schema(table: &TableInfo) -> Value {
+    let fields: Vec<Value> = table
+        .columns
+        .iter()
+        .map(|column| {
+            let mut field = json!({
+                "name": avro_name(&column.name),
+                "type": field_type(column),
+            });
+            if column.is_nullable {
+                field["default"] = Value::Null;
+            }
+            if let Some(comment) = &column.comment {
+                field["doc"] = json!(comment);
+            }
+            if avro_name(&column.name) != column.name {
+                field["aliases"] = json!([column.name]);
+            }
+            field
+        })
+        .collect();
+
+    let mut schema = json!({
+        "type": "record",
+        "name": avro_name(&table.table_name),
+        "namespace": format!("{}.{}", avro_name(&table.database_name), avro_name(&table.schema_name)).to_lowercase(),
+        "fields": fields,
+    });
+    if let Some(comment) = &table.comment {
+        schema["doc"] = json!(comment);
+    }
+    schema
+}
+
+fn field_type(column: &ColumnInfo) -> Value {
+    let base = base_type(column);
+    if column.is_nullable {
+        json!(["null", base])
+    } else {
+        base
+    }
+}
+
+fn base_type(column: &ColumnInfo) -> Value {
+    let column_type = column.snowflake_type();
+    // Millisecond logical types when no finer precision is kept
+    let unit = if column_type.datetime_precision().is_some_and(|p| p <= 3) { "millis" } else { "micros" };
+    match column_type {
+        SnowflakeType::Number { precision, scale: 0 } if precision <= 9 => json!("int"),
+        SnowflakeType::Number { precision, scale: 0 } if precision <= 18 => json!("long"),
+        SnowflakeType::Number { precision, scale } => json!({
+            "type": "bytes",
+            "logicalType": "decimal",
+            "precision": precision,
+            "scale": scale,
+        }),
+        SnowflakeType::Float => json!("double"),
+        SnowflakeType::Boolean => json!("boolean"),
+        SnowflakeType::Date => json!({"type": "int", "logicalType": "date"}),
+        SnowflakeType::Time { .. } if unit == "millis" => json!({"type": "int", "logicalType": "time-millis"}),
+        SnowflakeType::Time { .. } => json!({"type": "long", "logicalType": "time-micros"}),
+        SnowflakeType::TimestampNtz { .. } => json!({"type": "long", "logicalType": format!("local-timestamp-{}", unit)}),
+        SnowflakeType::TimestampLtz { .. } | SnowflakeType::TimestampTz { .. } | SnowflakeType::Timestamp { .. } => {
+            json!({"type": "long", "logicalType": format!("timestamp-{}", unit)})
+        }
+        SnowflakeType::Binary { .. } => json!("bytes"),
+        _ => json!("string"),
+    }
+}
+
+/// Avro names must match `[A-Za-z_][A-Za-z0-9_]*`
+fn avro_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) || sanitized.is_empty() {
+        format!("_{}", sanitized)
+    } else {
+        sanitized
+    }
+}
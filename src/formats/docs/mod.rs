@@ -0,0 +1,270 @@
+//! Static HTML documentation site rendered from a snapshot.
+//!
+//! Given the changes since an earlier snapshot, tables and columns carry new, changed or
+//! removed badges, and tables dropped since then are listed on the index.
+
+use anyhow::Result;
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::group_by_database;
+use crate::hash::fnv1a;
+use crate::diff::{ChangeMark, ChangeMarks};
+use crate::output::write_text_output;
+use crate::{ColumnInfo, SnowflakeMapperError, TableInfo};
+
+const INDEX_TEMPLATE: &str = include_str!("index.html.hbs");
+const TABLE_TEMPLATE: &str = include_str!("table.html.hbs");
+const STYLESHEET: &str = include_str!("style.css");
+
+#[derive(Serialize)]
+struct IndexContext<'a> {
+    table_count: usize,
+    database_count: usize,
+    databases: Vec<IndexDatabase<'a>>,
+    changes: Option<String>,
+    removed_tables: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct IndexDatabase<'a> {
+    name: &'a str,
+    anchor: String,
+    schemas: Vec<IndexSchema<'a>>,
+}
+
+#[derive(Serialize)]
+struct IndexSchema<'a> {
+    name: &'a str,
+    tables: Vec<IndexTable<'a>>,
+}
+
+#[derive(Serialize)]
+struct IndexTable<'a> {
+    name: &'a str,
+    href: String,
+    column_count: usize,
+    comment: Option<&'a str>,
+    change: Option<&'static str>,
+    /// Lower-cased table and column names matched by the client-side search box
+    search: String,
+}
+
+#[derive(Serialize)]
+struct TableContext<'a> {
+    database: &'a str,
+    database_anchor: String,
+    schema: &'a str,
+    root: &'static str,
+    table: &'a TableInfo,
+    change: Option<&'static str>,
+    columns: Vec<ColumnContext<'a>>,
+    foreign_keys: Vec<ForeignKeyContext<'a>>,
+}
+
+#[derive(Serialize)]
+struct ColumnContext<'a> {
+    name: &'a str,
+    data_type: &'a str,
+    is_nullable: bool,
+    comment: Option<&'a str>,
+    primary_key: bool,
+    change: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct ForeignKeyContext<'a> {
+    name: &'a str,
+    columns: String,
+    target: String,
+    href: Option<String>,
+    referenced_columns: String,
+}
+
+/// Table pages live two directories deep (`<database>/<schema>/<table>.html`)
+const TABLE_PAGE_ROOT: &str = "../../";
+
+/// Render `tables` into a static site under `out_dir`, returning the number of table pages
+/// written. `changes` are marked on the pages when given.
+pub async fn render_site(tables: &[TableInfo], out_dir: &Path, changes: Option<&ChangeMarks>) -> Result<usize> {
+    let mut registry = Handlebars::new();
+    registry
+        .register_template_string("index", INDEX_TEMPLATE)
+        .and_then(|_| registry.register_template_string("table", TABLE_TEMPLATE))
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Invalid docs template: {}", e)))?;
+
+    let pages = PageSlugs::new(tables);
+    for table in tables {
+        let context = table_context(table, &pages, changes);
+        let page = render(&registry, "table", &context)?;
+        write_text_output(out_dir.join(pages.table_href(table)), &page).await?;
+    }
+
+    let databases: Vec<IndexDatabase> = group_by_database(tables)
+        .into_iter()
+        .map(|(database, tables)| IndexDatabase {
+            name: database,
+            anchor: pages.databases[database].clone(),
+            schemas: group_by_schema(&tables, &pages, changes),
+        })
+        .collect();
+    let index = IndexContext {
+        table_count: tables.len(),
+        database_count: databases.len(),
+        databases,
+        changes: changes.map(ChangeMarks::summary),
+        removed_tables: changes
+            .map_or(&[][..], |c| c.removed_tables())
+            .iter()
+            .map(TableInfo::qualified_name)
+            .collect(),
+    };
+    write_text_output(out_dir.join("index.html"), &render(&registry, "index", &index)?).await?;
+    write_text_output(out_dir.join("style.css"), STYLESHEET).await?;
+
+    Ok(tables.len())
+}
+
+fn render(registry: &Handlebars, name: &str, context: &impl Serialize) -> Result<String> {
+    registry
+        .render(name, context)
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to render {} page: {}", name, e)).into())
+}
+
+fn group_by_schema<'a>(tables: &[&'a TableInfo], pages: &PageSlugs, changes: Option<&ChangeMarks>) -> Vec<IndexSchema<'a>> {
+    let mut schemas: Vec<IndexSchema<'a>> = Vec::new();
+    for table in tables {
+        let entry = IndexTable {
+            name: &table.table_name,
+            href: pages.table_href(table),
+            column_count: table.columns.len(),
+            comment: table.comment.as_deref(),
+            change: label(changes.and_then(|c| c.table(table))),
+            search: std::iter::once(table.qualified_name())
+                .chain(table.columns.iter().map(|c| c.name.clone()))
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase(),
+        };
+        match schemas.iter_mut().find(|s| s.name == table.schema_name) {
+            Some(schema) => schema.tables.push(entry),
+            None => schemas.push(IndexSchema {
+                name: &table.schema_name,
+                tables: vec![entry],
+            }),
+        }
+    }
+    schemas
+}
+
+fn table_context<'a>(
+    table: &'a TableInfo,
+    pages: &PageSlugs,
+    changes: Option<&'a ChangeMarks>,
+) -> TableContext<'a> {
+    let column_context = |column: &'a ColumnInfo, change: Option<ChangeMark>| ColumnContext {
+        name: &column.name,
+        data_type: &column.data_type,
+        is_nullable: column.is_nullable,
+        comment: column.comment.as_deref(),
+        primary_key: table.primary_key.contains(&column.name),
+        change: label(change),
+    };
+    // Dropped columns stay listed, after the current ones
+    let removed = changes.map_or(&[][..], |c| c.removed_columns(table));
+    let columns = table
+        .columns
+        .iter()
+        .map(|column| column_context(column, changes.and_then(|c| c.column(table, &column.name))))
+        .chain(removed.iter().map(|column| column_context(column, Some(ChangeMark::Removed))))
+        .collect();
+
+    let foreign_keys = table
+        .foreign_keys
+        .iter()
+        .map(|fk| {
+            let target_key = (
+                fk.referenced_database.as_str(),
+                fk.referenced_schema.as_str(),
+                fk.referenced_table.as_str(),
+            );
+            ForeignKeyContext {
+                name: &fk.name,
+                columns: fk.columns.join(", "),
+                target: format!("{}.{}.{}", fk.referenced_database, fk.referenced_schema, fk.referenced_table),
+                href: pages.tables.get(&target_key).map(|href| format!("{}{}", TABLE_PAGE_ROOT, href)),
+                referenced_columns: fk.referenced_columns.join(", "),
+            }
+        })
+        .collect();
+
+    TableContext {
+        database: &table.database_name,
+        database_anchor: pages.databases[table.database_name.as_str()].clone(),
+        schema: &table.schema_name,
+        root: TABLE_PAGE_ROOT,
+        table,
+        change: label(changes.and_then(|c| c.table(table))),
+        columns,
+        foreign_keys,
+    }
+}
+
+fn label(mark: Option<ChangeMark>) -> Option<&'static str> {
+    mark.map(ChangeMark::label)
+}
+
+/// Database anchors and table page paths, unique among the tables of a site
+struct PageSlugs<'a> {
+    databases: HashMap<&'a str, String>,
+    /// `<database>/<schema>/<table>.html` by database, schema and table name
+    tables: HashMap<(&'a str, &'a str, &'a str), String>,
+}
+
+impl<'a> PageSlugs<'a> {
+    fn new(tables: &'a [TableInfo]) -> Self {
+        let databases = unique_slugs(tables.iter().map(|t| t.database_name.as_str()));
+        let mut pages = HashMap::new();
+        for (database, database_slug) in &databases {
+            let in_database = || tables.iter().filter(move |t| t.database_name == *database);
+            for (schema, schema_slug) in unique_slugs(in_database().map(|t| t.schema_name.as_str())) {
+                let names = in_database().filter(|t| t.schema_name == schema).map(|t| t.table_name.as_str());
+                for (table, table_slug) in unique_slugs(names) {
+                    pages.insert((*database, schema, table), format!("{}/{}/{}.html", database_slug, schema_slug, table_slug));
+                }
+            }
+        }
+        Self { databases, tables: pages }
+    }
+
+    fn table_href(&self, table: &TableInfo) -> String {
+        self.tables[&(table.database_name.as_str(), table.schema_name.as_str(), table.table_name.as_str())].clone()
+    }
+}
+
+/// A [`slug`] for each of `names`. Names whose slugs would collide, also when compared
+/// ignoring case as on macOS and Windows file systems, get a hash of the name appended after
+/// a `.`, which no slug contains otherwise.
+fn unique_slugs<'a>(names: impl Iterator<Item = &'a str>) -> HashMap<&'a str, String> {
+    let slugs: HashMap<&str, String> = names.map(|name| (name, slug(name))).collect();
+    let mut taken: HashMap<String, usize> = HashMap::new();
+    for slug in slugs.values() {
+        *taken.entry(slug.to_lowercase()).or_default() += 1;
+    }
+    slugs
+        .into_iter()
+        .map(|(name, slug)| match taken[&slug.to_lowercase()] {
+            1 => (name, slug),
+            _ => (name, format!("{}.{:08x}", slug, fnv1a(name.as_bytes()) as u32)),
+        })
+        .collect()
+}
+
+/// Make an identifier safe to use as a path segment or URL fragment
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '$') { c } else { '_' })
+        .collect()
+}
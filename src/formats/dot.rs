@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::TableInfo;
+
+/// Render tables as a Graphviz digraph: one cluster per schema, one node per table and
+/// an edge from each referencing table to the table its foreign key points at.
+///
+/// Foreign keys that target tables outside `tables` are drawn as dashed external nodes.
+pub fn render_graph(name: &str, tables: &[TableInfo]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph {} {{", quote(name));
+    let _ = writeln!(out, "  graph [rankdir=LR, fontname=\"Helvetica\"];");
+    let _ = writeln!(out, "  node [shape=box, style=rounded, fontname=\"Helvetica\"];");
+    let _ = writeln!(out, "  edge [fontname=\"Helvetica\", fontsize=10];");
+
+    let mut clusters: Vec<(&str, &str, Vec<&TableInfo>)> = Vec::new();
+    for table in tables {
+        match clusters
+            .iter_mut()
+            .find(|(db, schema, _)| *db == table.database_name && *schema == table.schema_name)
+        {
+            Some((_, _, members)) => members.push(table),
+            None => clusters.push((&table.database_name, &table.schema_name, vec![table])),
+        }
+    }
+
+    for (index, (database, schema, members)) in clusters.iter().enumerate() {
+        let _ = writeln!(out, "  subgraph cluster_{} {{", index);
+        let _ = writeln!(out, "    label={};", quote(&format!("{}.{}", database, schema)));
+        for table in members {
+            let _ = writeln!(
+                out,
+                "    {} [label={}];",
+                quote(&node_id(&table.database_name, &table.schema_name, &table.table_name)),
+                quote(&format!("{}\n{} columns", table.table_name, table.columns.len())),
+            );
+        }
+        let _ = writeln!(out, "  }}");
+    }
+
+    let known: HashSet<String> = tables
+        .iter()
+        .map(|t| node_id(&t.database_name, &t.schema_name, &t.table_name))
+        .collect();
+    let mut external: HashSet<String> = HashSet::new();
+    for table in tables {
+        let from = node_id(&table.database_name, &table.schema_name, &table.table_name);
+        for fk in &table.foreign_keys {
+            let to = node_id(&fk.referenced_database, &fk.referenced_schema, &fk.referenced_table);
+            if !known.contains(&to) && external.insert(to.clone()) {
+                let _ = writeln!(out, "  {} [style=\"rounded,dashed\"];", quote(&to));
+            }
+            let _ = writeln!(
+                out,
+                "  {} -> {} [label={}];",
+                quote(&from),
+                quote(&to),
+                quote(&fk.columns.join(", ")),
+            );
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn node_id(database: &str, schema: &str, table: &str) -> String {
+    format!("{}.{}.{}", database, schema, table)
+}
+
+/// Quote a DOT identifier, escaping embedded quotes and turning newlines into DOT line breaks
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
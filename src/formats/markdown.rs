@@ -0,0 +1,102 @@
+use std::fmt::Write;
+
+use crate::diff::{ChangeMark, ChangeMarks};
+use crate::TableInfo;
+
+/// Render one database as a Markdown data dictionary: a table of contents grouped by
+/// schema followed by a column table per table. With `changes`, tables and columns that are
+/// new, changed or removed since an earlier snapshot are marked.
+pub fn render_database(database: &str, tables: &[TableInfo], changes: Option<&ChangeMarks>) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", database);
+    let _ = writeln!(out, "{} tables\n", tables.len());
+    if let Some(changes) = changes {
+        let _ = writeln!(out, "{}\n", changes.summary());
+    }
+
+    let _ = writeln!(out, "## Contents\n");
+    let mut current_schema: Option<&str> = None;
+    for table in tables {
+        if current_schema != Some(table.schema_name.as_str()) {
+            let _ = writeln!(out, "- **{}**", table.schema_name);
+            current_schema = Some(&table.schema_name);
+        }
+        let heading = table_heading(table);
+        let mark = badge(changes.and_then(|c| c.table(table)));
+        let _ = writeln!(out, "  - [{}](#{}){}", table.table_name, github_anchor(&heading), mark);
+    }
+    out.push('\n');
+
+    for table in tables {
+        let _ = writeln!(out, "## {}\n", table_heading(table));
+        if let Some(mark) = changes.and_then(|c| c.table(table)) {
+            let _ = writeln!(out, "{}\n", badge(Some(mark)).trim_start());
+        }
+        if let Some(comment) = &table.comment {
+            let _ = writeln!(out, "{}\n", comment);
+        }
+        let _ = writeln!(out, "| Column | Type | Nullable | Comment |");
+        let _ = writeln!(out, "| --- | --- | --- | --- |");
+        for column in &table.columns {
+            let key = if table.primary_key.contains(&column.name) { " (PK)" } else { "" };
+            let _ = writeln!(
+                out,
+                "| `{}`{}{} | `{}` | {} | {} |",
+                column.name,
+                key,
+                badge(changes.and_then(|c| c.column(table, &column.name))),
+                column.data_type,
+                if column.is_nullable { "yes" } else { "no" },
+                escape_cell(column.comment.as_deref().unwrap_or("")),
+            );
+        }
+        for column in changes.map_or(&[][..], |c| c.removed_columns(table)) {
+            let _ = writeln!(
+                out,
+                "| ~~`{}`~~{} | `{}` | {} | {} |",
+                column.name,
+                badge(Some(ChangeMark::Removed)),
+                column.data_type,
+                if column.is_nullable { "yes" } else { "no" },
+                escape_cell(column.comment.as_deref().unwrap_or("")),
+            );
+        }
+        out.push('\n');
+    }
+
+    let removed = changes.map_or(&[][..], |c| c.removed_tables());
+    if !removed.is_empty() {
+        let _ = writeln!(out, "## Removed tables\n");
+        for table in removed {
+            let _ = writeln!(out, "- ~~{}~~", table_heading(table));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// ` **new**` and the like, appended to a table or column name
+fn badge(mark: Option<ChangeMark>) -> String {
+    mark.map(|mark| format!(" **{}**", mark.label())).unwrap_or_default()
+}
+
+fn table_heading(table: &TableInfo) -> String {
+    format!("{}.{}", table.schema_name, table.table_name)
+}
+
+/// Anchor GitHub (and most wiki renderers) generate for a heading
+fn github_anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,311 @@
+use std::fmt::Write;
+
+use crate::diff::SchemaChange;
+use crate::formats::quote_identifier;
+use crate::{ColumnInfo, TableInfo};
+
+/// Render changes as a Flyway versioned migration: plain Snowflake DDL, one statement per change.
+///
+/// Views are skipped because the catalog doesn't capture their definitions. Collation and
+/// identity changes can't be altered in place, so they produce no statement.
+pub fn render_flyway(changes: &[SchemaChange]) -> String {
+    let mut out = String::from("-- Generated by snowflake-mapper\n\n");
+    for change in changes.iter().filter(|change| is_migratable(change)) {
+        for statement in sql_statements(change) {
+            let _ = writeln!(out, "{};\n", statement);
+        }
+    }
+    out
+}
+
+/// Render changes as a Liquibase XML changelog with one changeSet per change
+pub fn render_liquibase(changes: &[SchemaChange], id_prefix: &str) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<databaseChangeLog\n",
+        "    xmlns=\"http://www.liquibase.org/xml/ns/dbchangelog\"\n",
+        "    xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"\n",
+        "    xsi:schemaLocation=\"http://www.liquibase.org/xml/ns/dbchangelog ",
+        "http://www.liquibase.org/xml/ns/dbchangelog/dbchangelog-latest.xsd\">\n",
+    ));
+
+    for (index, change) in changes.iter().filter(|change| is_migratable(change)).enumerate() {
+        let _ = writeln!(
+            out,
+            "\n  <changeSet id=\"{}-{}\" author=\"snowflake-mapper\">",
+            xml_escape(id_prefix),
+            index + 1
+        );
+        out.push_str(&liquibase_change(change));
+        out.push_str("  </changeSet>\n");
+    }
+    out.push_str("\n</databaseChangeLog>\n");
+    out
+}
+
+/// Warehouses aren't part of the table DDL these tools manage
+fn is_migratable(change: &SchemaChange) -> bool {
+    match change {
+        SchemaChange::TableAdded { table } | SchemaChange::TableRemoved { table } => table.is_base_table(),
+        SchemaChange::WarehouseAdded { .. }
+        | SchemaChange::WarehouseRemoved { .. }
+        | SchemaChange::WarehouseChanged { .. } => false,
+        _ => true,
+    }
+}
+
+fn sql_statements(change: &SchemaChange) -> Vec<String> {
+    match change {
+        SchemaChange::TableAdded { table } => vec![create_table(table)],
+        SchemaChange::TableRemoved { table } => vec![format!(
+            "DROP TABLE {}",
+            qualified(&table.database_name, &table.schema_name, &table.table_name)
+        )],
+        SchemaChange::ColumnAdded { database, schema, table, column } => vec![format!(
+            "ALTER TABLE {} ADD COLUMN {}",
+            qualified(database, schema, table),
+            column_definition(column)
+        )],
+        SchemaChange::ColumnRemoved { database, schema, table, column } => vec![format!(
+            "ALTER TABLE {} DROP COLUMN {}",
+            qualified(database, schema, table),
+            quote_identifier(&column.name)
+        )],
+        SchemaChange::ColumnChanged { database, schema, table, before, after } => {
+            let alter = format!(
+                "ALTER TABLE {} ALTER COLUMN {}",
+                qualified(database, schema, table),
+                quote_identifier(&after.name)
+            );
+            let mut statements = Vec::new();
+            if before.sql_type() != after.sql_type() {
+                statements.push(format!("{} SET DATA TYPE {}", alter, after.sql_type()));
+            }
+            if before.is_nullable != after.is_nullable {
+                let action = if after.is_nullable { "DROP NOT NULL" } else { "SET NOT NULL" };
+                statements.push(format!("{} {}", alter, action));
+            }
+            if before.column_default != after.column_default {
+                statements.push(match &after.column_default {
+                    Some(default) => format!("{} SET DEFAULT {}", alter, default),
+                    None => format!("{} DROP DEFAULT", alter),
+                });
+            }
+            if before.comment != after.comment {
+                statements.push(match &after.comment {
+                    Some(comment) => format!("{} COMMENT {}", alter, quote_literal(comment)),
+                    None => format!("{} UNSET COMMENT", alter),
+                });
+            }
+            statements
+        }
+        SchemaChange::WarehouseAdded { .. }
+        | SchemaChange::WarehouseRemoved { .. }
+        | SchemaChange::WarehouseChanged { .. } => Vec::new(),
+    }
+}
+
+fn create_table(table: &TableInfo) -> String {
+    let mut definitions: Vec<String> = table.columns.iter().map(column_definition).collect();
+    if !table.primary_key.is_empty() {
+        let keys: Vec<String> = table.primary_key.iter().map(|key| quote_identifier(key)).collect();
+        definitions.push(format!("PRIMARY KEY ({})", keys.join(", ")));
+    }
+    let mut statement = format!(
+        "CREATE TABLE {} (\n    {}\n)",
+        qualified(&table.database_name, &table.schema_name, &table.table_name),
+        definitions.join(",\n    ")
+    );
+    if let Some(comment) = &table.comment {
+        let _ = write!(statement, " COMMENT = {}", quote_literal(comment));
+    }
+    statement
+}
+
+fn column_definition(column: &ColumnInfo) -> String {
+    let mut definition = format!("{} {}", quote_identifier(&column.name), column.sql_type());
+    if let Some(collation) = &column.collation {
+        let _ = write!(definition, " COLLATE {}", quote_literal(collation));
+    }
+    if let Some(identity) = &column.identity {
+        let _ = write!(
+            definition,
+            " IDENTITY({}, {})",
+            identity.start.unwrap_or(1),
+            identity.increment.unwrap_or(1)
+        );
+    } else if let Some(default) = &column.column_default {
+        let _ = write!(definition, " DEFAULT {}", default);
+    }
+    if !column.is_nullable {
+        definition.push_str(" NOT NULL");
+    }
+    if let Some(comment) = &column.comment {
+        let _ = write!(definition, " COMMENT {}", quote_literal(comment));
+    }
+    definition
+}
+
+fn liquibase_change(change: &SchemaChange) -> String {
+    let mut out = String::new();
+    match change {
+        SchemaChange::TableAdded { table } => {
+            let _ = writeln!(
+                out,
+                "    <createTable {}{}>",
+                table_attributes(&table.database_name, &table.schema_name, &table.table_name),
+                remarks_attribute(&table.comment)
+            );
+            for column in &table.columns {
+                let primary_key = table.primary_key.contains(&column.name);
+                let _ = write!(
+                    out,
+                    "      <column name=\"{}\" type=\"{}\"{}",
+                    xml_escape(&column.name),
+                    xml_escape(&column.sql_type()),
+                    column_attributes(column)
+                );
+                if primary_key || !column.is_nullable {
+                    let key = if primary_key { " primaryKey=\"true\"" } else { "" };
+                    let _ = writeln!(
+                        out,
+                        ">\n        <constraints nullable=\"{}\"{}/>\n      </column>",
+                        column.is_nullable && !primary_key,
+                        key
+                    );
+                } else {
+                    out.push_str("/>\n");
+                }
+            }
+            out.push_str("    </createTable>\n");
+        }
+        SchemaChange::TableRemoved { table } => {
+            let _ = writeln!(
+                out,
+                "    <dropTable {}/>",
+                table_attributes(&table.database_name, &table.schema_name, &table.table_name)
+            );
+        }
+        SchemaChange::ColumnAdded { database, schema, table, column } => {
+            let _ = writeln!(out, "    <addColumn {}>", table_attributes(database, schema, table));
+            let _ = write!(
+                out,
+                "      <column name=\"{}\" type=\"{}\"{}",
+                xml_escape(&column.name),
+                xml_escape(&column.sql_type()),
+                column_attributes(column)
+            );
+            if column.is_nullable {
+                out.push_str("/>\n");
+            } else {
+                out.push_str(">\n        <constraints nullable=\"false\"/>\n      </column>\n");
+            }
+            out.push_str("    </addColumn>\n");
+        }
+        SchemaChange::ColumnRemoved { database, schema, table, column } => {
+            let _ = writeln!(
+                out,
+                "    <dropColumn {} columnName=\"{}\"/>",
+                table_attributes(database, schema, table),
+                xml_escape(&column.name)
+            );
+        }
+        SchemaChange::ColumnChanged { database, schema, table, before, after } => {
+            let target = format!(
+                "{} columnName=\"{}\"",
+                table_attributes(database, schema, table),
+                xml_escape(&after.name)
+            );
+            if before.sql_type() != after.sql_type() {
+                let _ = writeln!(
+                    out,
+                    "    <modifyDataType {} newDataType=\"{}\"/>",
+                    target,
+                    xml_escape(&after.sql_type())
+                );
+            }
+            if before.is_nullable != after.is_nullable {
+                let tag = if after.is_nullable { "dropNotNullConstraint" } else { "addNotNullConstraint" };
+                let _ = writeln!(
+                    out,
+                    "    <{} {} columnDataType=\"{}\"/>",
+                    tag,
+                    target,
+                    xml_escape(&after.sql_type())
+                );
+            }
+            if before.column_default != after.column_default {
+                let _ = match &after.column_default {
+                    Some(default) => writeln!(
+                        out,
+                        "    <addDefaultValue {} defaultValueComputed=\"{}\"/>",
+                        target,
+                        xml_escape(default)
+                    ),
+                    None => writeln!(out, "    <dropDefaultValue {}/>", target),
+                };
+            }
+            if before.comment != after.comment {
+                let _ = writeln!(
+                    out,
+                    "    <setColumnRemarks {} remarks=\"{}\"/>",
+                    target,
+                    xml_escape(after.comment.as_deref().unwrap_or(""))
+                );
+            }
+        }
+        SchemaChange::WarehouseAdded { .. }
+        | SchemaChange::WarehouseRemoved { .. }
+        | SchemaChange::WarehouseChanged { .. } => {}
+    }
+    out
+}
+
+fn table_attributes(database: &str, schema: &str, table: &str) -> String {
+    format!(
+        "catalogName=\"{}\" schemaName=\"{}\" tableName=\"{}\"",
+        xml_escape(database),
+        xml_escape(schema),
+        xml_escape(table)
+    )
+}
+
+/// Remarks, default and identity attributes of a Liquibase `<column>`
+fn column_attributes(column: &ColumnInfo) -> String {
+    let mut attributes = remarks_attribute(&column.comment);
+    if let Some(identity) = &column.identity {
+        let _ = write!(
+            attributes,
+            " autoIncrement=\"true\" startWith=\"{}\" incrementBy=\"{}\"",
+            identity.start.unwrap_or(1),
+            identity.increment.unwrap_or(1)
+        );
+    } else if let Some(default) = &column.column_default {
+        let _ = write!(attributes, " defaultValueComputed=\"{}\"", xml_escape(default));
+    }
+    attributes
+}
+
+fn remarks_attribute(comment: &Option<String>) -> String {
+    comment
+        .as_ref()
+        .map(|comment| format!(" remarks=\"{}\"", xml_escape(comment)))
+        .unwrap_or_default()
+}
+
+fn qualified(database: &str, schema: &str, table: &str) -> String {
+    format!("{}.{}.{}", quote_identifier(database), quote_identifier(schema), quote_identifier(table))
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
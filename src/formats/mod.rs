@@ -0,0 +1,49 @@
+//! Renderers for output formats other than the default JSON documents.
+
+pub mod amundsen;
+pub mod atlas;
+pub mod avro;
+pub mod docs;
+pub mod dot;
+pub mod markdown;
+pub mod migrations;
+pub mod semantic;
+pub mod staging;
+pub mod template;
+pub mod terraform;
+pub mod topo;
+pub mod xlsx;
+
+use crate::TableInfo;
+
+/// Group a flat table list by database, preserving the order databases first appear in.
+pub fn group_by_database(tables: &[TableInfo]) -> Vec<(&str, Vec<&TableInfo>)> {
+    let mut groups: Vec<(&str, Vec<&TableInfo>)> = Vec::new();
+    for table in tables {
+        match groups.iter_mut().find(|(name, _)| *name == table.database_name) {
+            Some((_, members)) => members.push(table),
+            None => groups.push((&table.database_name, vec![table])),
+        }
+    }
+    groups
+}
+
+/// `name` double-quoted, with quotes inside it doubled
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Whether a Snowflake identifier resolves without quotes
+pub fn is_plain_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase() || c == '_')
+        && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_' || c == '$')
+}
+
+/// `name` as written in Snowflake SQL: bare when it resolves without quotes, quoted otherwise
+pub fn sql_identifier(name: &str) -> String {
+    if is_plain_identifier(name) {
+        name.to_string()
+    } else {
+        quote_identifier(name)
+    }
+}
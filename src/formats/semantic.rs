@@ -0,0 +1,224 @@
+//! Baseline semantic-layer models: Cube cubes and LookML views.
+//!
+//! Every column becomes a dimension, typed from its Snowflake type, with dates and timestamps
+//! as time dimensions. Each table gets a row count measure, and numeric columns that aren't
+//! keys get a sum (and an average for non-integers). Cubes also join along foreign keys to
+//! tables of the same database. The models are a starting point to edit, not a finished layer.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::formats::sql_identifier;
+use crate::mock::{self, ColumnKind};
+use crate::{ColumnInfo, TableInfo};
+
+/// How a column is modelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Number,
+    String,
+    Boolean,
+    Date,
+    Time,
+}
+
+/// A column's dimension type, and whether it gets sum and average measures
+fn classify(table: &TableInfo, column: &ColumnInfo) -> (Dimension, bool, bool) {
+    let (dimension, additive, fractional) = match mock::column_kind(column) {
+        ColumnKind::Integer { .. } => (Dimension::Number, true, false),
+        ColumnKind::Decimal { .. } | ColumnKind::Double => (Dimension::Number, true, true),
+        ColumnKind::Boolean => (Dimension::Boolean, false, false),
+        ColumnKind::Date => (Dimension::Date, false, false),
+        ColumnKind::Timestamp | ColumnKind::TimestampTz => (Dimension::Time, false, false),
+        ColumnKind::Binary | ColumnKind::Time | ColumnKind::Json | ColumnKind::Text { .. } => (Dimension::String, false, false),
+    };
+    // Summing identifiers means nothing
+    let additive = additive && !is_key(table, column);
+    (dimension, additive, additive && fractional)
+}
+
+fn is_key(table: &TableInfo, column: &ColumnInfo) -> bool {
+    let name = column.name.to_uppercase();
+    table.primary_key.contains(&column.name)
+        || table.foreign_keys.iter().any(|fk| fk.columns.contains(&column.name))
+        || name == "ID"
+        || name.ends_with("_ID")
+        || name.ends_with("_KEY")
+}
+
+/// Model names for the tables of a database: the lower-cased table name, prefixed with the
+/// schema when several schemas have a table of that name
+fn model_names(tables: &[TableInfo]) -> HashMap<(&str, &str), String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for table in tables {
+        *counts.entry(table.table_name.to_lowercase()).or_default() += 1;
+    }
+    tables
+        .iter()
+        .map(|table| {
+            let name = if counts[&table.table_name.to_lowercase()] > 1 {
+                format!("{}_{}", table.schema_name, table.table_name)
+            } else {
+                table.table_name.clone()
+            };
+            ((table.schema_name.as_str(), table.table_name.as_str()), member_name(&name))
+        })
+        .collect()
+}
+
+/// A lower-case identifier usable as a cube, view or member name
+fn member_name(name: &str) -> String {
+    let name: String = name.to_lowercase().chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("_{}", name)
+    }
+}
+
+fn sql_table(table: &TableInfo) -> String {
+    format!("{}.{}.{}", sql_identifier(&table.database_name), sql_identifier(&table.schema_name), sql_identifier(&table.table_name))
+}
+
+/// A double-quoted string, valid in both YAML and LookML
+fn string(text: &str) -> String {
+    serde_json::to_string(text).expect("strings serialize")
+}
+
+/// Cube data model (YAML) with a cube per table
+pub fn render_cube(tables: &[TableInfo]) -> String {
+    let names = model_names(tables);
+    let mut out = String::from("cubes:\n");
+    for table in tables {
+        let name = &names[&(table.schema_name.as_str(), table.table_name.as_str())];
+        let _ = writeln!(out, "  - name: {}", name);
+        let _ = writeln!(out, "    sql_table: {}", string(&sql_table(table)));
+        if let Some(comment) = &table.comment {
+            let _ = writeln!(out, "    description: {}", string(comment));
+        }
+
+        let joins: Vec<_> = table
+            .foreign_keys
+            .iter()
+            .filter(|fk| fk.referenced_database == table.database_name)
+            .filter_map(|fk| names.get(&(fk.referenced_schema.as_str(), fk.referenced_table.as_str())).map(|target| (fk, target)))
+            .collect();
+        if !joins.is_empty() {
+            let _ = writeln!(out, "\n    joins:");
+            for (fk, target) in joins {
+                let condition: Vec<String> = fk
+                    .columns
+                    .iter()
+                    .zip(&fk.referenced_columns)
+                    .map(|(column, referenced)| format!("{{CUBE}}.{} = {{{}}}.{}", sql_identifier(column), target, sql_identifier(referenced)))
+                    .collect();
+                let _ = writeln!(out, "      - name: {}", target);
+                let _ = writeln!(out, "        sql: {}", string(&condition.join(" AND ")));
+                let _ = writeln!(out, "        relationship: many_to_one");
+            }
+        }
+
+        let _ = writeln!(out, "\n    dimensions:");
+        for column in &table.columns {
+            let (dimension, _, _) = classify(table, column);
+            let _ = writeln!(out, "      - name: {}", member_name(&column.name));
+            let _ = writeln!(out, "        sql: {}", string(&format!("{{CUBE}}.{}", sql_identifier(&column.name))));
+            let cube_type = match dimension {
+                Dimension::Number => "number",
+                Dimension::String => "string",
+                Dimension::Boolean => "boolean",
+                Dimension::Date | Dimension::Time => "time",
+            };
+            let _ = writeln!(out, "        type: {}", cube_type);
+            if table.primary_key.len() == 1 && table.primary_key[0] == column.name {
+                let _ = writeln!(out, "        primary_key: true");
+            }
+            if let Some(comment) = &column.comment {
+                let _ = writeln!(out, "        description: {}", string(comment));
+            }
+        }
+
+        let _ = writeln!(out, "\n    measures:");
+        let _ = writeln!(out, "      - name: count\n        type: count");
+        for column in &table.columns {
+            let (_, sum, average) = classify(table, column);
+            let sql = string(&format!("{{CUBE}}.{}", sql_identifier(&column.name)));
+            if sum {
+                let _ = writeln!(out, "      - name: total_{}\n        sql: {}\n        type: sum", member_name(&column.name), sql);
+            }
+            if average {
+                let _ = writeln!(out, "      - name: average_{}\n        sql: {}\n        type: avg", member_name(&column.name), sql);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// LookML views, one per table
+pub fn render_lookml(tables: &[TableInfo]) -> String {
+    let names = model_names(tables);
+    let mut out = String::new();
+    for table in tables {
+        let name = &names[&(table.schema_name.as_str(), table.table_name.as_str())];
+        let _ = writeln!(out, "view: {} {{", name);
+        let _ = writeln!(out, "  sql_table_name: {} ;;", sql_table(table));
+        if let Some(comment) = &table.comment {
+            let _ = writeln!(out, "  # {}", comment.replace(['\r', '\n'], " "));
+        }
+
+        for column in &table.columns {
+            let (dimension, _, _) = classify(table, column);
+            let member = member_name(&column.name);
+            out.push('\n');
+            match dimension {
+                Dimension::Date | Dimension::Time => {
+                    // `created_at` becomes `created`, whose timeframes read `created_date` and so on
+                    let group = ["_at", "_date", "_time", "_timestamp"]
+                        .iter()
+                        .find_map(|suffix| member.strip_suffix(suffix).filter(|stem| !stem.is_empty()))
+                        .unwrap_or(&member);
+                    let _ = writeln!(out, "  dimension_group: {} {{", group);
+                    let _ = writeln!(out, "    type: time");
+                    if dimension == Dimension::Date {
+                        let _ = writeln!(out, "    datatype: date");
+                        let _ = writeln!(out, "    timeframes: [raw, date, week, month, quarter, year]");
+                    } else {
+                        let _ = writeln!(out, "    timeframes: [raw, time, date, week, month, quarter, year]");
+                    }
+                }
+                _ => {
+                    let _ = writeln!(out, "  dimension: {} {{", member);
+                    if table.primary_key.len() == 1 && table.primary_key[0] == column.name {
+                        let _ = writeln!(out, "    primary_key: yes");
+                    }
+                    let lookml_type = match dimension {
+                        Dimension::Number => "number",
+                        Dimension::Boolean => "yesno",
+                        _ => "string",
+                    };
+                    let _ = writeln!(out, "    type: {}", lookml_type);
+                }
+            }
+            if let Some(comment) = &column.comment {
+                let _ = writeln!(out, "    description: {}", string(comment));
+            }
+            let _ = writeln!(out, "    sql: ${{TABLE}}.{} ;;", sql_identifier(&column.name));
+            let _ = writeln!(out, "  }}");
+        }
+
+        let _ = writeln!(out, "\n  measure: count {{\n    type: count\n  }}");
+        for column in &table.columns {
+            let (_, sum, average) = classify(table, column);
+            let member = member_name(&column.name);
+            if sum {
+                let _ = writeln!(out, "\n  measure: total_{} {{\n    type: sum\n    sql: ${{{}}} ;;\n  }}", member, member);
+            }
+            if average {
+                let _ = writeln!(out, "\n  measure: average_{} {{\n    type: average\n    sql: ${{{}}} ;;\n  }}", member, member);
+            }
+        }
+        let _ = writeln!(out, "}}\n");
+    }
+    out
+}
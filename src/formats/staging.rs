@@ -0,0 +1,134 @@
+//! Staging model scaffolds for dbt and SQLMesh projects.
+//!
+//! Every table gets a staging model selecting each column cast to its captured type under a
+//! lower-cased name, from a source (dbt) or external model (SQLMesh) per Snowflake schema
+//! named `<database>_<schema>`. Models follow the `stg_<source>__<table>` convention under
+//! `models/staging/<source>/`.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::formats::{is_plain_identifier, quote_identifier, sql_identifier};
+use crate::TableInfo;
+
+/// A file of the scaffold, relative to the project directory
+pub struct ScaffoldFile {
+    pub path: PathBuf,
+    pub contents: String,
+    /// Describes the warehouse and is rewritten on every run, rather than a model meant to be
+    /// edited
+    pub generated: bool,
+}
+
+/// dbt sources (`_<source>__sources.yml`) and staging models
+pub fn render_dbt(tables: &[TableInfo]) -> Vec<ScaffoldFile> {
+    let mut files = Vec::new();
+    for (source, tables) in group_by_schema(tables) {
+        let dir = PathBuf::from("models").join("staging").join(&source);
+        let mut sources = format!("version: 2\n\nsources:\n  - name: {}\n", source);
+        let _ = writeln!(sources, "    database: {}", string(&tables[0].database_name));
+        let _ = writeln!(sources, "    schema: {}", string(&tables[0].schema_name));
+        let _ = writeln!(sources, "    tables:");
+        for table in &tables {
+            let _ = writeln!(sources, "      - name: {}", snake(&table.table_name));
+            let _ = writeln!(sources, "        identifier: {}", string(&table.table_name));
+            if !is_plain_identifier(&table.table_name) {
+                let _ = writeln!(sources, "        quoting:\n          identifier: true");
+            }
+            if let Some(comment) = &table.comment {
+                let _ = writeln!(sources, "        description: {}", string(comment));
+            }
+            let _ = writeln!(sources, "        columns:");
+            for column in &table.columns {
+                let _ = writeln!(sources, "          - name: {}", string(&column.name));
+                let _ = writeln!(sources, "            data_type: {}", string(&column.sql_type()));
+                if let Some(comment) = &column.comment {
+                    let _ = writeln!(sources, "            description: {}", string(comment));
+                }
+            }
+
+            let model = format!(
+                "{}from {{{{ source('{}', '{}') }}}}\n",
+                select_list(table, "select", "cast", "as"),
+                source,
+                snake(&table.table_name)
+            );
+            files.push(ScaffoldFile {
+                path: dir.join(format!("stg_{}__{}.sql", source, snake(&table.table_name))),
+                contents: model,
+                generated: false,
+            });
+        }
+        files.push(ScaffoldFile { path: dir.join(format!("_{}__sources.yml", source)), contents: sources, generated: true });
+    }
+    files
+}
+
+/// SQLMesh staging models, and `external_models.yaml` with the column types of every table
+pub fn render_sqlmesh(tables: &[TableInfo]) -> Vec<ScaffoldFile> {
+    let mut files = Vec::new();
+    let mut external = String::new();
+    for (source, tables) in group_by_schema(tables) {
+        for table in &tables {
+            let qualified = format!("{}.{}.{}", quote_identifier(&table.database_name), quote_identifier(&table.schema_name), quote_identifier(&table.table_name));
+            let _ = writeln!(external, "- name: {}", string(&qualified));
+            let _ = writeln!(external, "  columns:");
+            for column in &table.columns {
+                let _ = writeln!(external, "    {}: {}", string(&column.name), string(&column.sql_type()));
+            }
+
+            let name = format!("stg_{}__{}", source, snake(&table.table_name));
+            let mut model = format!("MODEL (\n  name staging.{},\n  kind VIEW", name);
+            if let Some(comment) = &table.comment {
+                let _ = write!(model, ",\n  description '{}'", comment.replace('\'', "''"));
+            }
+            let _ = write!(model, "\n);\n\n{}FROM {}\n", select_list(table, "SELECT", "CAST", "AS"), qualified);
+            files.push(ScaffoldFile {
+                path: PathBuf::from("models").join("staging").join(&source).join(format!("{}.sql", name)),
+                contents: model,
+                generated: false,
+            });
+        }
+    }
+    files.push(ScaffoldFile { path: PathBuf::from("external_models.yaml"), contents: external, generated: true });
+    files
+}
+
+/// `select` and one `cast(<column> as <type>) as <name>` line per column, in the keyword case
+/// of the tool's conventions
+fn select_list(table: &TableInfo, select: &str, cast: &str, alias: &str) -> String {
+    let columns: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| format!("    {}({} {} {}) {} {}", cast, sql_identifier(&column.name), alias, column.sql_type(), alias, snake(&column.name)))
+        .collect();
+    format!("{}\n{}\n", select, columns.join(",\n"))
+}
+
+/// Tables grouped by database and schema, with the source name of each group
+fn group_by_schema(tables: &[TableInfo]) -> Vec<(String, Vec<&TableInfo>)> {
+    let mut groups: Vec<(String, Vec<&TableInfo>)> = Vec::new();
+    for table in tables {
+        let source = snake(&format!("{}_{}", table.database_name, table.schema_name));
+        match groups.iter_mut().find(|(name, _)| *name == source) {
+            Some((_, members)) => members.push(table),
+            None => groups.push((source, vec![table])),
+        }
+    }
+    groups
+}
+
+/// A lower-case name usable for models, sources and column aliases
+fn snake(name: &str) -> String {
+    let name: String = name.to_lowercase().chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name
+    } else {
+        format!("_{}", name)
+    }
+}
+
+/// A double-quoted YAML string
+fn string(text: &str) -> String {
+    serde_json::to_string(text).expect("strings serialize")
+}
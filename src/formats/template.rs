@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::path::Path;
+
+use super::group_by_database;
+use crate::{SnowflakeMapperError, TableInfo};
+
+const TEMPLATE_NAME: &str = "catalog";
+
+#[derive(Serialize)]
+struct DatabaseContext<'a> {
+    database: &'a str,
+    tables: Vec<&'a TableInfo>,
+}
+
+#[derive(Serialize)]
+struct CatalogContext<'a> {
+    databases: Vec<DatabaseContext<'a>>,
+}
+
+/// Renders mapped tables through a user-provided Handlebars template.
+///
+/// Per-database templates receive `database` and `tables`; whole-catalog templates
+/// receive `databases`, a list of the same objects.
+pub struct TemplateRenderer {
+    registry: Handlebars<'static>,
+    extension: String,
+}
+
+impl TemplateRenderer {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        // `catalog.md.hbs` produces `.md` files; a bare `catalog.hbs` falls back to `.txt`
+        let extension = path
+            .file_stem()
+            .map(Path::new)
+            .and_then(Path::extension)
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("txt")
+            .to_string();
+
+        let mut registry = Handlebars::new();
+        // HTML escaping only makes sense for HTML; Markdown and SQL templates want raw names
+        if !matches!(extension.as_str(), "html" | "htm") {
+            registry.register_escape_fn(handlebars::no_escape);
+        }
+        registry
+            .register_template_file(TEMPLATE_NAME, path)
+            .with_context(|| format!("Failed to load template {}", path.display()))?;
+
+        Ok(Self { registry, extension })
+    }
+
+    /// File extension for rendered output, derived from the template file name
+    pub fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    pub fn render_database(&self, database: &str, tables: &[TableInfo]) -> Result<String> {
+        self.render(&DatabaseContext {
+            database,
+            tables: tables.iter().collect(),
+        })
+    }
+
+    pub fn render_catalog(&self, tables: &[TableInfo]) -> Result<String> {
+        let databases = group_by_database(tables)
+            .into_iter()
+            .map(|(database, tables)| DatabaseContext { database, tables })
+            .collect();
+        self.render(&CatalogContext { databases })
+    }
+
+    fn render(&self, context: &impl Serialize) -> Result<String> {
+        self.registry
+            .render(TEMPLATE_NAME, context)
+            .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to render template: {}", e)).into())
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::formats::quote_identifier;
+use crate::TableInfo;
+
+/// Schemas Snowflake manages itself; they can't be declared as resources
+const SYSTEM_SCHEMAS: [&str; 1] = ["INFORMATION_SCHEMA"];
+
+/// Render `snowflake_database`, `snowflake_schema` and `snowflake_table` resources for
+/// one database, in the format of the Snowflake-Labs/snowflake provider.
+///
+/// Views are listed as comments because the catalog doesn't capture their definitions.
+pub fn render_resources(database: &str, tables: &[TableInfo]) -> String {
+    let mut out = String::new();
+    let database_resource = resource_name(&[database]);
+    let _ = writeln!(out, "resource \"snowflake_database\" \"{}\" {{", database_resource);
+    let _ = writeln!(out, "  name = {}", hcl_string(database));
+    let _ = writeln!(out, "}}\n");
+
+    let mut schemas_seen: HashSet<&str> = HashSet::new();
+    for table in managed_tables(tables) {
+        let schema_resource = resource_name(&[database, &table.schema_name]);
+        if schemas_seen.insert(&table.schema_name) {
+            let _ = writeln!(out, "resource \"snowflake_schema\" \"{}\" {{", schema_resource);
+            let _ = writeln!(out, "  database = snowflake_database.{}.name", database_resource);
+            let _ = writeln!(out, "  name     = {}", hcl_string(&table.schema_name));
+            let _ = writeln!(out, "}}\n");
+        }
+
+        if !table.is_base_table() {
+            let _ = writeln!(
+                out,
+                "# {}.{}.{} is a {} and is not managed here\n",
+                database,
+                table.schema_name,
+                table.table_name,
+                table.table_type.as_deref().unwrap_or("view").to_lowercase(),
+            );
+            continue;
+        }
+
+        let _ = writeln!(
+            out,
+            "resource \"snowflake_table\" \"{}\" {{",
+            resource_name(&[database, &table.schema_name, &table.table_name])
+        );
+        let _ = writeln!(out, "  database = snowflake_database.{}.name", database_resource);
+        let _ = writeln!(out, "  schema   = snowflake_schema.{}.name", schema_resource);
+        let _ = writeln!(out, "  name     = {}", hcl_string(&table.table_name));
+        if let Some(comment) = &table.comment {
+            let _ = writeln!(out, "  comment  = {}", hcl_string(comment));
+        }
+        for column in &table.columns {
+            let _ = writeln!(out, "\n  column {{");
+            let _ = writeln!(out, "    name     = {}", hcl_string(&column.name));
+            let _ = writeln!(out, "    type     = {}", hcl_string(&column.sql_type()));
+            let _ = writeln!(out, "    nullable = {}", column.is_nullable);
+            if let Some(comment) = &column.comment {
+                let _ = writeln!(out, "    comment  = {}", hcl_string(comment));
+            }
+            if let Some(collation) = &column.collation {
+                let _ = writeln!(out, "    collate  = {}", hcl_string(collation));
+            }
+            if let Some(identity) = &column.identity {
+                let _ = writeln!(out, "\n    identity {{");
+                let _ = writeln!(out, "      start_num = {}", identity.start.unwrap_or(1));
+                let _ = writeln!(out, "      step_num  = {}", identity.increment.unwrap_or(1));
+                let _ = writeln!(out, "    }}");
+            } else if let Some(default) = &column.column_default {
+                let _ = writeln!(out, "\n    default {{");
+                let _ = writeln!(out, "      expression = {}", hcl_string(default));
+                let _ = writeln!(out, "    }}");
+            }
+            let _ = writeln!(out, "  }}");
+        }
+        if !table.primary_key.is_empty() {
+            let keys: Vec<String> = table.primary_key.iter().map(|key| hcl_string(key)).collect();
+            let _ = writeln!(out, "\n  primary_key {{");
+            let _ = writeln!(out, "    keys = [{}]", keys.join(", "));
+            let _ = writeln!(out, "  }}");
+        }
+        let _ = writeln!(out, "}}\n");
+    }
+    out
+}
+
+/// Render a shell script running `terraform import` for every resource in `render_resources`
+pub fn render_import_script(database: &str, tables: &[TableInfo]) -> String {
+    let mut out = String::from("#!/bin/sh\nset -e\n\n");
+    let _ = writeln!(
+        out,
+        "terraform import snowflake_database.{} {}",
+        resource_name(&[database]),
+        shell_quote(&quoted_identifier(&[database]))
+    );
+
+    let mut schemas_seen: HashSet<&str> = HashSet::new();
+    for table in managed_tables(tables) {
+        if schemas_seen.insert(&table.schema_name) {
+            let _ = writeln!(
+                out,
+                "terraform import snowflake_schema.{} {}",
+                resource_name(&[database, &table.schema_name]),
+                shell_quote(&quoted_identifier(&[database, &table.schema_name]))
+            );
+        }
+        if table.is_base_table() {
+            let _ = writeln!(
+                out,
+                "terraform import snowflake_table.{} {}",
+                resource_name(&[database, &table.schema_name, &table.table_name]),
+                shell_quote(&quoted_identifier(&[database, &table.schema_name, &table.table_name]))
+            );
+        }
+    }
+    out
+}
+
+fn managed_tables(tables: &[TableInfo]) -> impl Iterator<Item = &TableInfo> {
+    tables
+        .iter()
+        .filter(|t| !SYSTEM_SCHEMAS.iter().any(|s| t.schema_name.eq_ignore_ascii_case(s)))
+}
+
+/// Terraform resource name built from identifier parts, e.g. `sales_public_orders`
+fn resource_name(parts: &[&str]) -> String {
+    let name: String = parts
+        .join("_")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name
+    } else {
+        format!("_{}", name)
+    }
+}
+
+/// Provider import ID: a fully qualified, double-quoted identifier such as `"DB"."SCHEMA"`
+fn quoted_identifier(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|part| quote_identifier(part))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn hcl_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace("${", "$${")
+        .replace("%{", "%%{");
+    format!("\"{}\"", escaped)
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
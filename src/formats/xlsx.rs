@@ -0,0 +1,119 @@
+use anyhow::Result;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use std::collections::HashSet;
+
+use crate::{SnowflakeMapperError, TableInfo};
+
+/// Excel limits sheet names to 31 characters
+const MAX_SHEET_NAME: usize = 31;
+
+const COLUMN_HEADERS: [&str; 9] = [
+    "Table",
+    "Column",
+    "Type",
+    "Nullable",
+    "Max Length",
+    "Precision",
+    "Scale",
+    "Primary Key",
+    "Comment",
+];
+
+/// Render tables into an XLSX workbook: a summary sheet listing every table, followed by
+/// one sheet per schema with a row per column. Sheets are named after the schema, or
+/// `DATABASE.SCHEMA` when the workbook spans several databases.
+pub fn render_workbook(tables: &[TableInfo]) -> Result<Vec<u8>> {
+    build_workbook(tables).map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to build workbook: {}", e)).into())
+}
+
+fn build_workbook(tables: &[TableInfo]) -> Result<Vec<u8>, XlsxError> {
+    let header = Format::new().set_bold();
+    let mut workbook = Workbook::new();
+
+    let summary = workbook.add_worksheet();
+    summary.set_name("Summary")?;
+    write_header(summary, &["Database", "Schema", "Table", "Columns", "Comment"], &header)?;
+    for (row, table) in (1u32..).zip(tables) {
+        summary.write_string(row, 0, &table.database_name)?;
+        summary.write_string(row, 1, &table.schema_name)?;
+        summary.write_string(row, 2, &table.table_name)?;
+        summary.write_number(row, 3, table.columns.len() as f64)?;
+        summary.write_string(row, 4, table.comment.as_deref().unwrap_or(""))?;
+    }
+    finish_sheet(summary, tables.len() as u32, 4)?;
+
+    let multiple_databases = tables.iter().any(|t| t.database_name != tables[0].database_name);
+    let mut schemas: Vec<(&str, &str, Vec<&TableInfo>)> = Vec::new();
+    for table in tables {
+        match schemas
+            .iter_mut()
+            .find(|(db, schema, _)| *db == table.database_name && *schema == table.schema_name)
+        {
+            Some((_, _, members)) => members.push(table),
+            None => schemas.push((&table.database_name, &table.schema_name, vec![table])),
+        }
+    }
+
+    let mut used_names: HashSet<String> = HashSet::from(["summary".to_string()]);
+    for (database, schema, members) in schemas {
+        let label = if multiple_databases { format!("{}.{}", database, schema) } else { schema.to_string() };
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(unique_sheet_name(&label, &mut used_names))?;
+        write_header(sheet, &COLUMN_HEADERS, &header)?;
+
+        let mut row = 0u32;
+        for table in members {
+            for column in &table.columns {
+                row += 1;
+                sheet.write_string(row, 0, &table.table_name)?;
+                sheet.write_string(row, 1, &column.name)?;
+                sheet.write_string(row, 2, &column.data_type)?;
+                sheet.write_boolean(row, 3, column.is_nullable)?;
+                for (col, value) in [(4, column.character_maximum_length), (5, column.numeric_precision), (6, column.numeric_scale)] {
+                    if let Some(value) = value {
+                        sheet.write_number(row, col, value)?;
+                    }
+                }
+                sheet.write_boolean(row, 7, table.primary_key.contains(&column.name))?;
+                sheet.write_string(row, 8, column.comment.as_deref().unwrap_or(""))?;
+            }
+        }
+        finish_sheet(sheet, row, COLUMN_HEADERS.len() as u16 - 1)?;
+    }
+
+    workbook.save_to_buffer()
+}
+
+fn write_header(sheet: &mut Worksheet, headers: &[&str], format: &Format) -> Result<(), XlsxError> {
+    for (col, title) in (0u16..).zip(headers) {
+        sheet.write_string_with_format(0, col, *title, format)?;
+    }
+    Ok(())
+}
+
+fn finish_sheet(sheet: &mut Worksheet, last_row: u32, last_col: u16) -> Result<(), XlsxError> {
+    sheet.set_freeze_panes(1, 0)?;
+    if last_row > 0 {
+        sheet.autofilter(0, 0, last_row, last_col)?;
+    }
+    sheet.autofit();
+    Ok(())
+}
+
+/// Sanitize and truncate a sheet name, suffixing a counter if it collides (case-insensitively)
+fn unique_sheet_name(label: &str, used: &mut HashSet<String>) -> String {
+    let base: String = label
+        .chars()
+        .map(|c| if matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\') { '_' } else { c })
+        .take(MAX_SHEET_NAME)
+        .collect();
+
+    let mut name = base.clone();
+    let mut counter = 2;
+    while !used.insert(name.to_lowercase()) {
+        let suffix = format!("~{}", counter);
+        name = base.chars().take(MAX_SHEET_NAME - suffix.len()).collect::<String>() + &suffix;
+        counter += 1;
+    }
+    name
+}
@@ -0,0 +1,290 @@
+//! Safety limits on what a run may execute, how many queries it may issue and how long it may take.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::SnowflakeMapperError;
+
+/// Leading keywords allowed by read-only enforcement
+const READ_ONLY_KEYWORDS: &[&str] = &["SELECT", "SHOW", "DESCRIBE", "DESC", "USE"];
+
+/// Refuse anything but a single SHOW/SELECT/DESCRIBE/USE statement, or a SELECT with
+/// common table expressions (`WITH ... SELECT`)
+pub fn check_read_only(query: &str) -> Result<(), SnowflakeMapperError> {
+    let statements = split_statements(query);
+    let violation = |reason: String| Err(SnowflakeMapperError::ReadOnlyViolation(reason));
+    match statements.as_slice() {
+        [] => violation("empty query".to_string()),
+        [statement] => {
+            let keyword = leading_keyword(statement.trim_start_matches('('));
+            let keyword = match keyword.as_str() {
+                "WITH" => Some(main_keyword_after_ctes(&statement.trim_start_matches('(')[keyword.len()..]))
+                    .filter(|main| !main.is_empty())
+                    .unwrap_or(keyword),
+                _ => keyword,
+            };
+            if READ_ONLY_KEYWORDS.contains(&keyword.as_str()) {
+                Ok(())
+            } else {
+                violation(format!("`{}` statements are not allowed", keyword))
+            }
+        }
+        _ => violation("multiple statements are not allowed".to_string()),
+    }
+}
+
+/// The first word of `text`, uppercased
+fn leading_keyword(text: &str) -> String {
+    text.trim_start().chars().take_while(|c| c.is_ascii_alphabetic()).collect::<String>().to_ascii_uppercase()
+}
+
+/// The keyword of the statement that follows the common table expressions of a `WITH`,
+/// e.g. `SELECT` for `WITH a AS (SELECT 1), b (x) AS (SELECT 2) SELECT * FROM a, b`. Each
+/// expression is a name, an optional column list and a parenthesized body; the first word
+/// after a closing parenthesis that isn't followed by `AS` or `,` starts the statement.
+fn main_keyword_after_ctes(ctes: &str) -> String {
+    let mut depth = 0usize;
+    let mut chars = ctes.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                chars.by_ref().find(|&(_, inner)| inner == c);
+            }
+            '(' => depth += 1,
+            ')' if depth > 1 => depth -= 1,
+            ')' if depth == 1 => {
+                depth = 0;
+                let rest = ctes[index + 1..].trim_start();
+                if rest.starts_with(',') || rest.starts_with('(') {
+                    continue;
+                }
+                match leading_keyword(rest).as_str() {
+                    "AS" => continue,
+                    keyword => return keyword.to_string(),
+                }
+            }
+            _ => {}
+        }
+    }
+    String::new()
+}
+
+/// Split on `;`, dropping comments and ignoring separators inside quotes
+fn split_statements(query: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                current.push(c);
+                for inner in chars.by_ref() {
+                    current.push(inner);
+                    if inner == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                chars.by_ref().find(|&inner| inner == '\n');
+                current.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for inner in chars.by_ref() {
+                    if previous == '*' && inner == '/' {
+                        break;
+                    }
+                    previous = inner;
+                }
+                current.push(' ');
+            }
+            ';' => statements.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    statements.push(current);
+    statements
+        .into_iter()
+        .map(|statement| statement.trim().to_string())
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
+/// Query and runtime budget shared by every connection of a run
+#[derive(Debug)]
+pub struct QueryBudget {
+    max_queries: Option<usize>,
+    deadline: Option<Instant>,
+    used: AtomicUsize,
+    exhausted: AtomicBool,
+}
+
+impl QueryBudget {
+    /// Start the clock now; `None` leaves that limit off
+    pub fn new(max_queries: Option<usize>, max_runtime: Option<Duration>) -> Self {
+        Self {
+            max_queries,
+            deadline: max_runtime.map(|runtime| Instant::now() + runtime),
+            used: AtomicUsize::new(0),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Account for one more query, failing once either limit is exceeded
+    pub fn acquire(&self) -> Result<(), SnowflakeMapperError> {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.exhausted.store(true, Ordering::SeqCst);
+            return Err(SnowflakeMapperError::BudgetExceeded("--max-runtime reached".to_string()));
+        }
+        let within_limit = self
+            .used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                self.max_queries.is_none_or(|max| used < max).then_some(used + 1)
+            })
+            .is_ok();
+        if !within_limit {
+            self.exhausted.store(true, Ordering::SeqCst);
+            return Err(SnowflakeMapperError::BudgetExceeded(format!(
+                "--max-queries limit of {} reached",
+                self.max_queries.unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether a limit has been hit, however the resulting error was wrapped on the way up
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::SeqCst) || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Queries issued so far
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+}
+
+/// Rough credit impact of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UsageClass {
+    /// A handful of information_schema queries on the warehouse
+    Low,
+    /// Enough information_schema queries to keep the warehouse running for a while
+    Medium,
+    /// Scans ACCOUNT_USAGE views, which can take minutes per database
+    High,
+}
+
+impl fmt::Display for UsageClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UsageClass::Low => "low",
+            UsageClass::Medium => "medium",
+            UsageClass::High => "high",
+        })
+    }
+}
+
+/// Queries a Snowflake run is expected to issue, split by whether they need a running warehouse
+#[derive(Debug, Clone)]
+pub struct CostEstimate {
+    pub databases: usize,
+    /// information_schema and ACCOUNT_USAGE queries, billed as warehouse time
+    pub warehouse_queries: usize,
+    /// `SHOW` and `USE` commands, served by cloud services
+    pub metadata_queries: usize,
+    pub usage_class: UsageClass,
+    /// Whether tables with semi-structured columns are sampled, one query each on top of the count
+    pub samples_tables: bool,
+    /// `--hooks` table hooks, each run once per table on top of the count
+    pub table_hooks: usize,
+    /// Whether large databases are fetched in chunks, one query each on top of the count
+    pub shards: bool,
+}
+
+/// Warehouse queries above which a run is no longer considered cheap
+const MEDIUM_USAGE_QUERIES: usize = 100;
+
+impl CostEstimate {
+    /// Estimate a Snowflake run over `databases` databases on `connections` connections.
+    /// `list_databases` adds the `SHOW DATABASES` needed when no databases were named.
+    pub fn snowflake(databases: usize, connections: usize, list_databases: bool, access_history: bool) -> Self {
+        // Per database: columns and tables from information_schema, plus SHOW PRIMARY KEYS,
+        // SHOW IMPORTED KEYS and SHOW TABLES
+        let warehouse_queries = databases * (2 + usize::from(access_history));
+        // SHOW WAREHOUSES, USE WAREHOUSE and USE ROLE on every connection, and one more
+        // SHOW WAREHOUSES for the warehouse inventory
+        let metadata_queries = databases * 3 + connections * 3 + 1 + usize::from(list_databases);
+        let usage_class = if access_history {
+            UsageClass::High
+        } else if warehouse_queries > MEDIUM_USAGE_QUERIES {
+            UsageClass::Medium
+        } else {
+            UsageClass::Low
+        };
+        Self { databases, warehouse_queries, metadata_queries, usage_class, samples_tables: false, table_hooks: 0, shards: false }
+    }
+
+    /// Account for `--infer-variant`, whose query count depends on the tables found
+    pub fn with_variant_sampling(mut self) -> Self {
+        self.samples_tables = true;
+        self.usage_class = self.usage_class.max(UsageClass::Medium);
+        self
+    }
+
+    /// Account for `--include-usage`, one ACCOUNT_USAGE query per database
+    pub fn with_usage(mut self) -> Self {
+        self.warehouse_queries += self.databases;
+        self.usage_class = UsageClass::High;
+        self
+    }
+
+    /// Account for `--shard-columns`: one column count per database, plus the chunk queries
+    /// of large databases, which the estimate can't know
+    pub fn with_sharding(mut self) -> Self {
+        self.warehouse_queries += self.databases;
+        self.shards = true;
+        self
+    }
+
+    /// Account for `--hooks`: each database hook runs once per database, each table hook
+    /// once per table
+    pub fn with_sql_hooks(mut self, database_hooks: usize, table_hooks: usize) -> Self {
+        self.warehouse_queries += self.databases * database_hooks;
+        self.table_hooks = table_hooks;
+        if table_hooks > 0 {
+            self.usage_class = self.usage_class.max(UsageClass::Medium);
+        }
+        self
+    }
+
+    pub fn total_queries(&self) -> usize {
+        self.warehouse_queries + self.metadata_queries
+    }
+}
+
+impl fmt::Display for CostEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "databases:          {}", self.databases)?;
+        writeln!(f, "warehouse queries:  {}", self.warehouse_queries)?;
+        writeln!(f, "metadata queries:   {}", self.metadata_queries)?;
+        write!(f, "total queries:      {}", self.total_queries())?;
+        if self.samples_tables {
+            write!(f, " + 1 per table with VARIANT/OBJECT/ARRAY columns")?;
+        }
+        if self.shards {
+            write!(f, " + 1 per chunk of large databases")?;
+        }
+        if self.table_hooks > 0 {
+            write!(f, " + {} per table for SQL hooks", self.table_hooks)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "credit usage class: {}", self.usage_class)
+    }
+}
@@ -0,0 +1,8 @@
+//! Hashes that must not change between releases, for file names, pseudonyms and seeds.
+
+/// FNV-1a: stable across Rust versions and platforms, unlike `DefaultHasher`
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,75 @@
+//! User-defined SQL whose results are attached to tables under `custom`.
+//!
+//! Hooks come from a JSON file with per-database and per-table queries:
+//!
+//! ```json
+//! {
+//!   "database": [
+//!     { "name": "domain", "sql": "SELECT schema_name, table_name, domain, steward FROM GOVERNANCE.PUBLIC.TABLE_DOMAINS WHERE database_name = '{database_name}'" }
+//!   ],
+//!   "table": [
+//!     { "name": "freshness", "sql": "SELECT MAX(_loaded_at) AS loaded_at FROM {database}.{schema}.{table}" }
+//!   ]
+//! }
+//! ```
+//!
+//! A database hook runs once per database and must return `schema_name` and `table_name`
+//! columns; each row is attached to the table it names. A table hook runs once per table
+//! and its first row is attached. `{database}`, `{schema}` and `{table}` expand to quoted
+//! identifiers, `{database_name}`, `{schema_name}` and `{table_name}` to the names escaped
+//! for use inside a string literal.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::SnowflakeMapperError;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SqlHook {
+    /// Key under `custom` the results are attached as
+    pub name: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SqlHooks {
+    pub database: Vec<SqlHook>,
+    pub table: Vec<SqlHook>,
+}
+
+impl SqlHooks {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read SQL hooks {}", path.display()))?;
+        let hooks: SqlHooks =
+            serde_json::from_str(&contents).with_context(|| format!("Invalid SQL hooks {}", path.display()))?;
+        // Both kinds share the `custom` keys
+        let mut names = HashSet::new();
+        if let Some(hook) = hooks.database.iter().chain(&hooks.table).find(|hook| !names.insert(hook.name.as_str())) {
+            return Err(SnowflakeMapperError::InvalidExpression(format!("SQL hook name `{}` is used twice in {}", hook.name, path.display())).into());
+        }
+        Ok(hooks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.database.is_empty() && self.table.is_empty()
+    }
+}
+
+/// Fill the placeholders of `sql`; `schema` and `table` are only given for table hooks
+pub fn render(sql: &str, database: &str, schema: Option<&str>, table: Option<&str>) -> String {
+    let identifier = |name: &str| format!("\"{}\"", name.replace('"', "\"\""));
+    let literal = |name: &str| name.replace('\'', "''");
+    let mut rendered = sql.replace("{database}", &identifier(database)).replace("{database_name}", &literal(database));
+    if let Some(schema) = schema {
+        rendered = rendered.replace("{schema}", &identifier(schema)).replace("{schema_name}", &literal(schema));
+    }
+    if let Some(table) = table {
+        rendered = rendered.replace("{table}", &identifier(table)).replace("{table_name}", &literal(table));
+    }
+    rendered
+}
@@ -0,0 +1,93 @@
+//! Blast radius of renaming or dropping a column.
+//!
+//! Starts from every table or view with a column of the given name, then follows foreign
+//! keys: a column referencing an impacted column is impacted too, transitively. Views only
+//! appear when they expose the column themselves, since view definitions aren't captured.
+
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+
+use crate::{output, TableInfo};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpactedColumn {
+    /// `database.schema.table`
+    pub table: String,
+    pub table_type: Option<String>,
+    pub column: String,
+    /// 0 for tables containing the column, then one more per foreign key hop
+    pub depth: usize,
+    /// The foreign key that pulled this column in, as `<name> -> <table>.<column>`
+    pub via: Option<String>,
+}
+
+/// Columns named `column` (case-insensitively), then every column that references one of
+/// them through a foreign key, in breadth-first order
+pub fn column_impact(tables: &[TableInfo], column: &str) -> Vec<ImpactedColumn> {
+    let mut impacted = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    for table in tables {
+        for found in table.columns.iter().filter(|c| c.name.eq_ignore_ascii_case(column)) {
+            let key = (table.qualified_name(), found.name.clone());
+            if seen.insert(key.clone()) {
+                queue.push_back((key.0.clone(), key.1.clone(), 0));
+                impacted.push(ImpactedColumn {
+                    table: key.0,
+                    table_type: table.table_type.clone(),
+                    column: key.1,
+                    depth: 0,
+                    via: None,
+                });
+            }
+        }
+    }
+
+    while let Some((referenced_table, referenced_column, depth)) = queue.pop_front() {
+        for table in tables {
+            for fk in &table.foreign_keys {
+                let target = format!("{}.{}.{}", fk.referenced_database, fk.referenced_schema, fk.referenced_table);
+                if target != referenced_table {
+                    continue;
+                }
+                let referencing = fk
+                    .referenced_columns
+                    .iter()
+                    .zip(&fk.columns)
+                    .filter(|(referenced, _)| **referenced == referenced_column)
+                    .map(|(_, column)| column.clone());
+                for column in referencing {
+                    let key = (table.qualified_name(), column);
+                    if seen.insert(key.clone()) {
+                        queue.push_back((key.0.clone(), key.1.clone(), depth + 1));
+                        impacted.push(ImpactedColumn {
+                            table: key.0,
+                            table_type: table.table_type.clone(),
+                            column: key.1,
+                            depth: depth + 1,
+                            via: Some(format!("{} -> {}.{}", fk.name, referenced_table, referenced_column)),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    impacted
+}
+
+pub fn render_text(impacted: &[ImpactedColumn]) -> String {
+    let rows: Vec<[String; 5]> = impacted
+        .iter()
+        .map(|i| {
+            [
+                i.table.clone(),
+                i.table_type.clone().unwrap_or_else(|| "-".to_string()),
+                i.column.clone(),
+                i.depth.to_string(),
+                i.via.clone().unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    output::render_table(["TABLE", "TYPE", "COLUMN", "DEPTH", "VIA"], &rows)
+}
@@ -0,0 +1,398 @@
+//! Fetch and map warehouse schemas into a common catalog format.
+//!
+//! The CLI in `main.rs` is a thin layer over this library; embedding applications can map
+//! an account in memory with [`SnowflakeMapperBuilder`] and the [`WarehouseMapper`] trait.
+
+pub mod account;
+pub mod atlas;
+pub mod backends;
+pub mod bench;
+pub mod builder;
+pub mod catalog;
+pub mod changelog;
+pub mod cli;
+pub mod completions;
+pub mod diff;
+pub mod duplicates;
+pub mod exclude;
+pub mod fake;
+pub mod failures;
+pub mod formats;
+pub mod guardrails;
+pub mod hash;
+pub mod hooks;
+pub mod impact;
+pub mod merge;
+pub mod mock;
+pub mod naming;
+pub mod notify;
+pub mod output;
+pub mod ownership;
+pub mod patch;
+pub mod paths;
+pub mod plugins;
+pub mod progress;
+pub mod provenance;
+pub mod query;
+pub mod querylog;
+pub mod redact;
+pub mod registry;
+pub mod replication;
+pub mod retry;
+pub mod schema;
+pub mod shares;
+pub mod stale;
+pub mod stats;
+pub mod throttle;
+pub mod types;
+pub mod upgrade;
+pub mod users;
+pub mod variant;
+pub mod visibility;
+
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+pub use crate::backends::WarehouseMapper;
+pub use crate::builder::SnowflakeMapperBuilder;
+
+#[derive(Debug, Error)]
+pub enum SnowflakeMapperError {
+    #[error("Failed to connect to warehouse: {0}")]
+    ConnectionError(String),
+    
+    #[error("Failed to execute query: {0}")]
+    QueryError(String),
+    
+    #[error("Failed to read column {column}: {message}")]
+    ColumnError {
+        column: String,
+        message: String,
+    },
+    
+    #[error("Failed to write output: {0}")]
+    OutputError(String),
+    
+    #[error("Missing required environment variable: {0}")]
+    MissingEnvVar(String),
+
+    #[error("Missing required configuration: {0}")]
+    MissingConfig(String),
+
+    #[error("Query budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    #[error("Refusing to run a non-read-only query: {0}")]
+    ReadOnlyViolation(String),
+
+    #[error("Failed to read snapshot: {0}")]
+    SnapshotError(String),
+
+    #[error("Invalid catalog expression: {0}")]
+    InvalidExpression(String),
+
+    #[error("Schema registry error: {0}")]
+    RegistryError(String),
+
+    #[error("Schema drift: {0}")]
+    SchemaDrift(String),
+
+    #[error("Conflicting snapshots: {0}")]
+    MergeConflict(String),
+
+    #[error("Atlas error: {0}")]
+    AtlasError(String),
+
+    #[error("Notification failed: {0}")]
+    NotificationError(String),
+
+    #[error("Timed out: {0}")]
+    DatabaseTimeout(String),
+
+    #[error("Objects hidden from the current role: {0}")]
+    HiddenObjects(String),
+}
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ColumnInfo {
+    pub name: String,
+    /// Type name as information_schema reports it
+    pub data_type: String,
+    /// The type with its length, precision and scale, as every exporter interprets it
+    #[serde(default)]
+    pub snowflake_type: Option<types::SnowflakeType>,
+    pub is_nullable: bool,
+    pub character_maximum_length: Option<i32>,
+    pub numeric_precision: Option<i32>,
+    pub numeric_scale: Option<i32>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// 1-based position of the column within its table
+    #[serde(default)]
+    pub ordinal_position: Option<i32>,
+    /// Default expression, as written in the column definition
+    #[serde(default)]
+    pub column_default: Option<String>,
+    #[serde(default)]
+    pub collation: Option<String>,
+    /// Set for identity / autoincrement columns
+    #[serde(default)]
+    pub identity: Option<IdentityInfo>,
+    /// Structure inferred from sampled values of VARIANT / OBJECT / ARRAY columns
+    #[serde(default)]
+    pub variant_shape: Option<Box<variant::VariantShape>>,
+    /// Digits of fractional seconds kept by TIME and TIMESTAMP columns
+    #[serde(default)]
+    pub datetime_precision: Option<i32>,
+    /// How TIME and TIMESTAMP values relate to time zones; `None` for other types, and for a
+    /// bare `TIMESTAMP` whose mapping depends on the session
+    #[serde(default)]
+    pub time_zone: Option<TimeZoneHandling>,
+    /// Set for INTERVAL columns (PostgreSQL; Snowflake has no interval columns)
+    #[serde(default)]
+    pub interval: Option<IntervalInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct IdentityInfo {
+    pub start: Option<i64>,
+    pub increment: Option<i64>,
+}
+
+/// Time zone semantics of a TIME or TIMESTAMP column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeZoneHandling {
+    /// Wall-clock values without a zone: `TIMESTAMP_NTZ`, `TIME`, `timestamp without time zone`
+    Naive,
+    /// Instants stored in UTC and shown in the session's zone: `TIMESTAMP_LTZ`, PostgreSQL's
+    /// `timestamp with time zone`
+    Local,
+    /// Values stored with their own UTC offset: `TIMESTAMP_TZ`, PostgreSQL's `time with time zone`
+    Offset,
+}
+
+impl TimeZoneHandling {
+    /// Time zone handling of an information_schema `data_type`, if it's a time or timestamp,
+    /// see [`SnowflakeType::time_zone`](types::SnowflakeType::time_zone)
+    pub fn of(data_type: &str) -> Option<Self> {
+        types::SnowflakeType::parse(data_type).time_zone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct IntervalInfo {
+    /// Restricted fields, e.g. `YEAR TO MONTH` or `DAY TO SECOND`; `None` for a plain interval
+    pub fields: Option<String>,
+    /// Digits of fractional seconds
+    pub precision: Option<i32>,
+}
+
+impl ColumnInfo {
+    /// The column's type; derived from the raw fields for records that don't carry one
+    pub fn snowflake_type(&self) -> types::SnowflakeType {
+        self.snowflake_type.clone().unwrap_or_else(|| types::SnowflakeType::from_column(self))
+    }
+
+    /// Full SQL type including length, precision and scale, e.g. `VARCHAR(255)`,
+    /// `NUMBER(38,0)` or `TIMESTAMP_NTZ(3)`
+    pub fn sql_type(&self) -> String {
+        self.snowflake_type().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct TableInfo {
+    /// Snapshot structure version, see [`upgrade`]
+    #[serde(default = "upgrade::unversioned")]
+    pub format_version: u32,
+    pub database_name: String,
+    pub schema_name: String,
+    pub table_name: String,
+    /// `BASE TABLE`, `VIEW`, `MATERIALIZED VIEW`, ... as reported by information_schema
+    #[serde(default)]
+    pub table_type: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    pub columns: Vec<ColumnInfo>,
+    /// Primary key columns in key order
+    #[serde(default)]
+    pub primary_key: Vec<String>,
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+    /// Clustering key expression, e.g. `LINEAR(ORDER_DATE, REGION)`
+    #[serde(default)]
+    pub clustering_key: Option<String>,
+    /// Whether automatic clustering is running for the clustering key
+    #[serde(default)]
+    pub automatic_clustering: Option<bool>,
+    /// Whether search optimization is enabled; `None` when the account doesn't report it
+    #[serde(default)]
+    pub search_optimization: Option<bool>,
+    /// Time-travel retention period
+    #[serde(default)]
+    pub retention_time_in_days: Option<i32>,
+    #[serde(default)]
+    pub change_tracking: Option<bool>,
+    #[serde(default)]
+    pub is_transient: Option<bool>,
+    #[serde(default)]
+    pub is_temporary: Option<bool>,
+    /// RFC 3339 timestamps; `last_accessed` is only filled when access history is queried
+    #[serde(default)]
+    pub created: Option<String>,
+    #[serde(default)]
+    pub last_altered: Option<String>,
+    #[serde(default)]
+    pub last_accessed: Option<String>,
+    /// Reads over a recent window; only filled when usage is queried
+    #[serde(default)]
+    pub usage: Option<TableUsage>,
+    /// Owning teams from `--owners` rules
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// Roles that could see the table in a `--merge-roles` sweep; empty otherwise
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Rows returned by `--hooks` SQL, keyed by hook name, then by lower-cased column name
+    #[serde(default)]
+    pub custom: BTreeMap<String, BTreeMap<String, Option<String>>>,
+    /// The map run that captured the table; `None` in snapshots from before it was recorded
+    #[serde(default)]
+    pub provenance: Option<provenance::Provenance>,
+}
+
+/// How much a table was read over a recent window, from ACCOUNT_USAGE.ACCESS_HISTORY
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct TableUsage {
+    pub window_days: u32,
+    /// Queries that read the table; zero when nothing did
+    pub query_count: u64,
+    pub distinct_users: u64,
+}
+
+/// Kinds of tables and views, for `--table-types`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    /// Permanent tables
+    Base,
+    Transient,
+    Temporary,
+    /// External tables, and foreign tables on PostgreSQL
+    External,
+    View,
+    /// Materialized views
+    Materialized,
+}
+
+impl TableInfo {
+    /// `DATABASE.SCHEMA.TABLE`, unquoted
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}.{}", self.database_name, self.schema_name, self.table_name)
+    }
+
+    /// Whether this is a regular table rather than a view; older snapshots without a
+    /// recorded type are treated as tables
+    pub fn is_base_table(&self) -> bool {
+        self.table_type.as_deref().is_none_or(|t| t.eq_ignore_ascii_case("BASE TABLE"))
+    }
+
+    /// What kind of object this is; types this release doesn't know, and older snapshots
+    /// without a recorded type, count as base tables
+    pub fn kind(&self) -> TableKind {
+        let table_type = self.table_type.as_deref().unwrap_or_default().to_uppercase();
+        if table_type.contains("MATERIALIZED") {
+            TableKind::Materialized
+        } else if table_type.contains("VIEW") {
+            TableKind::View
+        } else if table_type.contains("TEMPORARY") || self.is_temporary == Some(true) {
+            TableKind::Temporary
+        } else if table_type.contains("EXTERNAL") || table_type.contains("FOREIGN") {
+            TableKind::External
+        } else if self.is_transient == Some(true) {
+            TableKind::Transient
+        } else {
+            TableKind::Base
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ForeignKeyInfo {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_database: String,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatabaseInfo {
+    pub name: String,
+    pub created_on: String,
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct WarehouseInfo {
+    #[serde(default = "upgrade::unversioned")]
+    pub format_version: u32,
+    pub name: String,
+    pub size: String,
+    pub state: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Seconds of inactivity before suspending; `None` when the warehouse never suspends
+    #[serde(default)]
+    pub auto_suspend: Option<i32>,
+    #[serde(default)]
+    pub auto_resume: Option<bool>,
+    /// Multi-cluster bounds; both 1 for a single-cluster warehouse
+    #[serde(default)]
+    pub min_cluster_count: Option<i32>,
+    #[serde(default)]
+    pub max_cluster_count: Option<i32>,
+    /// `STANDARD` or `ECONOMY`
+    #[serde(default)]
+    pub scaling_policy: Option<String>,
+}
+
+impl WarehouseInfo {
+    /// Whether sizing or scaling differs; `state` changes with every query and is ignored
+    pub fn config_differs(&self, other: &WarehouseInfo) -> bool {
+        self.size != other.size
+            || self.type_ != other.type_
+            || self.auto_suspend != other.auto_suspend
+            || self.auto_resume != other.auto_resume
+            || self.min_cluster_count != other.min_cluster_count
+            || self.max_cluster_count != other.max_cluster_count
+            || self.scaling_policy != other.scaling_policy
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaInfo {
+    pub name: String,
+    pub database_name: String,
+    pub created_on: String,
+    pub owner: String,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleInfo {
+    pub name: String,
+    pub created_on: String,
+    pub owner: String,
+    pub comment: Option<String>,
+}
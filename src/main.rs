@@ -1,376 +1,913 @@
-use anyhow::{Context, Result};
-use async_trait::async_trait;
+use anyhow::Result;
 use clap::Parser;
-use indicatif::{ProgressBar, ProgressStyle};
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use thiserror::Error;
-use tracing::{info, warn, error};
-use snowflake_connector_rs::{
-    SnowflakeClient, SnowflakeClientConfig, SnowflakeAuthMethod,
-    SnowflakeRow, SnowflakeSession,
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::{info, error, warn};
+
+use snowflake_mapper::account::{AccountSnapshot, ACCOUNT_FILE};
+use snowflake_mapper::atlas::AtlasClient;
+use snowflake_mapper::backends::fixture::MockSnowflakeOperations;
+use snowflake_mapper::backends::pool::SessionPool;
+use snowflake_mapper::backends::postgres::PostgresMapper;
+use snowflake_mapper::backends::Backend;
+use snowflake_mapper::bench::{self, BenchRun};
+use snowflake_mapper::backends::snowflake::SnowflakeOperations;
+use snowflake_mapper::backends::sweep::RoleSweep;
+use snowflake_mapper::cli::{
+    AccountArgs, Args, AuditVisibilityArgs, AuthMethod, BenchArgs, ChangelogArgs, Command, CompleteDatabasesArgs, CompletionsArgs, ConnectionArgs, DiffArgs, DocsArgs, DuplicatesArgs, ExportArgs, FailOn, ImpactArgs, ListArgs,
+    ListObject, ManArgs, MapArgs, MergeArgs, MigrationTool, MigrationsArgs, MockArgs, ScaffoldArgs, ScaffoldTool, OutputArgs, PushTarget, QueryArgs, ReplicationArgs, SchemaArgs, SharesArgs, StaleArgs, StatsArgs, UpgradeArgs, UsersArgs,
+};
+use snowflake_mapper::diff::ChangeMarks;
+use snowflake_mapper::exclude::ColumnFilter;
+use snowflake_mapper::fake::{FakeData, Faker};
+use snowflake_mapper::failures::{self, Failure, FailureManifest, FailureStage};
+use snowflake_mapper::guardrails::{CostEstimate, QueryBudget};
+use snowflake_mapper::hooks::SqlHooks;
+use snowflake_mapper::merge::{self, MergeInput, OnConflict};
+use snowflake_mapper::mock::{self, MockTarget};
+use snowflake_mapper::notify::SlackNotifier;
+use snowflake_mapper::output::{self, OutputWriter};
+use snowflake_mapper::ownership::Ownership;
+use snowflake_mapper::patch;
+use snowflake_mapper::progress::{BarProgress, NoProgress, ProgressEvent, ProgressListener};
+use snowflake_mapper::provenance::RunProvenance;
+use snowflake_mapper::querylog::QueryLog;
+use snowflake_mapper::registry::SchemaRegistryClient;
+use snowflake_mapper::retry;
+use snowflake_mapper::replication::{ReplicationSnapshot, REPLICATION_FILE};
+use snowflake_mapper::shares::{ShareInventory, SHARES_FILE};
+use snowflake_mapper::throttle::RateLimiter;
+use snowflake_mapper::upgrade::{self, FORMAT_VERSION};
+use snowflake_mapper::users::{UserInventory, USERS_FILE};
+use snowflake_mapper::visibility::{self, AccountObject};
+use snowflake_mapper::{
+    catalog, changelog, completions, diff, duplicates, formats, impact, query, stale, stats, DatabaseInfo, SnowflakeMapperBuilder, SnowflakeMapperError, TableInfo, WarehouseMapper,
 };
 
-#[derive(Debug, Error)]
-pub enum SnowflakeMapperError {
-    #[error("Failed to connect to Snowflake: {0}")]
-    ConnectionError(String),
-    
-    #[error("Failed to execute query: {0}")]
-    QueryError(String),
-    
-    #[error("Failed to read column {column}: {message}")]
-    ColumnError {
-        column: String,
-        message: String,
-    },
-    
-    #[error("Failed to write output: {0}")]
-    OutputError(String),
-    
-    #[error("Missing required environment variable: {0}")]
-    MissingEnvVar(String),
-}
-
-#[derive(Parser, Debug, Clone)]
-#[command(
-    name = "snowflake-mapper",
-    about = "A tool to fetch and map Snowflake database schemas",
-    version,
-    author
-)]
-pub struct Args {
-    /// Specific databases to process (comma-separated). If not provided, all accessible databases will be processed
-    #[arg(short, long, value_delimiter = ',')]
-    pub databases: Option<Vec<String>>,
-
-    /// Output directory for the JSON files
-    #[arg(short, long, default_value = "output")]
-    pub output_dir: PathBuf,
-
-    /// Number of retries for failed operations
-    #[arg(short, long, default_value = "3")]
-    pub retries: u32,
-
-    /// Delay in seconds between retries
-    #[arg(long, default_value = "5")]
-    pub retry_delay: u64,
-
-    /// Skip tables that fail to process
-    #[arg(long)]
-    pub skip_failed_tables: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ColumnInfo {
-    pub name: String,
-    pub data_type: String,
-    pub is_nullable: bool,
-    pub character_maximum_length: Option<i32>,
-    pub numeric_precision: Option<i32>,
-    pub numeric_scale: Option<i32>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TableInfo {
-    pub database_name: String,
-    pub schema_name: String,
-    pub table_name: String,
-    pub columns: Vec<ColumnInfo>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DatabaseInfo {
-    pub name: String,
-    pub created_on: String,
-    pub owner: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WarehouseInfo {
-    pub name: String,
-    pub size: String,
-    pub state: String,
-    pub type_: String,
-}
-
-#[async_trait]
-pub trait SnowflakeOperations {
-    async fn connect(&mut self) -> Result<()>;
-    async fn set_warehouse(&mut self, warehouse: &str) -> Result<()>;
-    async fn set_role(&mut self, role: &str) -> Result<()>;
-    async fn get_all_databases(&mut self) -> Result<Vec<DatabaseInfo>>;
-    async fn get_tables_for_database(&mut self, database: &str) -> Result<Vec<TableInfo>>;
-    async fn list_warehouses(&mut self) -> Result<Vec<WarehouseInfo>>;
-}
-
-pub struct SnowflakeMapper {
-    pub config: SnowflakeConfig,
-    pub client: Option<SnowflakeClient>,
-    pub session: Option<SnowflakeSession>,
-    pub args: Args,
-}
-
-pub struct SnowflakeConfig {
-    pub account: String,
-    pub username: String,
-    pub password: String,
-    pub warehouse: String,
-    pub database: Option<String>,
-    pub role: Option<String>,
-}
-
-impl SnowflakeMapper {
-    pub fn new(config: SnowflakeConfig, args: Args) -> Self {
-        Self {
-            config,
-            client: None,
-            session: None,
-            args,
-        }
-    }
-
-    #[allow(dead_code)]
-    async fn with_retry<F, T>(&self, operation: F) -> Result<T>
-    where
-        F: Fn() -> Result<T> + Send + Sync,
-    {
-        let mut last_error = None;
-        for attempt in 0..=self.args.retries {
-            if attempt > 0 {
-                warn!("Retry attempt {} of {}", attempt, self.args.retries);
-                tokio::time::sleep(std::time::Duration::from_secs(self.args.retry_delay)).await;
-            }
+async fn run_docs(docs: &DocsArgs) -> Result<()> {
+    let tables = catalog::load_snapshot(&docs.input)?;
+    let changes = match &docs.changes_since {
+        Some(path) => Some(ChangeMarks::new(&diff::diff_catalogs(&catalog::load_snapshot(path)?, &tables))),
+        None => None,
+    };
+    let pages = formats::docs::render_site(&tables, &docs.out, changes.as_ref()).await?;
+    info!("Wrote {} table pages to {}", pages, docs.out.display());
+    Ok(())
+}
+
+async fn run_migrations(migrations: &MigrationsArgs) -> Result<()> {
+    let current = catalog::load_snapshot(&migrations.input)?;
+    let previous = match &migrations.previous {
+        Some(path) => catalog::load_snapshot(path)?,
+        None => Vec::new(),
+    };
+    let changes = diff::diff_catalogs(&previous, &current);
+
+    let version = match &migrations.migration_version {
+        Some(version) => version.clone(),
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs()
+            .to_string(),
+    };
+    let description = if migrations.previous.is_some() { "snowflake_mapper_changes" } else { "snowflake_mapper_baseline" };
+
+    let path = match migrations.tool {
+        MigrationTool::Flyway => {
+            let path = migrations.out.join(format!("V{}__{}.sql", version, description));
+            output::write_text_output(path.clone(), &formats::migrations::render_flyway(&changes)).await?;
+            path
+        }
+        MigrationTool::Liquibase => {
+            let path = migrations.out.join(format!("changelog-{}.xml", version));
+            let changelog = formats::migrations::render_liquibase(&changes, &version);
+            output::write_text_output(path.clone(), &changelog).await?;
+            path
+        }
+    };
+    info!("Wrote {} changes to {}", changes.len(), path.display());
+    Ok(())
+}
+
+async fn run_mock(args: &MockArgs) -> Result<()> {
+    let mut tables = catalog::load_snapshot(&args.input)?;
+    let mut databases: Vec<&str> = tables.iter().map(|table| table.database_name.as_str()).collect();
+    databases.sort_unstable();
+    databases.dedup();
+    let database = match (&args.database, databases.as_slice()) {
+        (Some(database), _) => database.clone(),
+        (None, [database]) => database.to_string(),
+        (None, _) => {
+            let message = format!("{} holds databases {}; pick one with --database", args.input.display(), databases.join(", "));
+            return Err(SnowflakeMapperError::SnapshotError(message).into());
+        }
+    };
+    tables.retain(|table| table.database_name == database);
+    if tables.is_empty() {
+        return Err(SnowflakeMapperError::SnapshotError(format!("{} has no tables in {}", args.input.display(), database)).into());
+    }
+
+    let faker = args.with_fake_data.map(|rows| Faker::new(&tables, FakeData { rows, seed: args.seed })).transpose()?;
+    let faker = faker.as_ref();
+    match args.target {
+        MockTarget::Duckdb => {
+            mock::create_duckdb(&args.out, &args.duckdb_cli, &tables, faker).await?;
+            info!("Created {} tables of {} in {}", tables.len(), database, args.out.display());
+        }
+        MockTarget::DuckdbSql => {
+            output::write_text_output(args.out.clone(), &mock::render_duckdb(&tables, faker)).await?;
+            info!("Wrote {} tables of {} to {}; load it with `duckdb {}.duckdb < {}`", tables.len(), database, args.out.display(), database, args.out.display());
+        }
+        MockTarget::Sqlite => {
+            let files = mock::create_sqlite(&args.out, &tables, faker)?;
+            info!("Created {} tables of {} in {} SQLite files under {}", tables.len(), database, files.len(), args.out.display());
+        }
+        MockTarget::Csv => {
+            let files = mock::write_csv(&args.out, &tables, faker)?;
+            info!("Wrote {} CSV files for {} under {}", files.len(), database, args.out.display());
+        }
+    }
+    Ok(())
+}
+
+async fn run_scaffold(args: &ScaffoldArgs) -> Result<()> {
+    let tables = catalog::load_snapshot(&args.input)?;
+    let files = match args.tool {
+        ScaffoldTool::Dbt => formats::staging::render_dbt(&tables),
+        ScaffoldTool::Sqlmesh => formats::staging::render_sqlmesh(&tables),
+    };
+    // Models are meant to be edited, so only missing ones are written
+    let (mut written, mut kept) = (0, 0);
+    for file in files {
+        let path = args.out.join(&file.path);
+        if !file.generated && path.exists() {
+            kept += 1;
+            continue;
+        }
+        output::write_text_output(path, &file.contents).await?;
+        written += usize::from(!file.generated);
+    }
+    info!("Wrote {} staging models to {} ({} existing models kept)", written, args.out.display(), kept);
+    Ok(())
+}
 
-            match operation() {
-                Ok(result) => return Ok(result),
-                Err(e) => {
-                    last_error = Some(e);
-                    error!("Operation failed: {}", last_error.as_ref().unwrap());
+async fn run_stale(args: &StaleArgs) -> Result<()> {
+    let tables = catalog::load_snapshot(&args.input)?;
+    let report = stale::stale_tables(&tables, args.older_than, chrono::Utc::now());
+    if args.json {
+        output::write_stdout(&serde_json::to_string_pretty(&report)?)?;
+    } else {
+        output::write_stdout(&stale::render_text(&report))?;
+    }
+    info!("{} of {} tables are stale", report.len(), tables.len());
+    Ok(())
+}
+
+/// Schema changes between two snapshots, plus warehouse changes when both have an inventory
+fn snapshot_changes(old_path: &Path, new_path: &Path) -> Result<Vec<diff::SchemaChange>> {
+    let old = catalog::load_snapshot(old_path)?;
+    let new = catalog::load_snapshot(new_path)?;
+    let mut changes = diff::diff_catalogs(&old, &new);
+    // Only when both runs captured an inventory, otherwise every warehouse would look added
+    if let (Some(old), Some(new)) = (catalog::load_warehouses(old_path)?, catalog::load_warehouses(new_path)?) {
+        changes.extend(diff::diff_warehouses(&old, &new));
+    }
+    Ok(changes)
+}
+
+async fn run_diff(args: &DiffArgs) -> Result<()> {
+    let changes = snapshot_changes(&args.old, &args.new)?;
+    let ownership = args.owners.as_deref().map(Ownership::from_file).transpose()?;
+    if args.json_patch || args.merge_patch {
+        let (old, new) = (catalog::load_snapshot(&args.old)?, catalog::load_snapshot(&args.new)?);
+        let patch = if args.json_patch { serde_json::Value::from(patch::json_patch(&old, &new)) } else { patch::merge_patch(&old, &new) };
+        output::write_stdout(&serde_json::to_string_pretty(&patch)?)?;
+    } else if args.json {
+        let owners = |change: &diff::SchemaChange| match (&ownership, change.table()) {
+            (Some(ownership), Some((database, schema, table))) => ownership.owners(database, schema, table).to_vec(),
+            _ => Vec::new(),
+        };
+        let classified: Vec<_> = changes
+            .iter()
+            .map(|change| diff::ClassifiedChange { severity: change.severity(), owners: owners(change), change })
+            .collect();
+        output::write_stdout(&serde_json::to_string_pretty(&classified)?)?;
+    } else {
+        output::write_stdout(&diff::render_text(&changes))?;
+    }
+    let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+    info!("{} changes ({} breaking) between {} and {}", changes.len(), breaking, args.old.display(), args.new.display());
+    if let (true, Some(ownership)) = (args.notify, &ownership) {
+        if !changes.is_empty() {
+            let heading = format!("Schema drift in {} since {}", args.new.display(), args.old.display());
+            SlackNotifier::new().notify_owners(ownership, &changes, &heading).await?;
+        }
+    }
+    match args.fail_on {
+        Some(FailOn::Breaking) if breaking > 0 => Err(SnowflakeMapperError::SchemaDrift(format!("{} breaking changes", breaking)).into()),
+        Some(FailOn::Any) if !changes.is_empty() => Err(SnowflakeMapperError::SchemaDrift(format!("{} changes", changes.len())).into()),
+        _ => Ok(()),
+    }
+}
+
+async fn run_changelog(args: &ChangelogArgs) -> Result<()> {
+    let name = |path: &Path| path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+    let mut out = format!("# {}\n\n", args.title);
+    let mut total = 0;
+    // Newest release first, as changelogs are read
+    for pair in args.snapshots.windows(2).rev() {
+        let changes = snapshot_changes(&pair[0], &pair[1])?;
+        total += changes.len();
+        let title = format!("{} (since {})", name(&pair[1]), name(&pair[0]));
+        out.push_str(&changelog::render_release(&title, &changes));
+        out.push('\n');
+    }
+    output::write_stdout(&format!("{}\n", out.trim_end()))?;
+    info!("{} changes across {} snapshots", total, args.snapshots.len());
+    Ok(())
+}
+
+async fn run_export(args: &ExportArgs) -> Result<()> {
+    let output = OutputWriter::new(&args.output)?;
+    let exclude = column_filter(&args.output)?;
+    let mut tables = catalog::load_snapshot(&args.input)?;
+    tables.retain(|table| args.output.keeps_table(table));
+    if let Some(exclude) = &exclude {
+        tables = exclude.apply(tables);
+    }
+    let table_count = tables.len();
+    write_catalog(output, tables).await?;
+    info!("Exported {} tables from {}", table_count, args.input.display());
+    Ok(())
+}
+
+/// Write a loaded catalog one database at a time, keeping the per-database layout of the
+/// original run
+async fn write_catalog(mut output: OutputWriter, tables: Vec<TableInfo>) -> Result<()> {
+    let mut databases: Vec<(String, Vec<_>)> = Vec::new();
+    for table in tables {
+        match databases.iter_mut().find(|(name, _)| *name == table.database_name) {
+            Some((_, tables)) => tables.push(table),
+            None => databases.push((table.database_name.clone(), vec![table])),
+        }
+    }
+    for (database, tables) in databases {
+        output.write_database(&database, tables).await?;
+    }
+    output.finish().await
+}
+
+async fn run_upgrade(args: &UpgradeArgs) -> Result<()> {
+    let mut outdated = 0;
+    for path in &args.snapshots {
+        let files = if path.is_dir() { catalog::snapshot_files(path)? } else { vec![path.clone()] };
+        for file in files {
+            if upgrade::upgrade_file(&file, args.check)? {
+                outdated += 1;
+                if args.check {
+                    warn!("{} needs upgrading to format version {}", file.display(), FORMAT_VERSION);
+                } else {
+                    info!("Upgraded {}", file.display());
                 }
             }
         }
+    }
+    if args.check && outdated > 0 {
+        return Err(SnowflakeMapperError::SnapshotError(format!("{} files need `upgrade`", outdated)).into());
+    }
+    info!("{} files {} format version {}", outdated, if args.check { "behind" } else { "upgraded to" }, FORMAT_VERSION);
+    Ok(())
+}
 
-        Err(last_error.unwrap())
+async fn run_merge(args: &MergeArgs) -> Result<()> {
+    let mut inputs = Vec::new();
+    for path in &args.snapshots {
+        inputs.push(MergeInput {
+            label: path.display().to_string(),
+            tables: catalog::load_snapshot(path)?,
+            warehouses: catalog::load_warehouses(path)?,
+        });
+    }
+    let merged = merge::merge(inputs, args.on_conflict);
+    for conflict in &merged.conflicts {
+        warn!("{}", conflict);
+    }
+    if args.on_conflict == OnConflict::Error && !merged.conflicts.is_empty() {
+        return Err(SnowflakeMapperError::MergeConflict(format!(
+            "{} objects differ between snapshots; pass --on-conflict first or last to pick one",
+            merged.conflicts.len()
+        ))
+        .into());
     }
 
-    async fn ensure_connected(&mut self) -> Result<()> {
-        if self.client.is_none() {
-            let client = SnowflakeClient::new(
-                &self.config.username,
-                SnowflakeAuthMethod::Password(self.config.password.clone()),
-                SnowflakeClientConfig {
-                    account: self.config.account.clone(),
-                    role: self.config.role.clone(),
-                    warehouse: Some(self.config.warehouse.clone()),
-                    database: self.config.database.clone(),
-                    schema: None,
-                    timeout: Some(std::time::Duration::from_secs(30)),
-                },
-            ).context("Failed to create Snowflake client")?;
+    let mut tables = merged.tables;
+    tables.retain(|table| args.output.keeps_table(table));
+    if let Some(exclude) = column_filter(&args.output)? {
+        tables = exclude.apply(tables);
+    }
+    let table_count = tables.len();
+    write_catalog(OutputWriter::new(&args.output)?, tables).await?;
+    if let (Some(warehouses), false) = (&merged.warehouses, args.output.writes_to_stdout()) {
+        output::write_formatted_output(args.output.output_dir.join(catalog::WAREHOUSES_FILE), warehouses).await?;
+    }
+    info!(
+        "Merged {} tables from {} snapshots ({} conflicts)",
+        table_count,
+        args.snapshots.len(),
+        merged.conflicts.len()
+    );
+    Ok(())
+}
 
-            let session = client.create_session()
-                .await
-                .context("Failed to create Snowflake session")?;
+async fn run_list(args: &ListArgs) -> Result<()> {
+    dotenv::dotenv().ok();
+    let state = RunState::new(&args.connection)?;
+    // Only databases are part of the generic backend interface
+    let snowflake = || async {
+        if args.connection.backend != Backend::Snowflake {
+            return Err(SnowflakeMapperError::ConnectionError(
+                "Only `list databases` is supported outside the Snowflake backend".to_string(),
+            )
+            .into());
+        }
+        let mut client = create_snowflake_client(&args.connection, Enrichments::default(), &state)?;
+        client.connect().await?;
+        Ok::<_, anyhow::Error>(client)
+    };
 
-            self.client = Some(client);
-            self.session = Some(session);
+    let (json, table) = match args.object {
+        ListObject::Databases => {
+            let mut client = create_client(&args.connection, Enrichments::default(), &state)?;
+            client.connect().await?;
+            let databases = client.get_all_databases().await?;
+            let rows: Vec<[String; 3]> =
+                databases.iter().map(|db| [db.name.clone(), db.owner.clone(), db.created_on.clone()]).collect();
+            (serde_json::to_string_pretty(&databases)?, output::render_table(["NAME", "OWNER", "CREATED ON"], &rows))
+        }
+        ListObject::Schemas => {
+            // clap requires --database for schemas
+            let database = args.database.as_deref().unwrap_or_default();
+            let schemas = snowflake().await?.list_schemas(database).await?;
+            let rows: Vec<[String; 4]> = schemas
+                .iter()
+                .map(|schema| {
+                    [
+                        schema.name.clone(),
+                        schema.owner.clone(),
+                        schema.created_on.clone(),
+                        schema.comment.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            (serde_json::to_string_pretty(&schemas)?, output::render_table(["NAME", "OWNER", "CREATED ON", "COMMENT"], &rows))
+        }
+        ListObject::Warehouses => {
+            let warehouses = snowflake().await?.list_warehouses().await?;
+            let rows: Vec<[String; 6]> = warehouses
+                .iter()
+                .map(|warehouse| {
+                    [
+                        warehouse.name.clone(),
+                        warehouse.size.clone(),
+                        warehouse.state.clone(),
+                        warehouse.type_.clone(),
+                        warehouse.auto_suspend.map(|seconds| format!("{}s", seconds)).unwrap_or_else(|| "never".to_string()),
+                        match (warehouse.min_cluster_count, warehouse.max_cluster_count) {
+                            (Some(min), Some(max)) => format!("{}-{}", min, max),
+                            _ => String::new(),
+                        },
+                    ]
+                })
+                .collect();
+            let header = ["NAME", "SIZE", "STATE", "TYPE", "AUTO SUSPEND", "CLUSTERS"];
+            (serde_json::to_string_pretty(&warehouses)?, output::render_table(header, &rows))
+        }
+        ListObject::Roles => {
+            let roles = snowflake().await?.list_roles().await?;
+            let rows: Vec<[String; 4]> = roles
+                .iter()
+                .map(|role| {
+                    [role.name.clone(), role.owner.clone(), role.created_on.clone(), role.comment.clone().unwrap_or_default()]
+                })
+                .collect();
+            (serde_json::to_string_pretty(&roles)?, output::render_table(["NAME", "OWNER", "CREATED ON", "COMMENT"], &rows))
         }
-        Ok(())
+    };
+
+    output::write_stdout(&if args.json { json } else { table })?;
+    Ok(())
+}
+
+async fn run_query(args: &QueryArgs) -> Result<()> {
+    // Parse first so a typo fails before loading a large snapshot
+    let path = query::parse(&args.expression)?;
+    let tables = catalog::load_snapshot(&args.input)?;
+    let matches = query::query_catalog(&tables, &path)?;
+    if args.locate {
+        output::write_stdout(&serde_json::to_string_pretty(&matches)?)?;
+    } else {
+        let values: Vec<_> = matches.iter().map(|m| &m.value).collect();
+        output::write_stdout(&serde_json::to_string_pretty(&values)?)?;
+    }
+    info!("{} matches in {} tables", matches.len(), tables.len());
+    Ok(())
+}
+
+async fn run_impact(args: &ImpactArgs) -> Result<()> {
+    let tables = catalog::load_snapshot(&args.input)?;
+    let impacted = impact::column_impact(&tables, &args.column);
+    if args.json {
+        output::write_stdout(&serde_json::to_string_pretty(&impacted)?)?;
+    } else {
+        output::write_stdout(&impact::render_text(&impacted))?;
+    }
+    let direct = impacted.iter().filter(|i| i.depth == 0).count();
+    info!("{} columns named {}, {} more through foreign keys", direct, args.column, impacted.len() - direct);
+    Ok(())
+}
+
+async fn run_stats(args: &StatsArgs) -> Result<()> {
+    let tables = catalog::load_snapshot(&args.input)?;
+    let summary = stats::catalog_stats(&tables, args.top);
+    if args.json {
+        output::write_stdout(&serde_json::to_string_pretty(&summary)?)?;
+    } else {
+        output::write_stdout(&stats::render_text(&summary))?;
+    }
+    Ok(())
+}
+
+async fn run_duplicates(args: &DuplicatesArgs) -> Result<()> {
+    let tables = catalog::load_snapshot(&args.input)?;
+    let pairs = duplicates::find_duplicates(&tables, args.threshold, args.min_columns);
+    if args.json {
+        output::write_stdout(&serde_json::to_string_pretty(&pairs)?)?;
+    } else {
+        output::write_stdout(&duplicates::render_text(&pairs))?;
+    }
+    info!("{} likely duplicate pairs among {} tables", pairs.len(), tables.len());
+    Ok(())
+}
+
+fn run_schema(args: &SchemaArgs) -> Result<()> {
+    let mut json = serde_json::to_string_pretty(&args.document.schema())?;
+    json.push('\n');
+    output::write_stdout(&json)
+}
+
+fn run_completions(args: &CompletionsArgs) -> Result<()> {
+    completions::write_completions(args.shell, &mut std::io::stdout().lock())
+}
+
+fn run_man(args: &ManArgs) -> Result<()> {
+    let pages = completions::write_man_pages(&args.out)?;
+    info!("Wrote {} man pages to {}", pages, args.out.display());
+    Ok(())
+}
+
+fn run_complete_databases(args: &CompleteDatabasesArgs) -> Result<()> {
+    for database in completions::snapshot_databases(&args.input) {
+        output::write_stdout(&format!("{}\n", database))?;
     }
+    Ok(())
+}
+
+/// A connected client for subcommands that only make sense against Snowflake, and the
+/// provenance to stamp their document with
+async fn connect_snowflake(connection: &ConnectionArgs, command: &str) -> Result<(Box<dyn SnowflakeOperations>, RunProvenance)> {
+    dotenv::dotenv().ok();
+    if connection.backend != Backend::Snowflake {
+        return Err(SnowflakeMapperError::ConnectionError(format!("`{}` is only supported for the Snowflake backend", command)).into());
+    }
+    let state = RunState::new(connection)?;
+    let provenance = RunProvenance::start(connection, Arc::clone(&state.budget));
+    let mut client = create_snowflake_client(connection, Enrichments::default(), &state)?;
+    client.connect().await?;
+    Ok((client, provenance))
+}
 
-    fn get_session(&self) -> Result<&SnowflakeSession> {
-        self.session.as_ref().context("Not connected to Snowflake")
+async fn run_account(args: &AccountArgs) -> Result<()> {
+    let (mut client, provenance) = connect_snowflake(&args.connection, "account").await?;
+
+    let parameters = client.get_account_parameters().await?;
+    let resource_monitors = client.list_resource_monitors().await?;
+    let snapshot = AccountSnapshot {
+        format_version: FORMAT_VERSION,
+        captured_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        provenance: Some(provenance.finish()),
+        parameters,
+        resource_monitors,
+    };
+    if args.output_dir.as_os_str() == "-" {
+        output::write_stdout(&serde_json::to_string_pretty(&snapshot)?)?;
+    } else {
+        output::write_formatted_output(args.output_dir.join(ACCOUNT_FILE), &snapshot).await?;
     }
+    info!(
+        "Captured {} account parameters ({} overridden) and {} resource monitors",
+        snapshot.parameters.len(),
+        snapshot.overridden_parameters().count(),
+        snapshot.resource_monitors.len()
+    );
+    Ok(())
+}
 
-    fn get_value_from_row(row: &SnowflakeRow, column: &str) -> Result<String> {
-        match row.get::<Option<String>>(column) {
-            Ok(Some(value)) => Ok(value),
-            Ok(None) => Ok(String::new()),
-            Err(e) => Err(SnowflakeMapperError::ColumnError {
-                column: column.to_string(),
-                message: e.to_string(),
-            }.into())
+async fn run_users(args: &UsersArgs) -> Result<()> {
+    let (mut client, provenance) = connect_snowflake(&args.connection, "users").await?;
+    let users = client.list_users(args.source).await?;
+    let inventory = UserInventory {
+        format_version: FORMAT_VERSION,
+        captured_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        provenance: Some(provenance.finish()),
+        source: args.source,
+        users,
+    };
+    if args.output_dir.as_os_str() == "-" {
+        output::write_stdout(&serde_json::to_string_pretty(&inventory)?)?;
+    } else {
+        output::write_formatted_output(args.output_dir.join(USERS_FILE), &inventory).await?;
+    }
+    info!(
+        "Captured {} users ({} disabled, {} service accounts)",
+        inventory.users.len(),
+        inventory.users.iter().filter(|user| user.disabled).count(),
+        inventory.users.iter().filter(|user| user.is_service()).count()
+    );
+    Ok(())
+}
+
+async fn run_shares(args: &SharesArgs) -> Result<()> {
+    let (mut client, provenance) = connect_snowflake(&args.connection, "shares").await?;
+    let mut shares = client.list_shares().await?;
+    for share in &mut shares {
+        // An inbound share that was revoked or never accepted can't be described
+        match client.describe_share(&share.name).await {
+            Ok(objects) => share.objects = objects,
+            Err(e) => warn!("Failed to list the objects in share {}: {}", share.name, e),
         }
     }
+    let inventory = ShareInventory {
+        format_version: FORMAT_VERSION,
+        captured_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        provenance: Some(provenance.finish()),
+        shares,
+    };
+    if args.output_dir.as_os_str() == "-" {
+        output::write_stdout(&serde_json::to_string_pretty(&inventory)?)?;
+    } else {
+        output::write_formatted_output(args.output_dir.join(SHARES_FILE), &inventory).await?;
+    }
+    let outbound = inventory.shares.iter().filter(|share| share.is_outbound()).count();
+    info!("Captured {} outbound and {} inbound shares", outbound, inventory.shares.len() - outbound);
+    Ok(())
+}
 
-    fn get_i32_from_row(row: &SnowflakeRow, column: &str) -> Result<Option<i32>> {
-        match row.get::<Option<String>>(column) {
-            Ok(Some(value)) if !value.is_empty() => {
-                value.parse()
-                    .map(Some)
-                    .map_err(|e| SnowflakeMapperError::ColumnError {
-                        column: column.to_string(),
-                        message: format!("Failed to parse as i32: {}", e),
-                    }.into())
-            },
-            Ok(_) => Ok(None),
-            Err(e) => Err(SnowflakeMapperError::ColumnError {
-                column: column.to_string(),
-                message: e.to_string(),
-            }.into())
-        }
-    }
-}
-
-#[async_trait]
-impl SnowflakeOperations for SnowflakeMapper {
-    async fn connect(&mut self) -> Result<()> {
-        self.ensure_connected().await?;
-        
-        // Store values before using them to avoid borrowing issues
-        let warehouse = self.config.warehouse.clone();
-        let role = self.config.role.clone();
-        
-        // Set warehouse immediately after connection
-        self.set_warehouse(&warehouse).await?;
-        if let Some(role) = role {
-            self.set_role(&role).await?;
-        }
-        Ok(())
-    }
-
-    async fn set_warehouse(&mut self, warehouse: &str) -> Result<()> {
-        info!("Setting warehouse to: {}", warehouse);
-        
-        // Try to get list of warehouses first
-        info!("Listing available warehouses...");
-        let warehouses = self.list_warehouses().await?;
-        let warehouse_names: Vec<String> = warehouses.iter().map(|w| w.name.clone()).collect();
-        info!("Available warehouses: {:?}", warehouse_names);
-        
-        // Use specified warehouse or fall back to COMPUTE_WH
-        let target_warehouse = if warehouse_names.iter().any(|w| w.eq_ignore_ascii_case(warehouse)) {
-            warehouse.to_string()
-        } else {
-            warn!("Warehouse '{}' not found, falling back to COMPUTE_WH", warehouse);
-            "COMPUTE_WH".to_string()
-        };
+async fn run_replication(args: &ReplicationArgs) -> Result<()> {
+    let (mut client, provenance) = connect_snowflake(&args.connection, "replication").await?;
+    let mut groups = client.list_replication_groups().await?;
+    for group in &mut groups {
+        // Linked groups in other accounts are listed too, but can't be inspected from here
+        match client.list_replication_group_databases(&group.name).await {
+            Ok(databases) => group.databases = databases,
+            Err(e) => warn!("Failed to list the databases in replication group {}: {}", group.name, e),
+        }
+    }
+    let databases = client.list_replication_databases().await?;
+    let snapshot = ReplicationSnapshot {
+        format_version: FORMAT_VERSION,
+        captured_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        provenance: Some(provenance.finish()),
+        groups,
+        databases,
+    };
+    if args.output_dir.as_os_str() == "-" {
+        output::write_stdout(&serde_json::to_string_pretty(&snapshot)?)?;
+    } else {
+        output::write_formatted_output(args.output_dir.join(REPLICATION_FILE), &snapshot).await?;
+    }
+    info!(
+        "Captured {} replication and failover groups and {} replicated databases",
+        snapshot.groups.len(),
+        snapshot.databases.len()
+    );
+    Ok(())
+}
 
-        let query = format!("USE WAREHOUSE \"{}\"", target_warehouse);
-        info!("Executing query: {}", query);
-        self.get_session()?
-            .query(query.as_str())
-            .await
-            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to set warehouse: {}", e)))?;
-        info!("Successfully set warehouse to: {}", target_warehouse);
-        Ok(())
-    }
-
-    async fn set_role(&mut self, role: &str) -> Result<()> {
-        info!("Setting role to: {}", role);
-        let query = format!("USE ROLE \"{}\"", role);
-        info!("Executing query: {}", query);
-        self.get_session()?
-            .query(query.as_str())
-            .await
-            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to set role: {}", e)))?;
-        info!("Successfully set role to: {}", role);
-        Ok(())
-    }
-
-    async fn get_all_databases(&mut self) -> Result<Vec<DatabaseInfo>> {
-        self.ensure_connected().await?;
-        let rows = self.get_session()?
-            .query("SHOW DATABASES")
-            .await
-            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list databases: {}", e)))?;
-        
-        let mut databases = Vec::new();
-        for row in rows {
-            databases.push(DatabaseInfo {
-                name: Self::get_value_from_row(&row, "name")?,
-                created_on: Self::get_value_from_row(&row, "created_on")?,
-                owner: Self::get_value_from_row(&row, "owner")?,
-            });
-        }
-        Ok(databases)
-    }
-
-    async fn get_tables_for_database(&mut self, database: &str) -> Result<Vec<TableInfo>> {
-        self.ensure_connected().await?;
-        let query = format!(
-            "SELECT table_schema, table_name, column_name, data_type, 
-             is_nullable, character_maximum_length, numeric_precision, numeric_scale
-             FROM {}.information_schema.columns
-             ORDER BY table_schema, table_name, ordinal_position",
-            database
-        );
-
-        let rows = self.get_session()?
-            .query(query.as_str())
-            .await
-            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to get tables for database {}: {}", database, e)))?;
-
-        let mut tables: Vec<TableInfo> = Vec::new();
-        let mut current_table: Option<TableInfo> = None;
-
-        for row in rows {
-            let schema_name = Self::get_value_from_row(&row, "table_schema")?;
-            let table_name = Self::get_value_from_row(&row, "table_name")?;
-
-            if current_table.as_ref().map_or(true, |t| {
-                t.schema_name != schema_name || t.table_name != table_name
-            }) {
-                if let Some(table) = current_table.take() {
-                    tables.push(table);
+async fn run_audit_visibility(args: &AuditVisibilityArgs) -> Result<()> {
+    let (mut client, _) = connect_snowflake(&args.connection, "audit-visibility").await?;
+    let account_usage = client.list_account_usage_objects().await?;
+    let visible = match &args.snapshot {
+        Some(path) => visibility::catalog_objects(&catalog::load_snapshot(path)?),
+        None => {
+            let mut visible = Vec::new();
+            for database in client.get_all_databases().await? {
+                // A database the role can list but not use hides its schemas and tables
+                match client.list_visible_objects(&database.name).await {
+                    Ok(objects) => visible.extend(objects),
+                    Err(e) => warn!("Failed to list the objects in {}: {}", database.name, e),
                 }
-                current_table = Some(TableInfo {
-                    database_name: database.to_string(),
-                    schema_name,
-                    table_name,
-                    columns: Vec::new(),
-                });
-            }
-
-            if let Some(table) = current_table.as_mut() {
-                table.columns.push(ColumnInfo {
-                    name: Self::get_value_from_row(&row, "column_name")?,
-                    data_type: Self::get_value_from_row(&row, "data_type")?,
-                    is_nullable: Self::get_value_from_row(&row, "is_nullable")?.eq_ignore_ascii_case("YES"),
-                    character_maximum_length: Self::get_i32_from_row(&row, "character_maximum_length")?,
-                    numeric_precision: Self::get_i32_from_row(&row, "numeric_precision")?,
-                    numeric_scale: Self::get_i32_from_row(&row, "numeric_scale")?,
-                });
+                visible.push(AccountObject { database: database.name, schema: None, table: None, owner: Some(database.owner) });
             }
+            visible
         }
+    };
+
+    let report = visibility::compare(&account_usage, &visible, args.snapshot.is_some());
+    if args.json {
+        output::write_stdout(&serde_json::to_string_pretty(&report)?)?;
+    } else {
+        output::write_stdout(&visibility::render_text(&report))?;
+    }
+    info!(
+        "{} of {} objects in ACCOUNT_USAGE are hidden from the current role",
+        report.hidden.len(),
+        report.account_usage_objects
+    );
+    if args.fail_on_hidden && !report.hidden.is_empty() {
+        return Err(SnowflakeMapperError::HiddenObjects(format!("{} hidden objects", report.hidden.len())).into());
+    }
+    Ok(())
+}
 
-        if let Some(table) = current_table.take() {
-            tables.push(table);
+async fn run_bench(args: &BenchArgs) -> Result<()> {
+    dotenv::dotenv().ok();
+    // Catalog files are written to a scratch directory, removed after each run
+    let scratch = std::env::temp_dir().join(format!("snowflake-mapper-bench-{}", std::process::id()));
+    let shard_settings: Vec<Option<u64>> = std::iter::once(None).chain(args.shard_columns.iter().copied().map(Some)).collect();
+    let mut runs = Vec::new();
+    for &jobs in &args.jobs {
+        for &shard_columns in &shard_settings {
+            info!("Benchmarking --jobs {}{}", jobs, shard_columns.map_or(String::new(), |columns| format!(" --shard-columns {}", columns)));
+            let run = bench_run(args, jobs, shard_columns, &scratch).await;
+            let _ = std::fs::remove_dir_all(&scratch);
+            runs.push(run?);
         }
+    }
+    if args.json {
+        output::write_stdout(&serde_json::to_string_pretty(&runs)?)?;
+    } else {
+        output::write_stdout(&bench::render_text(&runs))?;
+    }
+    Ok(())
+}
+
+/// Map the databases once with the given settings, timing each phase
+async fn bench_run(args: &BenchArgs, jobs: u32, shard_columns: Option<u64>, scratch: &Path) -> Result<BenchRun> {
+    let started = Instant::now();
+    let mut state = RunState::new(&args.connection)?;
+    state.shard_columns = shard_columns.map(|columns| columns as usize);
+    let replaying = args.connection.fixture.is_some() && !args.connection.record;
+    if jobs > 1 && args.connection.backend == Backend::Snowflake && !replaying {
+        state.sessions = Some(snowflake_builder(&args.connection)?.build_session_pool(jobs as usize)?);
+    }
+
+    let phase = Instant::now();
+    let mut clients = Vec::new();
+    for _ in 0..jobs {
+        let mut client = create_client(&args.connection, Enrichments::default(), &state)?;
+        client.connect().await?;
+        clients.push(client);
+    }
+    let connect = phase.elapsed();
+
+    let phase = Instant::now();
+    let listed = clients[0].get_all_databases().await?;
+    let list_databases = phase.elapsed();
+    let databases: VecDeque<String> = match &args.databases {
+        Some(databases) => databases.iter().cloned().collect(),
+        None => listed.into_iter().map(|database| database.name).collect(),
+    };
+
+    let phase = Instant::now();
+    let queue = Arc::new(Mutex::new(databases));
+    let mut workers = Vec::new();
+    for mut client in clients {
+        let queue = Arc::clone(&queue);
+        workers.push(tokio::spawn(async move {
+            let mut fetched = Vec::new();
+            loop {
+                let Some(database) = queue.lock().unwrap().pop_front() else { break };
+                let started = Instant::now();
+                let tables = client.get_tables_for_database(&database).await?;
+                fetched.push((database, started.elapsed(), tables));
+            }
+            Ok::<_, anyhow::Error>(fetched)
+        }));
+    }
+    let mut fetched = Vec::new();
+    for worker in workers {
+        fetched.extend(worker.await??);
+    }
+    let fetch = phase.elapsed();
+
+    let phase = Instant::now();
+    let mut files = Vec::new();
+    for (database, _, tables) in &fetched {
+        files.push((database, serde_json::to_string_pretty(tables)?));
+    }
+    let serialize = phase.elapsed();
+
+    let phase = Instant::now();
+    std::fs::create_dir_all(scratch)
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", scratch.display(), e)))?;
+    for (database, json) in &files {
+        output::write_text_output(scratch.join(format!("{}.json", database)), json).await?;
+    }
+    let write = phase.elapsed();
+
+    let slowest = fetched.iter().max_by_key(|(_, elapsed, _)| *elapsed);
+    let fetch_times: Vec<f64> = fetched.iter().map(|(_, elapsed, _)| bench::millis(*elapsed)).collect();
+    Ok(BenchRun {
+        jobs,
+        shard_columns,
+        databases: fetched.len(),
+        tables: fetched.iter().map(|(_, _, tables)| tables.len()).sum(),
+        queries: state.budget.used(),
+        connect_ms: bench::millis(connect),
+        list_databases_ms: bench::millis(list_databases),
+        fetch_ms: bench::millis(fetch),
+        mean_database_ms: fetch_times.iter().sum::<f64>() / fetch_times.len().max(1) as f64,
+        slowest_database: slowest.map(|(database, _, _)| database.clone()),
+        slowest_database_ms: slowest.map_or(0.0, |(_, elapsed, _)| bench::millis(*elapsed)),
+        serialize_ms: bench::millis(serialize),
+        write_ms: bench::millis(write),
+        total_ms: bench::millis(started.elapsed()),
+    })
+}
+
+/// The `--exclude-columns` filter, if any patterns were given
+fn column_filter(args: &OutputArgs) -> Result<Option<ColumnFilter>> {
+    if args.exclude_columns.is_empty() {
+        return Ok(None);
+    }
+    ColumnFilter::new(&args.exclude_columns).map(Some)
+}
+
+/// State shared by every connection of a run
+struct RunState {
+    budget: Arc<QueryBudget>,
+    query_log: Option<Arc<QueryLog>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Snowflake sessions shared by parallel workers; each client opens its own otherwise
+    sessions: Option<Arc<SessionPool>>,
+    /// `--hooks` of a map run
+    sql_hooks: Option<Arc<SqlHooks>>,
+    /// `--shard-columns` of a map run
+    shard_columns: Option<usize>,
+}
 
-        Ok(tables)
+impl RunState {
+    fn new(connection: &ConnectionArgs) -> Result<Self> {
+        Ok(Self {
+            budget: Arc::new(QueryBudget::new(connection.max_queries, connection.max_runtime)),
+            query_log: connection.log_queries.as_deref().map(QueryLog::create).transpose()?.map(Arc::new),
+            rate_limiter: connection.max_qps.map(|qps| Arc::new(RateLimiter::new(qps))),
+            sessions: None,
+            sql_hooks: None,
+            shard_columns: None,
+        })
     }
+}
 
-    async fn list_warehouses(&mut self) -> Result<Vec<WarehouseInfo>> {
-        info!("Listing warehouses...");
-        let rows = self.get_session()?
-            .query("SHOW WAREHOUSES")
-            .await
-            .map_err(|e| SnowflakeMapperError::QueryError(format!("Failed to list warehouses: {}", e)))?;
+/// Optional per-table metadata that costs extra queries
+#[derive(Debug, Default, Clone, Copy)]
+struct Enrichments {
+    access_history: bool,
+    variant_sample: Option<u32>,
+    usage_window_days: Option<u32>,
+}
 
-        let mut warehouses = Vec::new();
-        for row in rows {
-            warehouses.push(WarehouseInfo {
-                name: Self::get_value_from_row(&row, "name")?,
-                size: Self::get_value_from_row(&row, "size")?,
-                state: Self::get_value_from_row(&row, "state")?,
-                type_: Self::get_value_from_row(&row, "type")?,
-            });
+impl Enrichments {
+    fn from_args(args: &MapArgs) -> Self {
+        Self {
+            access_history: args.access_history,
+            variant_sample: args.infer_variant.then_some(args.sample),
+            usage_window_days: args.usage_window_days(),
         }
-        info!("Found {} warehouses", warehouses.len());
-        Ok(warehouses)
     }
 }
 
-async fn write_formatted_output(path: PathBuf, data: &impl Serialize) -> Result<()> {
-    let json = serde_json::to_string_pretty(data)?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
+/// A builder for the Snowflake account in the environment, with the login options of `args`
+fn snowflake_builder(args: &ConnectionArgs) -> Result<SnowflakeMapperBuilder> {
+    let mut builder = SnowflakeMapperBuilder::from_env()?;
+    if let Some(warehouse) = &args.metadata_warehouse {
+        builder = builder.metadata_warehouse(warehouse);
     }
-    std::fs::write(&path, json)
-        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to write to {}: {}", path.display(), e)))?;
-    Ok(())
+    if let Some(timeout) = args.mfa_timeout {
+        builder = builder.mfa_timeout(timeout);
+    }
+    if let Some(passcode) = &args.mfa_passcode {
+        builder = builder.mfa_passcode(passcode);
+    }
+    if let (AuthMethod::Okta, Some(url)) = (args.auth, &args.okta_url) {
+        builder = builder.okta(url);
+    }
+    if let Some(host) = &args.host {
+        builder = builder.host(host);
+    }
+    if let Some(role) = &args.role {
+        builder = builder.role(role);
+    }
+    if let Some(interval) = args.keep_alive {
+        builder = builder.keep_alive(interval);
+    }
+    Ok(builder)
+}
+
+/// Create an unconnected Snowflake client, replaying or recording fixtures when asked
+fn create_snowflake_client(args: &ConnectionArgs, enrichments: Enrichments, state: &RunState) -> Result<Box<dyn SnowflakeOperations>> {
+    if let (Some(dir), false) = (&args.fixture, args.record) {
+        let mut mock = MockSnowflakeOperations::new(dir)
+            .with_access_history(enrichments.access_history)
+            .with_variant_sample(enrichments.variant_sample)
+            .with_usage_window(enrichments.usage_window_days)
+            .with_sql_hooks(state.sql_hooks.clone())
+            .with_shard_columns(state.shard_columns)
+            .with_enforce_readonly(args.enforce_readonly)
+            .with_query_budget(Arc::clone(&state.budget));
+        if let Some(log) = &state.query_log {
+            mock = mock.with_query_log(Arc::clone(log));
+        }
+        if let Some(limiter) = &state.rate_limiter {
+            mock = mock.with_rate_limiter(Arc::clone(limiter));
+        }
+        return Ok(Box::new(mock));
+    }
+
+    let mut builder = snowflake_builder(args)?;
+    if let Some(log) = &state.query_log {
+        builder = builder.query_log(Arc::clone(log));
+    }
+    if let Some(limiter) = &state.rate_limiter {
+        builder = builder.rate_limiter(Arc::clone(limiter));
+    }
+    if let Some(dir) = &args.fixture {
+        builder = builder.record_fixtures(dir);
+    }
+    if let Some(pool) = &state.sessions {
+        builder = builder.session_pool(Arc::clone(pool));
+    }
+    if let Some(rows) = enrichments.variant_sample {
+        builder = builder.infer_variant(rows);
+    }
+    if let Some(days) = enrichments.usage_window_days {
+        builder = builder.include_usage(days);
+    }
+    if let Some(hooks) = &state.sql_hooks {
+        builder = builder.sql_hooks(Arc::clone(hooks));
+    }
+    if let Some(columns) = state.shard_columns {
+        builder = builder.shard_columns(columns);
+    }
+    Ok(Box::new(
+        builder
+            .retry_policy(args.retry_policy())
+            .access_history(enrichments.access_history)
+            .enforce_readonly(args.enforce_readonly)
+            .query_budget(Arc::clone(&state.budget))
+            .build()?,
+    ))
+}
+
+/// Create an unconnected client for a map run, sweeping `--roles` when they're merged
+fn create_map_client(args: &MapArgs, state: &RunState) -> Result<Box<dyn WarehouseMapper>> {
+    if args.roles.is_empty() {
+        return create_client(&args.connection, Enrichments::from_args(args), state);
+    }
+    let mut members: Vec<(String, Box<dyn WarehouseMapper>)> = Vec::new();
+    for role in &args.roles {
+        let mut connection = args.connection.clone();
+        connection.role = Some(role.clone());
+        members.push((role.clone(), create_snowflake_client(&connection, Enrichments::from_args(args), state)?));
+    }
+    Ok(Box::new(RoleSweep::new(members)))
+}
+
+/// Create an unconnected client for the selected backend
+fn create_client(args: &ConnectionArgs, enrichments: Enrichments, state: &RunState) -> Result<Box<dyn WarehouseMapper>> {
+    Ok(match (args.backend, &args.fixture) {
+        (Backend::Snowflake, _) => create_snowflake_client(args, enrichments, state)?,
+        (Backend::Postgres, Some(_)) => {
+            return Err(SnowflakeMapperError::ConnectionError("Fixtures are only supported for the Snowflake backend".to_string()).into());
+        }
+        (Backend::Postgres, None) => {
+            let mut postgres = PostgresMapper::new(
+                &std::env::var("POSTGRES_URL")
+                    .map_err(|_| SnowflakeMapperError::MissingEnvVar("POSTGRES_URL".to_string()))?,
+            )?
+            .with_enforce_readonly(args.enforce_readonly);
+            if let Some(log) = &state.query_log {
+                postgres = postgres.with_query_log(Arc::clone(log));
+            }
+            if let Some(limiter) = &state.rate_limiter {
+                postgres = postgres.with_rate_limiter(Arc::clone(limiter));
+            }
+            Box::new(postgres)
+        }
+    })
 }
 
 #[tokio::main]
@@ -378,31 +915,166 @@ async fn main() -> Result<()> {
     // Parse command line arguments
     let args = Args::parse();
 
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+    // Initialize logging; keep stdout clean for data when streaming the catalog
+    let level = if args.quiet { tracing::Level::WARN } else { tracing::Level::INFO };
+    let subscriber = tracing_subscriber::fmt().with_max_level(level);
+    if args.writes_to_stdout() {
+        subscriber.with_writer(std::io::stderr).init();
+    } else {
+        subscriber.init();
+    }
 
+    let quiet = args.quiet;
+    match args.into_command() {
+        Command::Map(map) => run_map(&map, quiet).await,
+        Command::List(list) => run_list(&list).await,
+        Command::Account(account) => run_account(&account).await,
+        Command::Users(users) => run_users(&users).await,
+        Command::Shares(shares) => run_shares(&shares).await,
+        Command::Replication(replication) => run_replication(&replication).await,
+        Command::AuditVisibility(audit) => run_audit_visibility(&audit).await,
+        Command::Bench(bench) => run_bench(&bench).await,
+        Command::Diff(diff) => run_diff(&diff).await,
+        Command::Changelog(changelog) => run_changelog(&changelog).await,
+        Command::Export(export) => run_export(&export).await,
+        Command::Merge(merge) => run_merge(&merge).await,
+        Command::Upgrade(upgrade) => run_upgrade(&upgrade).await,
+        Command::Docs(docs) => run_docs(&docs).await,
+        Command::Migrations(migrations) => run_migrations(&migrations).await,
+        Command::Mock(mock) => run_mock(&mock).await,
+        Command::Scaffold(scaffold) => run_scaffold(&scaffold).await,
+        Command::Stale(stale) => run_stale(&stale).await,
+        Command::Query(query) => run_query(&query).await,
+        Command::Impact(impact) => run_impact(&impact).await,
+        Command::Stats(stats) => run_stats(&stats).await,
+        Command::Duplicates(duplicates) => run_duplicates(&duplicates).await,
+        Command::Schema(schema) => run_schema(&schema),
+        Command::Completions(completions) => run_completions(&completions),
+        Command::Man(man) => run_man(&man),
+        Command::CompleteDatabases(complete) => run_complete_databases(&complete),
+    }
+}
+
+async fn run_map(args: &MapArgs, quiet: bool) -> Result<()> {
+    if !args.atomic_snapshot {
+        return map_roles(args, quiet).await;
+    }
+    if args.writes_to_stdout() {
+        return Err(SnowflakeMapperError::OutputError("--atomic-snapshot needs an output directory".to_string()).into());
+    }
+    // Consumers keep reading the previous snapshot until the new one is complete
+    let target = args.output.output_dir.clone();
+    let mut staged = args.clone();
+    staged.output.output_dir = output::staging_directory(&target);
+    let staging = staged.output.output_dir.clone();
+    // A run that maps nothing still replaces the snapshot with an empty one
+    std::fs::create_dir_all(&staging)
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", staging.display(), e)))?;
+    let result = match map_roles(&staged, quiet).await {
+        Ok(()) => staged_failures(&staging),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(failed) if !failed.is_empty() && !args.replace_partial_snapshot => {
+            let _ = std::fs::remove_dir_all(&staging);
+            warn!("Kept the previous snapshot in {}", target.display());
+            Err(SnowflakeMapperError::OutputError(format!(
+                "{} databases failed ({}), so the snapshot is incomplete; pass --replace-partial-snapshot to replace the previous one anyway",
+                failed.len(),
+                failed.join(", ")
+            ))
+            .into())
+        }
+        Ok(_) => {
+            output::replace_directory(&staging, &target)?;
+            info!("Replaced {} with the new snapshot", target.display());
+            Ok(())
+        }
+        Err(e) => {
+            if staging.exists() {
+                let _ = std::fs::remove_dir_all(&staging);
+            }
+            warn!("Kept the previous snapshot in {}", target.display());
+            Err(e)
+        }
+    }
+}
+
+/// Databases listed in the failure manifests of a staged snapshot, per-role ones included
+fn staged_failures(staging: &Path) -> Result<Vec<String>> {
+    let mut failed = Vec::new();
+    for path in catalog::snapshot_files(staging)?.into_iter().filter(|path| path.file_name().is_some_and(|name| name == failures::FAILURES_FILE)) {
+        failed.extend(FailureManifest::load(&path)?.databases());
+    }
+    Ok(failed)
+}
+
+/// Map under each of `--roles` into a subdirectory per role, unless they're merged
+async fn map_roles(args: &MapArgs, quiet: bool) -> Result<()> {
+    if args.roles.is_empty() {
+        return map_databases(args, quiet).await;
+    }
+    if args.connection.backend != Backend::Snowflake {
+        return Err(SnowflakeMapperError::ConnectionError("--roles is only supported for the Snowflake backend".to_string()).into());
+    }
+    if args.merge_roles {
+        return map_databases(args, quiet).await;
+    }
+    if args.writes_to_stdout() {
+        return Err(SnowflakeMapperError::OutputError("--roles needs an output directory unless --merge-roles is given".to_string()).into());
+    }
+    for role in &args.roles {
+        let mut single = args.clone();
+        single.roles.clear();
+        single.connection.role = Some(role.clone());
+        single.output.output_dir = args.output.output_dir.join(role);
+        info!("Mapping as role {}", role);
+        map_databases(&single, quiet).await?;
+    }
+    Ok(())
+}
+
+async fn map_databases(args: &MapArgs, quiet: bool) -> Result<()> {
     // Load environment variables
     dotenv::dotenv().ok();
 
-    // Create Snowflake configuration from environment variables
-    let config = SnowflakeConfig {
-        account: std::env::var("SNOWFLAKE_ACCOUNT")
-            .map_err(|_| SnowflakeMapperError::MissingEnvVar("SNOWFLAKE_ACCOUNT".to_string()))?,
-        username: std::env::var("SNOWFLAKE_USERNAME")
-            .map_err(|_| SnowflakeMapperError::MissingEnvVar("SNOWFLAKE_USERNAME".to_string()))?,
-        password: std::env::var("SNOWFLAKE_PASSWORD")
-            .map_err(|_| SnowflakeMapperError::MissingEnvVar("SNOWFLAKE_PASSWORD".to_string()))?,
-        warehouse: std::env::var("SNOWFLAKE_WAREHOUSE")
-            .map_err(|_| SnowflakeMapperError::MissingEnvVar("SNOWFLAKE_WAREHOUSE".to_string()))?,
-        database: std::env::var("SNOWFLAKE_DATABASE").ok(),
-        role: Some(std::env::var("SNOWFLAKE_ROLE").unwrap_or_else(|_| "SALES".to_string())),
+    // Resolve templates and plugins before connecting so a bad format fails fast
+    let mut output = OutputWriter::new(&args.output)?;
+    let exclude = column_filter(&args.output)?;
+
+    let progress: Arc<dyn ProgressListener> = if args.shows_progress(quiet) {
+        Arc::new(BarProgress::new())
+    } else {
+        Arc::new(NoProgress)
     };
+    if args.dry_run && args.connection.backend != Backend::Snowflake {
+        return Err(SnowflakeMapperError::ConnectionError("--dry-run is only supported for the Snowflake backend".to_string()).into());
+    }
 
-    let mut client = SnowflakeMapper::new(config, args.clone());
-    client.connect().await?;
+    // Shared by every connection so the limits and the query log cover the whole run
+    let mut state = RunState::new(&args.connection)?;
+    state.shard_columns = args.shard_columns.map(|columns| columns as usize);
+    state.sql_hooks = args.hooks.as_deref().map(SqlHooks::from_file).transpose()?.filter(|hooks| !hooks.is_empty()).map(Arc::new);
+    // Parallel workers share one pool of sessions, opened as the workers need them
+    let replaying = args.connection.fixture.is_some() && !args.connection.record;
+    // A sweep connects as several roles, which can't share sessions
+    if args.jobs > 1 && args.connection.backend == Backend::Snowflake && !replaying && args.roles.is_empty() {
+        state.sessions = Some(snowflake_builder(&args.connection)?.build_session_pool(args.jobs as usize)?);
+    }
+    let budget = Arc::clone(&state.budget);
+    let provenance = RunProvenance::start(&args.connection, Arc::clone(&budget)).with_roles(&args.roles);
+    let mut client = create_map_client(args, &state)?;
+    // A dry run only connects when it has to list databases
+    if !args.dry_run || (args.databases.is_none() && args.retry_failures.is_none()) {
+        client.connect().await?;
+    }
 
     // Get databases to process
-    let databases = match &args.databases {
+    let requested = match &args.retry_failures {
+        Some(path) => Some(FailureManifest::load(path)?.databases()),
+        None => args.databases.clone(),
+    };
+    let databases: Vec<DatabaseInfo> = match &requested {
         Some(dbs) => dbs.iter().map(|name| DatabaseInfo {
             name: name.clone(),
             created_on: String::new(),
@@ -411,44 +1083,240 @@ async fn main() -> Result<()> {
         None => client.get_all_databases().await?,
     };
 
-    // Create progress bar
-    let progress = ProgressBar::new(databases.len() as u64);
-    progress.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-            .unwrap()
-            .progress_chars("##-"),
-    );
+    let total = databases.len();
+    let jobs = (args.jobs as usize).min(total.max(1));
 
-    // Process each database
-    for db in databases {
-        progress.set_message(format!("Processing database: {}", db.name));
-        
-        match client.get_tables_for_database(&db.name).await {
-            Ok(tables) => {
-                let output_path = args.output_dir
-                    .join(&db.name)
-                    .with_extension("json");
-                
-                if let Err(e) = write_formatted_output(output_path.clone(), &tables).await {
-                    error!("Failed to write output for database {}: {}", db.name, e);
-                    if !args.skip_failed_tables {
-                        return Err(e);
+    if args.dry_run {
+        let mut estimate = CostEstimate::snowflake(total, jobs, requested.is_none(), args.access_history);
+        if args.infer_variant {
+            estimate = estimate.with_variant_sampling();
+        }
+        if args.include_usage {
+            estimate = estimate.with_usage();
+        }
+        if args.shard_columns.is_some() {
+            estimate = estimate.with_sharding();
+        }
+        if let Some(hooks) = &state.sql_hooks {
+            estimate = estimate.with_sql_hooks(hooks.database.len(), hooks.table.len());
+        }
+        output::write_stdout(&estimate.to_string())?;
+        if args.connection.max_queries.is_some_and(|max| estimate.total_queries() > max) {
+            warn!("Estimated {} queries exceed --max-queries; the run would abort", estimate.total_queries());
+        }
+        return Ok(());
+    }
+
+    // Warehouses are account-wide, so the inventory is captured once per run
+    let warehouses = if args.output.writes_to_stdout() {
+        Vec::new()
+    } else {
+        client.get_warehouses().await.unwrap_or_else(|e| {
+            warn!("Failed to capture the warehouse inventory: {}", e);
+            Vec::new()
+        })
+    };
+
+    progress.on_event(&ProgressEvent::Started { total });
+
+    // Each worker maps databases off a shared queue over its own connection
+    let mut clients = vec![client];
+    for _ in 1..jobs {
+        let mut client = create_map_client(args, &state)?;
+        client.connect().await?;
+        clients.push(client);
+    }
+    let queue = Arc::new(Mutex::new(databases.into_iter().enumerate().collect::<VecDeque<_>>()));
+    let (results_tx, mut results) = mpsc::unbounded_channel();
+    for mut client in clients {
+        let queue = Arc::clone(&queue);
+        let results_tx = results_tx.clone();
+        let progress = Arc::clone(&progress);
+        let pause = args.pause_between_databases;
+        let budget = Arc::clone(&budget);
+        let policy = args.connection.retry_policy();
+        let database_timeout = args.database_timeout;
+        client.set_progress_listener(Arc::clone(&progress));
+        tokio::spawn(async move {
+            let mut first = true;
+            loop {
+                let Some((index, db)) = queue.lock().unwrap().pop_front() else { break };
+                if let Some(pause) = pause.filter(|_| !first) {
+                    tokio::time::sleep(pause).await;
+                }
+                first = false;
+                progress.on_event(&ProgressEvent::DatabaseStarted { database: db.name.clone(), index, total });
+                // Retries of chunked queries inside the client count here too, so the database
+                // gets `max_retries` in all rather than per level
+                let mut retries = 0;
+                let fetch = async {
+                    loop {
+                        client.set_retries_spent(retries);
+                        let result = client.get_tables_for_database(&db.name).await;
+                        retries = retries.max(client.retries_spent());
+                        match result {
+                            Err(e) if policy.should_retry(&e, retries) && !budget.is_exhausted() => {
+                                retries += 1;
+                                let class = retry::classify(&e);
+                                warn!("Mapping database {} failed ({:?}), retry {} of {}: {}", db.name, class, retries, policy.max_retries, e);
+                                tokio::time::sleep(policy.delay_before(retries)).await;
+                            }
+                            result => break result,
+                        }
+                    }
+                };
+                let tables = match database_timeout {
+                    // Dropping the fetch abandons its in-flight query, so the worker can go on
+                    // to the next database
+                    Some(limit) => tokio::time::timeout(limit, fetch).await.unwrap_or_else(|_| {
+                        let limit = humantime::format_duration(limit);
+                        Err(SnowflakeMapperError::DatabaseTimeout(format!("{} took longer than --database-timeout {}", db.name, limit)).into())
+                    }),
+                    None => fetch.await,
+                };
+                if results_tx.send((db, tables, retries + 1)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(results_tx);
+
+    let registry = match args.push {
+        Some(PushTarget::SchemaRegistry) => {
+            let url = match &args.schema_registry_url {
+                Some(url) => url.clone(),
+                None => std::env::var("SCHEMA_REGISTRY_URL")
+                    .map_err(|_| SnowflakeMapperError::MissingEnvVar("SCHEMA_REGISTRY_URL".to_string()))?,
+            };
+            let credentials = std::env::var("SCHEMA_REGISTRY_USERNAME")
+                .ok()
+                .zip(std::env::var("SCHEMA_REGISTRY_PASSWORD").ok());
+            Some(SchemaRegistryClient::new(&url, credentials))
+        }
+        _ => None,
+    };
+    let atlas = match args.push {
+        Some(PushTarget::Atlas) => {
+            let url = match &args.atlas_url {
+                Some(url) => url.clone(),
+                None => std::env::var("ATLAS_URL").map_err(|_| SnowflakeMapperError::MissingEnvVar("ATLAS_URL".to_string()))?,
+            };
+            let credentials = std::env::var("ATLAS_USERNAME").ok().zip(std::env::var("ATLAS_PASSWORD").ok());
+            let client = AtlasClient::new(&url, credentials, args.output.atlas_types, &args.output.atlas_cluster);
+            client.register_types().await?;
+            Some(client)
+        }
+        _ => None,
+    };
+
+    // Process each database; an exhausted budget stops the run but keeps what was mapped so far
+    let mut mapped_tables = 0;
+    let mut retries = 0;
+    let mut failures = FailureManifest::default();
+    let mut aborted = None;
+    loop {
+        let next = match budget.deadline() {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), results.recv()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    aborted = Some(SnowflakeMapperError::BudgetExceeded("--max-runtime reached".to_string()).into());
+                    break;
+                }
+            },
+            None => results.recv().await,
+        };
+        let Some((db, tables, attempts)) = next else { break };
+        retries += attempts as usize - 1;
+        match tables {
+            Ok(mut tables) => {
+                tables.retain(|table| args.output.keeps_table(table));
+                // Excluded columns must not reach the registry either, so filter before publishing
+                if let Some(exclude) = &exclude {
+                    tables = exclude.apply(tables);
+                }
+                let table_count = tables.len();
+                let stamp = provenance.finish();
+                for table in &mut tables {
+                    table.provenance = Some(stamp.clone());
+                }
+                let published = match (&registry, &atlas) {
+                    (Some(registry), _) => registry.publish_tables(&args.subject_prefix, &tables).await,
+                    (_, Some(atlas)) => atlas.publish_tables(&db.name, &tables).await,
+                    (None, None) => Ok(()),
+                };
+                let written = match published {
+                    Ok(()) => output.write_database(&db.name, tables).await.map_err(|e| (FailureStage::Write, e)),
+                    Err(e) => Err((FailureStage::Publish, e)),
+                };
+
+                match written {
+                    Ok(()) => {
+                        mapped_tables += table_count;
+                        progress.on_event(&ProgressEvent::DatabaseFinished { database: db.name.clone(), tables: table_count });
+                        info!("Processed database: {}", db.name);
+                    }
+                    Err((stage, e)) => {
+                        error!("Failed to write output for database {}: {}", db.name, e);
+                        failures.failures.push(Failure { database: db.name.clone(), stage, error: e.to_string(), attempts });
+                        progress.on_event(&ProgressEvent::DatabaseFailed { database: db.name.clone(), error: e.to_string() });
+                        if !args.skip_failed_tables {
+                            return Err(e);
+                        }
                     }
                 }
-                info!("Processed database: {}", db.name);
             }
             Err(e) => {
-                error!("Failed to process database {}: {}", db.name, e);
+                error!("Failed to process database {} after {} attempts: {}", db.name, attempts, e);
+                failures.failures.push(Failure {
+                    database: db.name.clone(),
+                    stage: FailureStage::Fetch,
+                    error: e.to_string(),
+                    attempts,
+                });
+                progress.on_event(&ProgressEvent::DatabaseFailed { database: db.name.clone(), error: e.to_string() });
+                if budget.is_exhausted() {
+                    aborted = Some(e);
+                    break;
+                }
                 if !args.skip_failed_tables {
                     return Err(e);
                 }
             }
         }
-        
-        progress.inc(1);
     }
 
-    progress.finish_with_message("Done!");
+    output.finish().await?;
+    if !warehouses.is_empty() {
+        output::write_formatted_output(args.output.output_dir.join(catalog::WAREHOUSES_FILE), &warehouses).await?;
+    }
+
+    // Only runs that skip failures get this far with failures; a clean run clears an old manifest
+    let manifest = failures::manifest_path(&args.output.output_dir);
+    if !failures.failures.is_empty() {
+        output::write_formatted_output(manifest.clone(), &failures).await?;
+        warn!("{} databases failed; see {}", failures.failures.len(), manifest.display());
+    } else if manifest.exists() && !args.output.writes_to_stdout() {
+        std::fs::remove_file(&manifest)?;
+    }
+
+    if let Some(e) = aborted {
+        error!("Stopped after {} queries; output contains the databases mapped so far", budget.used());
+        return Err(e);
+    }
+
+    progress.on_event(&ProgressEvent::Finished {
+        databases: total,
+        tables: mapped_tables,
+        failed: failures.failures.len(),
+        retries,
+    });
+    info!(
+        "Mapped {} tables from {} databases ({} failed, {} retries)",
+        mapped_tables,
+        total,
+        failures.failures.len(),
+        retries
+    );
     Ok(())
 }
@@ -0,0 +1,129 @@
+//! Combining snapshots written by separate runs into one catalog.
+//!
+//! Runs sharded across CI jobs, or against different accounts, each write part of the
+//! catalog. Merging keeps one copy of every table and warehouse; an object found in several
+//! snapshots is a conflict when its structure differs between them.
+
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::diff;
+use crate::{TableInfo, WarehouseInfo};
+
+/// What to do when snapshots disagree about an object
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnConflict {
+    /// Fail and list every conflict
+    #[default]
+    Error,
+    /// Keep the object from the earliest snapshot that has it
+    First,
+    /// Keep the object from the latest snapshot that has it
+    Last,
+}
+
+/// An object whose structure differs between snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// `DATABASE.SCHEMA.TABLE`, or `warehouse NAME`
+    pub object: String,
+    /// Labels of the snapshots that disagree, in merge order
+    pub sources: Vec<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} differs between {}", self.object, self.sources.join(", "))
+    }
+}
+
+/// One snapshot to merge, labelled for conflict reports
+pub struct MergeInput {
+    pub label: String,
+    pub tables: Vec<TableInfo>,
+    pub warehouses: Option<Vec<WarehouseInfo>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub tables: Vec<TableInfo>,
+    /// `None` when no snapshot had a warehouse inventory
+    pub warehouses: Option<Vec<WarehouseInfo>>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merge snapshots in order. Copies of an object with the same structure are merged
+/// silently, keeping the later one (its timestamps and usage are fresher); copies that
+/// differ are recorded as conflicts and resolved by `on_conflict`, which for
+/// [`OnConflict::Error`] keeps the first.
+pub fn merge(inputs: Vec<MergeInput>, on_conflict: OnConflict) -> Merged {
+    let mut merged = Merged::default();
+    let mut tables: Vec<(TableInfo, String)> = Vec::new();
+    let mut table_index: HashMap<(String, String, String), usize> = HashMap::new();
+    let mut warehouses: Vec<(WarehouseInfo, String)> = Vec::new();
+    let mut has_inventory = false;
+
+    for input in inputs {
+        for table in input.tables {
+            let key = (table.database_name.clone(), table.schema_name.clone(), table.table_name.clone());
+            let Some(&index) = table_index.get(&key) else {
+                table_index.insert(key, tables.len());
+                tables.push((table, input.label.clone()));
+                continue;
+            };
+            let (existing, source) = &mut tables[index];
+            let same = diff::diff_catalogs(std::slice::from_ref(existing), std::slice::from_ref(&table)).is_empty();
+            if !same {
+                record(
+                    &mut merged.conflicts,
+                    table.qualified_name(),
+                    source,
+                    &input.label,
+                );
+            }
+            if same || on_conflict == OnConflict::Last {
+                *existing = table;
+                *source = input.label.clone();
+            }
+        }
+
+        let Some(inventory) = input.warehouses else { continue };
+        has_inventory = true;
+        for warehouse in inventory {
+            match warehouses.iter_mut().find(|(existing, _)| existing.name == warehouse.name) {
+                None => warehouses.push((warehouse, input.label.clone())),
+                Some((existing, source)) => {
+                    let same = !existing.config_differs(&warehouse);
+                    if !same {
+                        record(&mut merged.conflicts, format!("warehouse {}", warehouse.name), source, &input.label);
+                    }
+                    if same || on_conflict == OnConflict::Last {
+                        *existing = warehouse;
+                        *source = input.label.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    merged.tables = tables.into_iter().map(|(table, _)| table).collect();
+    merged.warehouses = has_inventory.then(|| warehouses.into_iter().map(|(warehouse, _)| warehouse).collect());
+    merged
+}
+
+/// Add `source` and `label` to the conflict for `object`, creating it on first sight
+fn record(conflicts: &mut Vec<Conflict>, object: String, source: &str, label: &str) {
+    let conflict = match conflicts.iter().position(|c| c.object == object) {
+        Some(index) => &mut conflicts[index],
+        None => {
+            conflicts.push(Conflict { object, sources: Vec::new() });
+            conflicts.last_mut().expect("just pushed")
+        }
+    };
+    for label in [source, label] {
+        if !conflict.sources.iter().any(|s| s == label) {
+            conflict.sources.push(label.to_string());
+        }
+    }
+}
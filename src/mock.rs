@@ -0,0 +1,315 @@
+//! Empty local databases reproducing a catalog, for running transformation SQL without
+//! Snowflake credentials.
+//!
+//! Each Snowflake schema becomes a schema of a DuckDB database, created by running a
+//! generated script through the DuckDB CLI, or a SQLite database file of its own to
+//! `ATTACH` under the schema's name, so `SCHEMA.TABLE` references resolve unchanged. The CSV
+//! target writes a file per table instead. Views become empty tables, since their
+//! definitions aren't captured. Only `NOT NULL` is carried over: Snowflake doesn't enforce
+//! primary and foreign keys, so neither does the mock, and column defaults are Snowflake
+//! expressions. Tables can be filled with fake rows, see [`fake`](crate::fake).
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::fake::{Fake, Faker};
+use crate::formats::quote_identifier;
+use crate::output::{self, csv_field};
+use crate::types::SnowflakeType;
+use crate::{ColumnInfo, SnowflakeMapperError, TableInfo};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockTarget {
+    /// A DuckDB database file, created by the DuckDB CLI
+    Duckdb,
+    /// The DDL script `--target duckdb` runs, for the DuckDB CLI: `duckdb SALES.duckdb < mock.sql`
+    DuckdbSql,
+    /// One SQLite database file per schema
+    Sqlite,
+    /// One CSV file per table, named `SCHEMA.TABLE.csv`, with a header row
+    Csv,
+}
+
+/// Tables of one database, grouped by schema in name order
+fn by_schema(tables: &[TableInfo]) -> BTreeMap<&str, Vec<&TableInfo>> {
+    let mut schemas: BTreeMap<&str, Vec<&TableInfo>> = BTreeMap::new();
+    for table in tables {
+        schemas.entry(table.schema_name.as_str()).or_default().push(table);
+    }
+    schemas
+}
+
+/// Rows per `INSERT` statement in DuckDB scripts
+const INSERT_BATCH: usize = 1000;
+
+/// DuckDB script creating every schema and table of `tables`, filled with `faker` rows if given
+pub fn render_duckdb(tables: &[TableInfo], faker: Option<&Faker>) -> String {
+    let mut out = String::from("-- Generated by snowflake-mapper\n");
+    for (schema, tables) in by_schema(tables) {
+        out.push_str(&format!("\nCREATE SCHEMA IF NOT EXISTS {};\n", quote_identifier(schema)));
+        for table in tables {
+            let name = format!("{}.{}", quote_identifier(schema), quote_identifier(&table.table_name));
+            out.push_str(&format!("{};\n", create_table(&name, table, Dialect::Duckdb)));
+            let Some(faker) = faker.filter(|_| !table.columns.is_empty()) else { continue };
+            for batch in faker.rows(table).chunks(INSERT_BATCH) {
+                let rows: Vec<String> = batch
+                    .iter()
+                    .map(|row| format!("({})", row.iter().map(duckdb_literal).collect::<Vec<_>>().join(", ")))
+                    .collect();
+                out.push_str(&format!("INSERT INTO {} VALUES\n  {};\n", name, rows.join(",\n  ")));
+            }
+        }
+    }
+    out
+}
+
+/// Create the DuckDB database `path` by running [`render_duckdb`] through the DuckDB CLI
+/// `cli`, since no DuckDB library is linked in. Like SQLite mocks, an existing file is left
+/// alone; a failed script leaves no file behind.
+pub async fn create_duckdb(path: &Path, cli: &Path, tables: &[TableInfo], faker: Option<&Faker>) -> Result<()> {
+    if path.exists() {
+        return Err(SnowflakeMapperError::OutputError(format!("{} already exists; remove it to recreate the mock", path.display())).into());
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", dir.display(), e)))?;
+    }
+    let error = |message: String| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", path.display(), message));
+    let script = render_duckdb(tables, faker);
+
+    // Built under a temporary name, so an interrupted run doesn't look like a finished mock
+    let partial = path.with_extension("duckdb.partial");
+    let remove_partial = || {
+        let _ = std::fs::remove_file(&partial);
+        let _ = std::fs::remove_file(partial.with_extension("partial.wal"));
+    };
+    remove_partial();
+    let mut child = Command::new(cli)
+        .arg("-bail")
+        .arg(&partial)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => error(format!(
+                "the DuckDB CLI '{}' isn't installed; pass its path with --duckdb-cli, or write the script with --target duckdb-sql",
+                cli.display()
+            )),
+            _ => error(format!("failed to start '{}': {}", cli.display(), e)),
+        })?;
+
+    // Feed stdin from a separate task so the CLI can't deadlock on a full stderr pipe
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let feeder = tokio::spawn(async move {
+        stdin.write_all(script.as_bytes()).await?;
+        stdin.shutdown().await
+    });
+    let output = child.wait_with_output().await.map_err(|e| error(format!("'{}' failed while running: {}", cli.display(), e)))?;
+    let fed = feeder.await;
+    if !output.status.success() || !matches!(fed, Ok(Ok(()))) {
+        remove_partial();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(error(format!("'{}' exited with {}: {}", cli.display(), output.status, stderr.trim())).into());
+    }
+    std::fs::rename(&partial, path).map_err(|e| error(e.to_string()))?;
+    Ok(())
+}
+
+fn duckdb_literal(value: &Fake) -> String {
+    match value {
+        Fake::Null => "NULL".to_string(),
+        Fake::Integer(value) => value.to_string(),
+        Fake::Decimal(value) => value.clone(),
+        Fake::Real(value) => format!("{:?}", value),
+        Fake::Boolean(value) => value.to_string().to_uppercase(),
+        Fake::Text(value) => format!("'{}'", value.replace('\'', "''")),
+        Fake::Blob(bytes) => format!("'{}'::BLOB", bytes.iter().map(|byte| format!("\\x{:02X}", byte)).collect::<String>()),
+    }
+}
+
+/// Create `<dir>/<SCHEMA>.sqlite` for every schema of `tables`, filled with `faker` rows if
+/// given; returns the files written. Existing files are left alone, so a mock holding test
+/// data is never overwritten.
+pub fn create_sqlite(dir: &Path, tables: &[TableInfo], faker: Option<&Faker>) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let schemas = by_schema(tables);
+    let path = |schema: &str| dir.join(format!("{}.sqlite", schema));
+    if let Some(existing) = schemas.keys().map(|schema| path(schema)).find(|path| path.exists()) {
+        return Err(SnowflakeMapperError::OutputError(format!("{} already exists; remove it to recreate the mock", existing.display())).into());
+    }
+    let mut files = Vec::new();
+    for (schema, tables) in schemas {
+        let path = path(schema);
+        let statements: Vec<String> = tables
+            .iter()
+            .map(|table| format!("{};", create_table(&quote_identifier(&table.table_name), table, Dialect::Sqlite)))
+            .collect();
+        let sqlite_error = |e: rusqlite::Error| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", path.display(), e));
+        let mut connection = rusqlite::Connection::open(&path).map_err(sqlite_error)?;
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        transaction.execute_batch(&statements.join("\n")).map_err(sqlite_error)?;
+        if let Some(faker) = faker {
+            for table in tables.iter().filter(|table| !table.columns.is_empty()) {
+                let placeholders = vec!["?"; table.columns.len()].join(", ");
+                let mut insert = transaction
+                    .prepare(&format!("INSERT INTO {} VALUES ({})", quote_identifier(&table.table_name), placeholders))
+                    .map_err(sqlite_error)?;
+                for row in faker.rows(table) {
+                    insert.execute(rusqlite::params_from_iter(row.into_iter().map(sqlite_value))).map_err(sqlite_error)?;
+                }
+            }
+        }
+        transaction.commit().map_err(sqlite_error)?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// Write `<dir>/<SCHEMA>.<TABLE>.csv` for every table of `tables`, holding a header and the
+/// `faker` rows if given; returns the files written. Like SQLite mocks, existing files
+/// are left alone.
+pub fn write_csv(dir: &Path, tables: &[TableInfo], faker: Option<&Faker>) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let path = |table: &TableInfo| dir.join(format!("{}.{}.csv", table.schema_name, table.table_name));
+    if let Some(existing) = tables.iter().map(path).find(|path| path.exists()) {
+        return Err(SnowflakeMapperError::OutputError(format!("{} already exists; remove it to recreate the mock", existing.display())).into());
+    }
+    let mut files = Vec::new();
+    for table in tables {
+        let header: Vec<String> = table.columns.iter().map(|column| csv_field(&column.name)).collect();
+        let mut out = format!("{}\n", header.join(","));
+        if let Some(faker) = faker.filter(|_| !table.columns.is_empty()) {
+            for row in faker.rows(table) {
+                let fields: Vec<String> = row.iter().map(csv_value).collect();
+                out.push_str(&fields.join(","));
+                out.push('\n');
+            }
+        }
+        let path = path(table);
+        output::write_atomically(&path, |file| file.write_all(out.as_bytes()))?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// Nulls are empty fields, binary values hex
+fn csv_value(value: &Fake) -> String {
+    match value {
+        Fake::Null => String::new(),
+        Fake::Integer(value) => value.to_string(),
+        Fake::Decimal(value) => value.clone(),
+        Fake::Real(value) => format!("{:?}", value),
+        Fake::Boolean(value) => value.to_string(),
+        Fake::Text(value) => csv_field(value),
+        Fake::Blob(bytes) => bytes.iter().map(|byte| format!("{:02X}", byte)).collect(),
+    }
+}
+
+fn sqlite_value(value: Fake) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        Fake::Null => Value::Null,
+        Fake::Integer(value) => Value::Integer(value),
+        Fake::Boolean(value) => Value::Integer(i64::from(value)),
+        Fake::Real(value) => Value::Real(value),
+        // NUMERIC affinity stores it as a number
+        Fake::Decimal(value) | Fake::Text(value) => Value::Text(value),
+        Fake::Blob(bytes) => Value::Blob(bytes),
+    }
+}
+
+/// SQL dialects tables are created in
+#[derive(Debug, Clone, Copy)]
+enum Dialect {
+    Duckdb,
+    Sqlite,
+}
+
+fn create_table(name: &str, table: &TableInfo, dialect: Dialect) -> String {
+    let columns: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| {
+            let not_null = if column.is_nullable { "" } else { " NOT NULL" };
+            format!("  {} {}{}", quote_identifier(&column.name), local_type(column, dialect), not_null)
+        })
+        .collect();
+    format!("CREATE TABLE {} (\n{}\n)", name, columns.join(",\n"))
+}
+
+/// What a column holds, independent of the local database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnKind {
+    /// Whole numbers of at most `digits` digits
+    Integer { digits: u32 },
+    Decimal { precision: u32, scale: u32 },
+    Double,
+    Boolean,
+    Binary,
+    Date,
+    Time,
+    Timestamp,
+    TimestampTz,
+    Json,
+    /// Text of at most `length` characters
+    Text { length: Option<usize> },
+}
+
+/// Classify a Snowflake (or PostgreSQL) column; anything unknown is text
+pub(crate) fn column_kind(column: &ColumnInfo) -> ColumnKind {
+    match column.snowflake_type() {
+        // Snowflake's integers are NUMBER(38,0)
+        SnowflakeType::Number { precision, scale: 0 } => ColumnKind::Integer { digits: precision },
+        SnowflakeType::Number { precision, scale } => ColumnKind::Decimal { precision, scale },
+        SnowflakeType::Float => ColumnKind::Double,
+        SnowflakeType::Boolean => ColumnKind::Boolean,
+        SnowflakeType::Binary { .. } => ColumnKind::Binary,
+        SnowflakeType::Date => ColumnKind::Date,
+        SnowflakeType::Time { .. } | SnowflakeType::TimeTz { .. } => ColumnKind::Time,
+        SnowflakeType::Timestamp { .. } | SnowflakeType::TimestampNtz { .. } => ColumnKind::Timestamp,
+        SnowflakeType::TimestampLtz { .. } | SnowflakeType::TimestampTz { .. } => ColumnKind::TimestampTz,
+        SnowflakeType::Variant | SnowflakeType::Object | SnowflakeType::Array => ColumnKind::Json,
+        SnowflakeType::Varchar { length } => ColumnKind::Text { length: length.and_then(|l| usize::try_from(l).ok()) },
+        _ => ColumnKind::Text { length: column.character_maximum_length.and_then(|l| usize::try_from(l).ok()) },
+    }
+}
+
+/// The closest local type to a column's type
+fn local_type(column: &ColumnInfo, dialect: Dialect) -> String {
+    let kind = column_kind(column);
+    match dialect {
+        Dialect::Duckdb => match kind {
+            ColumnKind::Integer { digits } if digits <= 18 => "BIGINT".to_string(),
+            ColumnKind::Integer { .. } => "HUGEINT".to_string(),
+            ColumnKind::Decimal { precision, scale } => format!("DECIMAL({},{})", precision, scale),
+            ColumnKind::Double => "DOUBLE".to_string(),
+            ColumnKind::Boolean => "BOOLEAN".to_string(),
+            ColumnKind::Binary => "BLOB".to_string(),
+            ColumnKind::Date => "DATE".to_string(),
+            ColumnKind::Time => "TIME".to_string(),
+            ColumnKind::Timestamp => "TIMESTAMP".to_string(),
+            ColumnKind::TimestampTz => "TIMESTAMPTZ".to_string(),
+            ColumnKind::Json => "JSON".to_string(),
+            ColumnKind::Text { .. } => "VARCHAR".to_string(),
+        },
+        // SQLite only has storage classes; dates and JSON are stored as text
+        Dialect::Sqlite => match kind {
+            ColumnKind::Integer { .. } | ColumnKind::Boolean => "INTEGER",
+            ColumnKind::Decimal { .. } => "NUMERIC",
+            ColumnKind::Double => "REAL",
+            ColumnKind::Binary => "BLOB",
+            _ => "TEXT",
+        }
+        .to_string(),
+    }
+}
+
@@ -0,0 +1,188 @@
+//! Naming-convention rules applied to schema, table and column names before output.
+//!
+//! Rules come from a JSON file with an optional section per kind of name:
+//!
+//! ```json
+//! {
+//!   "tables": {
+//!     "strip_prefixes": ["TBL_", "T_"],
+//!     "rewrites": [{ "pattern": "^DIM_(.*)$", "replacement": "${1}_DIMENSION" }],
+//!     "case": "pascal"
+//!   },
+//!   "columns": { "case": "snake" }
+//! }
+//! ```
+//!
+//! Within a section, the first matching prefix is stripped, then every rewrite is applied in
+//! order, then the case transform. Foreign keys and primary keys are renamed with the same
+//! rules, so references still resolve.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::{ColumnInfo, ForeignKeyInfo, SnowflakeMapperError, TableInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Case {
+    /// `order_items`
+    Snake,
+    /// `orderItems`
+    Camel,
+    /// `OrderItems`
+    Pascal,
+    /// `ORDER_ITEMS`
+    ScreamingSnake,
+    /// `orderitems`
+    Lower,
+    /// `ORDERITEMS`
+    Upper,
+}
+
+#[derive(Debug, Deserialize)]
+struct RewriteConfig {
+    pattern: String,
+    replacement: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RulesConfig {
+    strip_prefixes: Vec<String>,
+    rewrites: Vec<RewriteConfig>,
+    case: Option<Case>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NamingConfig {
+    schemas: RulesConfig,
+    tables: RulesConfig,
+    columns: RulesConfig,
+}
+
+#[derive(Debug, Default)]
+struct Rules {
+    strip_prefixes: Vec<String>,
+    rewrites: Vec<(Regex, String)>,
+    case: Option<Case>,
+}
+
+impl Rules {
+    fn compile(config: RulesConfig) -> Result<Self> {
+        let rewrites = config
+            .rewrites
+            .into_iter()
+            .map(|rewrite| {
+                Regex::new(&rewrite.pattern)
+                    .map(|regex| (regex, rewrite.replacement))
+                    .map_err(|e| SnowflakeMapperError::InvalidExpression(format!("`{}`: {}", rewrite.pattern, e)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { strip_prefixes: config.strip_prefixes, rewrites, case: config.case })
+    }
+
+    fn apply(&self, name: &str) -> String {
+        let mut name = self
+            .strip_prefixes
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix.as_str()).filter(|rest| !rest.is_empty()))
+            .unwrap_or(name)
+            .to_string();
+        for (pattern, replacement) in &self.rewrites {
+            name = pattern.replace_all(&name, replacement.as_str()).into_owned();
+        }
+        match self.case {
+            Some(case) => convert_case(&name, case),
+            None => name,
+        }
+    }
+}
+
+/// Compiled naming rules for schemas, tables and columns
+#[derive(Debug, Default)]
+pub struct NamingRules {
+    schemas: Rules,
+    tables: Rules,
+    columns: Rules,
+}
+
+impl NamingRules {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read naming rules {}", path.display()))?;
+        let config: NamingConfig = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid naming rules {}", path.display()))?;
+        Ok(Self {
+            schemas: Rules::compile(config.schemas)?,
+            tables: Rules::compile(config.tables)?,
+            columns: Rules::compile(config.columns)?,
+        })
+    }
+
+    pub fn apply(&self, tables: Vec<TableInfo>) -> Vec<TableInfo> {
+        tables.into_iter().map(|table| self.apply_table(table)).collect()
+    }
+
+    fn apply_table(&self, table: TableInfo) -> TableInfo {
+        TableInfo {
+            schema_name: self.schemas.apply(&table.schema_name),
+            table_name: self.tables.apply(&table.table_name),
+            columns: table
+                .columns
+                .into_iter()
+                .map(|column| ColumnInfo { name: self.columns.apply(&column.name), ..column })
+                .collect(),
+            primary_key: table.primary_key.iter().map(|column| self.columns.apply(column)).collect(),
+            foreign_keys: table
+                .foreign_keys
+                .into_iter()
+                .map(|fk| ForeignKeyInfo {
+                    columns: fk.columns.iter().map(|column| self.columns.apply(column)).collect(),
+                    referenced_schema: self.schemas.apply(&fk.referenced_schema),
+                    referenced_table: self.tables.apply(&fk.referenced_table),
+                    referenced_columns: fk.referenced_columns.iter().map(|column| self.columns.apply(column)).collect(),
+                    ..fk
+                })
+                .collect(),
+            ..table
+        }
+    }
+}
+
+/// Split on `_`, `-`, spaces and lower-to-upper boundaries, then join in `case`
+fn convert_case(name: &str, case: Case) -> String {
+    let mut words: Vec<String> = Vec::new();
+    for part in name.split(['_', '-', ' ']).filter(|part| !part.is_empty()) {
+        let mut word = String::new();
+        let mut previous_lower = false;
+        for c in part.chars() {
+            if c.is_uppercase() && previous_lower {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+            word.push(c);
+        }
+        words.push(word);
+    }
+
+    let capitalize = |word: &String| {
+        let lower = word.to_lowercase();
+        let mut chars = lower.chars();
+        chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+    };
+    match case {
+        Case::Snake => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("_"),
+        Case::ScreamingSnake => words.iter().map(|w| w.to_uppercase()).collect::<Vec<_>>().join("_"),
+        Case::Lower => words.concat().to_lowercase(),
+        Case::Upper => words.concat().to_uppercase(),
+        Case::Pascal => words.iter().map(capitalize).collect(),
+        Case::Camel => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.to_lowercase() } else { capitalize(w) })
+            .collect(),
+    }
+}
@@ -0,0 +1,65 @@
+//! Drift alerts, routed to the teams that own the changed tables.
+
+use anyhow::Result;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::diff::{self, SchemaChange};
+use crate::ownership::Ownership;
+use crate::SnowflakeMapperError;
+
+pub struct SlackNotifier {
+    http: reqwest::Client,
+}
+
+impl Default for SlackNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SlackNotifier {
+    pub fn new() -> Self {
+        Self { http: reqwest::Client::new() }
+    }
+
+    /// Post `text` to a Slack incoming webhook
+    pub async fn post(&self, webhook: &str, text: &str) -> Result<()> {
+        let response = self
+            .http
+            .post(webhook)
+            .json(&json!({ "text": text }))
+            .send()
+            .await
+            .map_err(|e| SnowflakeMapperError::NotificationError(format!("Slack request failed: {}", e)))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(SnowflakeMapperError::NotificationError(format!("Slack returned {}: {}", status, text)).into());
+        }
+        Ok(())
+    }
+
+    /// Send each owning team the changes to its tables, headed by `heading`. Teams without a
+    /// webhook and changes without an owner are logged and skipped. Returns how many teams
+    /// were notified.
+    pub async fn notify_owners(&self, ownership: &Ownership, changes: &[SchemaChange], heading: &str) -> Result<usize> {
+        let (routed, unowned) = ownership.route(changes);
+        if !unowned.is_empty() {
+            warn!("{} changes have no owner and were not sent anywhere", unowned.len());
+        }
+        let mut notified = 0;
+        for (team, changes) in routed {
+            let Some(webhook) = ownership.team(&team).and_then(|t| t.slack_webhook.as_deref()) else {
+                warn!("Team {} has no slack_webhook; skipping {} changes", team, changes.len());
+                continue;
+            };
+            let changes: Vec<SchemaChange> = changes.into_iter().cloned().collect();
+            let text = format!("{}\n```\n{}```", heading, diff::render_text(&changes));
+            self.post(webhook, &text).await?;
+            info!("Sent {} changes to {}", changes.len(), team);
+            notified += 1;
+        }
+        Ok(notified)
+    }
+}
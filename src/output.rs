@@ -0,0 +1,454 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::formats::atlas::{self, AtlasModel};
+use crate::formats::{amundsen, avro, dot, markdown, semantic, terraform, topo};
+use crate::formats::template::TemplateRenderer;
+use crate::formats::xlsx;
+use crate::naming::NamingRules;
+use crate::ownership::Ownership;
+use crate::paths::PathTemplate;
+use crate::plugins::FormatPlugin;
+use crate::redact::Redactor;
+use crate::cli::{OutputArgs, OutputFormat};
+use crate::catalog;
+use crate::diff::{self, ChangeMarks};
+use crate::{SnowflakeMapperError, TableInfo};
+
+/// Writes mapped databases in the requested format.
+///
+/// Formats that produce one document per database are written as each database
+/// finishes; stdout output and whole-catalog templates are buffered until `finish`.
+pub struct OutputWriter {
+    args: OutputArgs,
+    template: Option<TemplateRenderer>,
+    plugin: Option<FormatPlugin>,
+    naming: Option<NamingRules>,
+    ownership: Option<Ownership>,
+    redactor: Option<Redactor>,
+    path_template: Option<PathTemplate>,
+    /// Earlier snapshot Markdown output marks changes against
+    previous: Option<Vec<TableInfo>>,
+    catalog: Vec<TableInfo>,
+}
+
+impl OutputWriter {
+    pub fn new(args: &OutputArgs) -> Result<Self> {
+        let template = match (&args.format, &args.template) {
+            (OutputFormat::Template, Some(path)) => Some(TemplateRenderer::from_file(path)?),
+            _ => None,
+        };
+        if args.format == OutputFormat::Amundsen && args.writes_to_stdout() {
+            return Err(SnowflakeMapperError::OutputError("--format amundsen writes three files and needs an output directory".to_string()).into());
+        }
+        let path_template = match &args.path_template {
+            Some(_) if args.format != OutputFormat::Json || args.writes_to_stdout() => {
+                return Err(SnowflakeMapperError::OutputError("--path-template only applies to JSON files in an output directory".to_string()).into());
+            }
+            Some(template) => Some(PathTemplate::parse(template, args.ndjson)?),
+            None => None,
+        };
+        let plugin = match &args.format {
+            OutputFormat::Plugin(name) => Some(FormatPlugin::find(name, &args.plugin_args)?),
+            _ => None,
+        };
+        let mut writer = Self {
+            args: args.clone(),
+            template,
+            plugin,
+            naming: args.naming.as_deref().map(NamingRules::from_file).transpose()?,
+            ownership: args.owners.as_deref().map(Ownership::from_file).transpose()?,
+            redactor: args.redact.then(|| Redactor::new(args.redact_salt.as_deref())),
+            path_template,
+            previous: None,
+            catalog: Vec::new(),
+        };
+        // Compared with the tables as written, so it goes through the same renaming
+        let previous = args.changes_since.as_deref().map(catalog::load_snapshot).transpose()?;
+        writer.previous = previous.map(|previous| writer.transform(previous));
+        Ok(writer)
+    }
+
+    /// Fill owners, then apply the naming rules and redaction, to tables of any database
+    fn transform(&self, tables: Vec<TableInfo>) -> Vec<TableInfo> {
+        // Ownership patterns are written against the real names, so before renaming
+        let tables = match &self.ownership {
+            Some(ownership) => ownership.apply(tables),
+            None => tables,
+        };
+        let tables = match &self.naming {
+            Some(naming) => naming.apply(tables),
+            None => tables,
+        };
+        match &self.redactor {
+            Some(redactor) => redactor.redact_tables(tables),
+            None => tables,
+        }
+    }
+
+    fn buffers_catalog(&self) -> bool {
+        match &self.args.format {
+            OutputFormat::Json => self.args.writes_to_stdout() && !self.args.ndjson,
+            OutputFormat::Template => self.args.writes_to_stdout() || self.args.template_catalog,
+            // Databuilder reads one set of files for the whole catalog
+            OutputFormat::Amundsen => true,
+            // Foreign keys can cross databases
+            OutputFormat::TopoOrder => true,
+            OutputFormat::Markdown
+            | OutputFormat::Atlas
+            | OutputFormat::Cube
+            | OutputFormat::Lookml
+            | OutputFormat::Terraform
+            | OutputFormat::Avro
+            | OutputFormat::Plugin(_) => false,
+            // Single-document formats can't be streamed, so stdout gets one document for everything
+            OutputFormat::Xlsx | OutputFormat::Dot => self.args.writes_to_stdout(),
+        }
+    }
+
+    pub async fn write_database(&mut self, database: &str, tables: Vec<TableInfo>) -> Result<()> {
+        let tables = self.transform(tables);
+        let database = match &self.redactor {
+            Some(redactor) => redactor.database(database),
+            None => database.to_string(),
+        };
+        let database = database.as_str();
+        if self.buffers_catalog() {
+            self.catalog.extend(tables);
+            return Ok(());
+        }
+
+        let output_dir = &self.args.output_dir;
+        match (&self.args.format, &self.template) {
+            (OutputFormat::Template, Some(renderer)) => {
+                let rendered = renderer.render_database(database, &tables)?;
+                let path = output_dir.join(database).with_extension(renderer.extension());
+                write_text_output(path, &rendered).await
+            }
+            (OutputFormat::Markdown, _) => {
+                let changes = self.previous.as_ref().map(|previous| {
+                    let previous: Vec<TableInfo> = previous.iter().filter(|t| t.database_name == database).cloned().collect();
+                    ChangeMarks::new(&diff::diff_catalogs(&previous, &tables))
+                });
+                let rendered = markdown::render_database(database, &tables, changes.as_ref());
+                if self.args.writes_to_stdout() {
+                    write_stdout(&rendered)
+                } else {
+                    write_text_output(output_dir.join(database).with_extension("md"), &rendered).await
+                }
+            }
+            (OutputFormat::Atlas, _) => {
+                let entities = atlas::render_entities(database, &tables, self.args.atlas_types, &self.args.atlas_cluster);
+                if self.args.writes_to_stdout() {
+                    write_stdout(&format!("{}\n", serde_json::to_string_pretty(&entities)?))
+                } else {
+                    write_formatted_output(output_dir.join(format!("{}.atlas.json", database)), &entities).await
+                }
+            }
+            (OutputFormat::Cube | OutputFormat::Lookml, _) => {
+                let (model, extension) = match self.args.format {
+                    OutputFormat::Cube => (semantic::render_cube(&tables), "cube.yml"),
+                    _ => (semantic::render_lookml(&tables), "view.lkml"),
+                };
+                if self.args.writes_to_stdout() {
+                    write_stdout(&model)
+                } else {
+                    write_text_output(output_dir.join(format!("{}.{}", database, extension)), &model).await
+                }
+            }
+            (OutputFormat::Xlsx, _) => {
+                let workbook = xlsx::render_workbook(&tables)?;
+                write_binary_output(output_dir.join(database).with_extension("xlsx"), &workbook).await
+            }
+            (OutputFormat::Dot, _) => {
+                let graph = dot::render_graph(database, &tables);
+                write_text_output(output_dir.join(database).with_extension("dot"), &graph).await
+            }
+            (OutputFormat::Terraform, _) => {
+                let resources = terraform::render_resources(database, &tables);
+                if self.args.writes_to_stdout() {
+                    return write_stdout(&resources);
+                }
+                write_text_output(output_dir.join(database).with_extension("tf"), &resources).await?;
+                let script = terraform::render_import_script(database, &tables);
+                write_text_output(output_dir.join(format!("{}_import.sh", database)), &script).await
+            }
+            (OutputFormat::Avro, _) => {
+                for table in &tables {
+                    let schema = avro::table_schema(table);
+                    if self.args.writes_to_stdout() {
+                        write_stdout(&format!("{}\n", schema))?;
+                    } else {
+                        let path = output_dir
+                            .join(database)
+                            .join(format!("{}.{}.avsc", table.schema_name, table.table_name));
+                        write_formatted_output(path, &schema).await?;
+                    }
+                }
+                Ok(())
+            }
+            (OutputFormat::Plugin(name), _) => {
+                let plugin = self.plugin.as_ref().expect("plugin is resolved in OutputWriter::new");
+                let rendered = plugin.render(database, output_dir, &tables).await?;
+                if self.args.writes_to_stdout() {
+                    write_stdout_bytes(&rendered)
+                } else if rendered.is_empty() {
+                    Ok(())
+                } else {
+                    let extension = self.args.plugin_extension.as_deref().unwrap_or(name);
+                    write_binary_output(output_dir.join(database).with_extension(extension), &rendered).await
+                }
+            }
+            _ if self.args.writes_to_stdout() => write_ndjson(&mut std::io::stdout().lock(), &tables),
+            _ => match &self.path_template {
+                Some(template) if template.splits_schemas() => {
+                    for (schema, tables) in group_by_schema(tables) {
+                        self.write_catalog(output_dir.join(template.render(database, Some(&schema))), &tables).await?;
+                    }
+                    Ok(())
+                }
+                Some(template) => self.write_catalog(output_dir.join(template.render(database, None)), &tables).await,
+                None => {
+                    let extension = if self.args.ndjson { "ndjson" } else { "json" };
+                    self.write_catalog(output_dir.join(database).with_extension(extension), &tables).await
+                }
+            },
+        }
+    }
+
+    async fn write_catalog(&self, path: PathBuf, tables: &[TableInfo]) -> Result<()> {
+        if self.args.ndjson {
+            write_ndjson_output(path, tables).await
+        } else {
+            write_formatted_output(path, &tables).await
+        }
+    }
+
+    /// Flush anything buffered for whole-catalog output
+    pub async fn finish(self) -> Result<()> {
+        if self.args.format == OutputFormat::Atlas && self.args.atlas_types == AtlasModel::Snowflake && !self.args.writes_to_stdout() {
+            write_formatted_output(self.args.output_dir.join(atlas::TYPEDEFS_FILE), &atlas::typedefs()).await?;
+        }
+        if !self.buffers_catalog() {
+            return Ok(());
+        }
+
+        match (&self.args.format, &self.template) {
+            (OutputFormat::Template, Some(renderer)) => {
+                let rendered = renderer.render_catalog(&self.catalog)?;
+                if self.args.writes_to_stdout() {
+                    write_stdout(&rendered)
+                } else {
+                    let path = self.args.output_dir.join("catalog").with_extension(renderer.extension());
+                    write_text_output(path, &rendered).await
+                }
+            }
+            (OutputFormat::Amundsen, _) => {
+                let output_dir = &self.args.output_dir;
+                write_text_output(output_dir.join("table.csv"), &amundsen::render_tables(&self.catalog)).await?;
+                write_text_output(output_dir.join("column.csv"), &amundsen::render_columns(&self.catalog)).await?;
+                write_text_output(output_dir.join("table_owner.csv"), &amundsen::render_owners(&self.catalog)).await
+            }
+            (OutputFormat::TopoOrder, _) => {
+                let steps = topo::dependency_order(&self.catalog);
+                if self.args.writes_to_stdout() {
+                    write_stdout(&format!("{}\n", serde_json::to_string_pretty(&steps)?))
+                } else {
+                    write_formatted_output(self.args.output_dir.join(topo::LOAD_ORDER_FILE), &steps).await
+                }
+            }
+            (OutputFormat::Xlsx, _) => write_stdout_bytes(&xlsx::render_workbook(&self.catalog)?),
+            (OutputFormat::Dot, _) => write_stdout(&dot::render_graph("catalog", &self.catalog)),
+            _ => {
+                let mut json = serde_json::to_string_pretty(&self.catalog)?;
+                json.push('\n');
+                write_stdout(&json)
+            }
+        }
+    }
+}
+
+/// Tables grouped by schema, in the order schemas first appear
+fn group_by_schema(tables: Vec<TableInfo>) -> Vec<(String, Vec<TableInfo>)> {
+    let mut groups: Vec<(String, Vec<TableInfo>)> = Vec::new();
+    for table in tables {
+        match groups.iter_mut().find(|(schema, _)| *schema == table.schema_name) {
+            Some((_, members)) => members.push(table),
+            None => groups.push((table.schema_name.clone(), vec![table])),
+        }
+    }
+    groups
+}
+
+pub async fn write_formatted_output(path: PathBuf, data: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(data)?;
+    write_text_output(path, &json).await
+}
+
+pub async fn write_text_output(path: PathBuf, contents: &str) -> Result<()> {
+    write_binary_output(path, contents.as_bytes()).await
+}
+
+pub async fn write_binary_output(path: PathBuf, contents: &[u8]) -> Result<()> {
+    write_atomically(&path, |file| file.write_all(contents))
+}
+
+/// Write a file through a temporary file next to it, renamed over `path` once complete, so
+/// readers see the old contents or the new ones but never a partial file
+pub fn write_atomically(path: &Path, write: impl FnOnce(&mut std::fs::File) -> std::io::Result<()>) -> Result<()> {
+    let failed = |e: std::io::Error| SnowflakeMapperError::OutputError(format!("Failed to write to {}: {}", path.display(), e));
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent).map_err(failed)?;
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("output");
+    let temporary = parent.join(format!(".{}.{}.tmp", name, std::process::id()));
+
+    let written = std::fs::File::create(&temporary).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+    match written.and_then(|()| std::fs::rename(&temporary, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temporary);
+            Err(failed(e).into())
+        }
+    }
+}
+
+/// Hidden directory next to `target` that a run with `--atomic-snapshot` writes into
+pub fn staging_directory(target: &Path) -> PathBuf {
+    let name = target.file_name().and_then(|name| name.to_str()).unwrap_or("output");
+    sibling(target, &format!(".{}.staging-{}", name, std::process::id()))
+}
+
+/// Replace `target` with the complete snapshot in `staging`. The old snapshot is moved aside
+/// first, so `target` is briefly missing but never holds a mix of both.
+pub fn replace_directory(staging: &Path, target: &Path) -> Result<()> {
+    let failed = |e: std::io::Error| SnowflakeMapperError::OutputError(format!("Failed to replace {}: {}", target.display(), e));
+    let name = target.file_name().and_then(|name| name.to_str()).unwrap_or("output");
+    let previous = sibling(target, &format!(".{}.previous-{}", name, std::process::id()));
+    if target.exists() {
+        std::fs::rename(target, &previous).map_err(failed)?;
+    }
+    if let Err(e) = std::fs::rename(staging, target) {
+        // Put the old snapshot back rather than leave nothing
+        let _ = std::fs::rename(&previous, target);
+        return Err(failed(e).into());
+    }
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous).map_err(failed)?;
+    }
+    Ok(())
+}
+
+fn sibling(path: &Path, name: &str) -> PathBuf {
+    match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) => parent.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Align rows into space-separated columns under an upper-case header
+pub fn render_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) -> String {
+    let header = header.map(String::from);
+    let widths: Vec<usize> = (0..N)
+        .map(|i| rows.iter().chain([&header]).map(|row| row[i].len()).max().unwrap_or(0))
+        .collect();
+
+    let mut out = String::new();
+    for row in [&header].into_iter().chain(rows) {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:width$}", cell, width = width)).collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+pub fn write_stdout(contents: &str) -> Result<()> {
+    write_stdout_bytes(contents.as_bytes())
+}
+
+fn write_stdout_bytes(contents: &[u8]) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(contents)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn write_ndjson(writer: &mut impl Write, tables: &[TableInfo]) -> Result<()> {
+    for table in tables {
+        serde_json::to_writer(&mut *writer, table)?;
+        writeln!(writer)
+            .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to write NDJSON line: {}", e)))?;
+    }
+    writer.flush()
+        .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to flush output: {}", e)))?;
+    Ok(())
+}
+
+async fn write_ndjson_output(path: PathBuf, tables: &[TableInfo]) -> Result<()> {
+    write_atomically(&path, |file| {
+        let mut writer = std::io::BufWriter::new(file);
+        for table in tables {
+            serde_json::to_writer(&mut writer, table)?;
+            writeln!(writer)?;
+        }
+        writer.flush()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Args {
+        #[command(flatten)]
+        output: OutputArgs,
+    }
+
+    #[tokio::test]
+    async fn changes_since_compares_redacted_tables_with_a_redacted_snapshot() {
+        let dir = std::env::temp_dir().join(format!("snowflake-mapper-changes-since-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tables: Vec<TableInfo> = serde_json::from_value(serde_json::json!([{
+            "database_name": "SALES",
+            "schema_name": "PUBLIC",
+            "table_name": "ORDERS",
+            "columns": [{ "name": "ID", "data_type": "NUMBER", "is_nullable": false }],
+        }]))
+        .unwrap();
+        let snapshot = dir.join("previous.json");
+        std::fs::write(&snapshot, serde_json::to_string(&tables).unwrap()).unwrap();
+        let out = dir.join("out");
+        let args = Args::parse_from([
+            "test".as_ref(),
+            "--format".as_ref(),
+            "markdown".as_ref(),
+            "--redact".as_ref(),
+            "--changes-since".as_ref(),
+            snapshot.as_os_str(),
+            "--output-dir".as_ref(),
+            out.as_os_str(),
+        ]);
+
+        let mut writer = OutputWriter::new(&args.output).unwrap();
+        writer.write_database("SALES", tables).await.unwrap();
+        let database = Redactor::new(None).database("SALES");
+        let rendered = std::fs::read_to_string(out.join(database).with_extension("md")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(rendered.contains("0 new, 0 changed and 0 removed tables"), "{}", rendered);
+    }
+}
@@ -0,0 +1,127 @@
+//! Which team owns which tables, CODEOWNERS-style.
+//!
+//! Ownership comes from a JSON file of teams and pattern rules:
+//!
+//! ```json
+//! {
+//!   "teams": {
+//!     "data-platform": { "slack_webhook": "https://hooks.slack.com/services/T000/B000/XXXX" },
+//!     "payments": { "slack_webhook": "https://hooks.slack.com/services/T000/B001/YYYY" }
+//!   },
+//!   "rules": [
+//!     { "pattern": "*", "owners": ["data-platform"] },
+//!     { "pattern": "SALES.PAYMENTS", "owners": ["payments"] },
+//!     { "pattern": "SALES.*.REFUND_*", "owners": ["payments", "finance"] }
+//!   ]
+//! }
+//! ```
+//!
+//! Patterns are globs over `DATABASE.SCHEMA.TABLE`, matched case-insensitively; a pattern
+//! with fewer parts covers everything beneath it. As in CODEOWNERS, the last matching rule
+//! wins, so general rules go first.
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::diff::SchemaChange;
+use crate::exclude::glob_to_regex;
+use crate::{SnowflakeMapperError, TableInfo};
+
+/// Where a team is notified
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Team {
+    /// Slack incoming webhook URL for the team's channel
+    pub slack_webhook: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OwnershipConfig {
+    teams: HashMap<String, Team>,
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug)]
+pub struct Ownership {
+    teams: HashMap<String, Team>,
+    rules: Vec<(Regex, Vec<String>)>,
+}
+
+impl Ownership {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read ownership rules {}", path.display()))?;
+        let config: OwnershipConfig = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid ownership rules {}", path.display()))?;
+        let rules = config
+            .rules
+            .into_iter()
+            .map(|rule| {
+                // `SALES.PAYMENTS` covers every table in the schema
+                let mut pattern = rule.pattern.clone();
+                for _ in pattern.split('.').count()..3 {
+                    pattern.push_str(".*");
+                }
+                RegexBuilder::new(&glob_to_regex(&pattern))
+                    .case_insensitive(true)
+                    .build()
+                    .map(|regex| (regex, rule.owners))
+                    .map_err(|e| SnowflakeMapperError::InvalidExpression(format!("ownership rule {}: {}", rule.pattern, e)).into())
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { teams: config.teams, rules })
+    }
+
+    /// Owning teams of a table, from the last matching rule; empty when no rule matches
+    pub fn owners(&self, database: &str, schema: &str, table: &str) -> &[String] {
+        let name = format!("{}.{}.{}", database, schema, table);
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.is_match(&name))
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    pub fn team(&self, name: &str) -> Option<&Team> {
+        self.teams.get(name)
+    }
+
+    /// Fill `owners` on every table
+    pub fn apply(&self, tables: Vec<TableInfo>) -> Vec<TableInfo> {
+        tables
+            .into_iter()
+            .map(|table| TableInfo {
+                owners: self.owners(&table.database_name, &table.schema_name, &table.table_name).to_vec(),
+                ..table
+            })
+            .collect()
+    }
+
+    /// Group changes by owning team; a change owned by several teams goes to each. Changes
+    /// with no owner, including warehouse changes, are returned separately.
+    pub fn route<'a>(&self, changes: &'a [SchemaChange]) -> (BTreeMap<String, Vec<&'a SchemaChange>>, Vec<&'a SchemaChange>) {
+        let mut routed: BTreeMap<String, Vec<&SchemaChange>> = BTreeMap::new();
+        let mut unowned = Vec::new();
+        for change in changes {
+            let owners = change.table().map_or(&[][..], |(database, schema, table)| self.owners(database, schema, table));
+            if owners.is_empty() {
+                unowned.push(change);
+            }
+            for owner in owners {
+                routed.entry(owner.clone()).or_default().push(change);
+            }
+        }
+        (routed, unowned)
+    }
+}
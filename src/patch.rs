@@ -0,0 +1,226 @@
+//! Machine-applicable diffs between two catalogs.
+//!
+//! [`json_patch`] is an RFC 6902 JSON Patch that turns the older merged catalog (the array
+//! of tables `load_snapshot` returns, as `export --output-dir -` prints it) into the newer
+//! one. Tables are matched by database, schema and name and columns by name, so a change to
+//! one column is a few operations on that column rather than a new table; other arrays are
+//! replaced whole. Operations apply in order, each against the result of the ones before.
+//!
+//! JSON Merge Patches (RFC 7386) can only replace arrays whole, so [`merge_patch`] works on
+//! the catalog keyed by `database.schema.table`, with each table's columns keyed by name
+//! ([`keyed_catalog`]). Merge patches can't set a value to null, so applying one drops the
+//! fields that are null in the newer catalog instead.
+
+use serde_json::{json, Map, Value};
+
+use crate::TableInfo;
+
+/// RFC 6902 operations turning `old` into `new`
+pub fn json_patch(old: &[TableInfo], new: &[TableInfo]) -> Vec<Value> {
+    let mut ops = Vec::new();
+    let old: Vec<Value> = old.iter().map(to_value).collect();
+    let new: Vec<Value> = new.iter().map(to_value).collect();
+    diff_keyed_array("", &old, &new, table_key, &mut ops);
+    ops
+}
+
+/// RFC 7386 merge patch turning the [`keyed_catalog`] of `old` into that of `new`
+pub fn merge_patch(old: &[TableInfo], new: &[TableInfo]) -> Value {
+    merge_diff(&keyed_catalog(old), &keyed_catalog(new)).unwrap_or_else(|| json!({}))
+}
+
+/// The catalog as an object keyed by `database.schema.table`, with `columns` keyed by name
+pub fn keyed_catalog(tables: &[TableInfo]) -> Value {
+    let mut catalog = Map::new();
+    for table in tables {
+        let mut value = to_value(table);
+        let columns: Map<String, Value> =
+            table.columns.iter().map(|column| (column.name.clone(), serde_json::to_value(column).expect("columns serialize"))).collect();
+        value["columns"] = Value::Object(columns);
+        catalog.insert(table.qualified_name(), value);
+    }
+    Value::Object(catalog)
+}
+
+fn to_value(table: &TableInfo) -> Value {
+    serde_json::to_value(table).expect("tables serialize")
+}
+
+fn table_key(table: &Value) -> Value {
+    json!([table["database_name"], table["schema_name"], table["table_name"]])
+}
+
+fn column_key(column: &Value) -> Value {
+    column["name"].clone()
+}
+
+fn diff_value(path: &str, old: &Value, new: &Value, ops: &mut Vec<Value>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                ops.push(json!({ "op": "remove", "path": format!("{}/{}", path, escape(key)) }));
+            }
+            for (key, value) in new {
+                let child = format!("{}/{}", path, escape(key));
+                match old.get(key) {
+                    Some(previous) => match (key.as_str(), previous, value) {
+                        ("columns", Value::Array(previous), Value::Array(value)) => diff_keyed_array(&child, previous, value, column_key, ops),
+                        _ => diff_value(&child, previous, value, ops),
+                    },
+                    None => ops.push(json!({ "op": "add", "path": child, "value": value })),
+                }
+            }
+        }
+        _ => ops.push(json!({ "op": "replace", "path": path, "value": new })),
+    }
+}
+
+/// Remove the items missing from `new`, then walk `new` in order, moving matched items
+/// into place and adding the others, and diff each matched pair
+fn diff_keyed_array(path: &str, old: &[Value], new: &[Value], key: fn(&Value) -> Value, ops: &mut Vec<Value>) {
+    let new_keys: Vec<Value> = new.iter().map(key).collect();
+    let mut current: Vec<(Value, &Value)> = Vec::new();
+    let mut removed = Vec::new();
+    for (index, item) in old.iter().enumerate() {
+        let item_key = key(item);
+        if new_keys.contains(&item_key) {
+            current.push((item_key, item));
+        } else {
+            removed.push(index);
+        }
+    }
+    // From the end, so earlier indexes stay valid
+    for index in removed.into_iter().rev() {
+        ops.push(json!({ "op": "remove", "path": format!("{}/{}", path, index) }));
+    }
+
+    for (index, (item_key, item)) in new_keys.into_iter().zip(new).enumerate() {
+        match current.iter().position(|(key, _)| *key == item_key) {
+            Some(position) => {
+                // Everything before `index` is already in place, so the match is further on
+                if position != index {
+                    let moved = current.remove(position);
+                    current.insert(index, moved);
+                    ops.push(json!({ "op": "move", "from": format!("{}/{}", path, position), "path": format!("{}/{}", path, index) }));
+                }
+                diff_value(&format!("{}/{}", path, index), current[index].1, item, ops);
+            }
+            None => {
+                current.insert(index, (item_key, item));
+                ops.push(json!({ "op": "add", "path": format!("{}/{}", path, index), "value": item }));
+            }
+        }
+    }
+}
+
+/// Merge patch from `old` to `new`, or `None` when they're equal
+fn merge_diff(old: &Value, new: &Value) -> Option<Value> {
+    if old == new {
+        return None;
+    }
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = Map::new();
+            for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            for (key, value) in new {
+                match old.get(key) {
+                    Some(previous) => {
+                        if let Some(child) = merge_diff(previous, value) {
+                            patch.insert(key.clone(), child);
+                        }
+                    }
+                    None => {
+                        patch.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            Some(Value::Object(patch))
+        }
+        _ => Some(new.clone()),
+    }
+}
+
+/// A JSON Pointer reference token (RFC 6901)
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str, comment: Option<&str>, columns: &[(&str, &str)]) -> TableInfo {
+        let columns: Vec<Value> =
+            columns.iter().map(|(name, data_type)| json!({ "name": name, "data_type": data_type, "is_nullable": true })).collect();
+        serde_json::from_value(json!({
+            "database_name": "SALES",
+            "schema_name": "PUBLIC",
+            "table_name": name,
+            "comment": comment,
+            "columns": columns,
+            "primary_key": [columns.first().map(|column| column["name"].clone())],
+        }))
+        .expect("test tables deserialize")
+    }
+
+    fn catalog(tables: &[TableInfo]) -> Value {
+        serde_json::to_value(tables).expect("tables serialize")
+    }
+
+    /// Applying a merge patch removes the members it sets to null
+    fn without_nulls(value: Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                Value::Object(map.into_iter().filter(|(_, value)| !value.is_null()).map(|(key, value)| (key, without_nulls(value))).collect())
+            }
+            Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+            other => other,
+        }
+    }
+
+    fn old_and_new() -> (Vec<TableInfo>, Vec<TableInfo>) {
+        let old = vec![
+            table("ORDERS", Some("orders"), &[("ID", "NUMBER"), ("CUSTOMER", "NUMBER"), ("NOTE", "TEXT"), ("TOTAL", "FLOAT")]),
+            table("CUSTOMERS", None, &[("ID", "NUMBER"), ("NAME", "TEXT")]),
+            table("LEGACY", None, &[("ID", "NUMBER")]),
+            table("ITEMS", None, &[("ID", "NUMBER")]),
+        ];
+        let new = vec![
+            table("CUSTOMERS", Some("who buys"), &[("NAME", "TEXT"), ("ID", "NUMBER"), ("EMAIL/ADDRESS", "TEXT")]),
+            table("PAYMENTS", None, &[("ID", "NUMBER")]),
+            table("ORDERS", None, &[("TOTAL", "NUMBER"), ("ID", "NUMBER"), ("PLACED~AT", "TIMESTAMP_NTZ"), ("CUSTOMER", "NUMBER")]),
+            table("ITEMS", None, &[("ID", "NUMBER")]),
+        ];
+        (old, new)
+    }
+
+    #[test]
+    fn json_patch_turns_old_into_new() {
+        let (old, new) = old_and_new();
+        let ops = json_patch(&old, &new);
+        let patch: json_patch::Patch = serde_json::from_value(Value::Array(ops)).expect("operations are valid RFC 6902");
+        let mut patched = catalog(&old);
+        json_patch::patch(&mut patched, &patch).expect("patch applies");
+        assert_eq!(patched, catalog(&new));
+    }
+
+    #[test]
+    fn json_patch_of_equal_catalogs_is_empty() {
+        let (old, _) = old_and_new();
+        assert!(json_patch(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn merge_patch_turns_keyed_old_into_keyed_new() {
+        let (old, new) = old_and_new();
+        let mut patched = keyed_catalog(&old);
+        json_patch::merge(&mut patched, &merge_patch(&old, &new));
+        assert_eq!(without_nulls(patched), without_nulls(keyed_catalog(&new)));
+        assert_eq!(merge_patch(&old, &old), json!({}));
+    }
+}
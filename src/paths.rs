@@ -0,0 +1,90 @@
+//! `--path-template`: where catalog files are written within the output directory.
+//!
+//! Templates are paths with `{account}`, `{database}`, `{schema}`, `{date}` and `{run_id}`
+//! placeholders, e.g. `{account}/dt={date}/{database}.json`. `{database}` is required so
+//! databases don't overwrite each other; with `{schema}`, each schema gets a file of its own.
+//! `{date}` is the UTC date the run started and `{run_id}` its start time
+//! (`20240131T235959Z`), so every run of a day lands in its own partition.
+
+use anyhow::Result;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+use crate::SnowflakeMapperError;
+
+const VARIABLES: &[&str] = &["account", "database", "schema", "date", "run_id"];
+
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    template: String,
+    account: Option<String>,
+    date: String,
+    run_id: String,
+}
+
+impl PathTemplate {
+    /// Check `template` for unknown placeholders and an extension matching the output
+    /// (`.json`, or `.ndjson` with `--ndjson`). `{account}` needs SNOWFLAKE_ACCOUNT.
+    pub fn parse(template: &str, ndjson: bool) -> Result<Self> {
+        let invalid = |message: String| SnowflakeMapperError::OutputError(format!("--path-template {}: {}", template, message));
+        let placeholder = Regex::new(r"\{([^{}]*)\}").expect("valid regex");
+        let used: Vec<&str> = placeholder.captures_iter(template).map(|c| c.get(1).expect("group").as_str()).collect();
+        if let Some(unknown) = used.iter().find(|name| !VARIABLES.contains(name)) {
+            return Err(invalid(format!("unknown placeholder {{{}}}; use {}", unknown, VARIABLES.join(", "))).into());
+        }
+        if !used.contains(&"database") {
+            return Err(invalid("needs {database}, or databases would overwrite each other".to_string()).into());
+        }
+        let extension = if ndjson { "ndjson" } else { "json" };
+        if Path::new(template).extension().is_none_or(|ext| ext != extension) {
+            return Err(invalid(format!("must end in .{}", extension)).into());
+        }
+        if Path::new(template).is_absolute() || template.split(['/', '\\']).any(|part| part == "..") {
+            return Err(invalid("must stay within the output directory".to_string()).into());
+        }
+
+        let account = if used.contains(&"account") {
+            Some(std::env::var("SNOWFLAKE_ACCOUNT").map_err(|_| SnowflakeMapperError::MissingEnvVar("SNOWFLAKE_ACCOUNT".to_string()))?)
+        } else {
+            None
+        };
+        let started = chrono::Utc::now();
+        Ok(Self {
+            template: template.to_string(),
+            account,
+            date: started.format("%Y-%m-%d").to_string(),
+            run_id: started.format("%Y%m%dT%H%M%SZ").to_string(),
+        })
+    }
+
+    /// Whether each schema is written to a file of its own
+    pub fn splits_schemas(&self) -> bool {
+        self.template.contains("{schema}")
+    }
+
+    /// Path of the file for `database`, or one of its schemas, relative to the output directory
+    pub fn render(&self, database: &str, schema: Option<&str>) -> PathBuf {
+        let mut path = self
+            .template
+            .replace("{database}", &segment(database))
+            .replace("{date}", &self.date)
+            .replace("{run_id}", &self.run_id);
+        if let Some(account) = &self.account {
+            path = path.replace("{account}", &segment(account));
+        }
+        if let Some(schema) = schema {
+            path = path.replace("{schema}", &segment(schema));
+        }
+        PathBuf::from(path)
+    }
+}
+
+/// A name made safe to use as one path segment
+fn segment(name: &str) -> String {
+    let name = name.replace(['/', '\\'], "_");
+    if name == "." || name == ".." {
+        name.replace('.', "_")
+    } else {
+        name
+    }
+}
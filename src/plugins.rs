@@ -0,0 +1,95 @@
+//! External output-format plugins.
+//!
+//! `--format <name>` with a name that isn't built in runs `snowflake-mapper-format-<name>`
+//! from `PATH` once per database. The plugin receives the database's tables as NDJSON on
+//! stdin and writes its rendered output to stdout, which is saved as `<database>.<ext>`
+//! (or passed through with `--output -`). The database name and output directory are
+//! available in `SNOWFLAKE_MAPPER_DATABASE` and `SNOWFLAKE_MAPPER_OUTPUT_DIR` for plugins
+//! that prefer to write their own files; an empty stdout writes nothing.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::{SnowflakeMapperError, TableInfo};
+
+const PLUGIN_PREFIX: &str = "snowflake-mapper-format-";
+
+pub struct FormatPlugin {
+    name: String,
+    executable: PathBuf,
+    args: Vec<String>,
+}
+
+impl FormatPlugin {
+    /// Locate the plugin for `name` on `PATH`
+    pub fn find(name: &str, args: &[String]) -> Result<Self> {
+        let binary = format!("{}{}", PLUGIN_PREFIX, name);
+        let executable = std::env::var_os("PATH")
+            .iter()
+            .flat_map(std::env::split_paths)
+            .flat_map(|dir| {
+                [dir.join(&binary), dir.join(&binary).with_extension(std::env::consts::EXE_EXTENSION)]
+            })
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| {
+                SnowflakeMapperError::OutputError(format!(
+                    "Unknown format '{}': no built-in format or `{}` plugin on PATH",
+                    name, binary
+                ))
+            })?;
+
+        Ok(Self {
+            name: name.to_string(),
+            executable,
+            args: args.to_vec(),
+        })
+    }
+
+    /// Run the plugin for one database and return what it wrote to stdout
+    pub async fn render(&self, database: &str, output_dir: &Path, tables: &[TableInfo]) -> Result<Vec<u8>> {
+        let mut input = Vec::new();
+        for table in tables {
+            serde_json::to_writer(&mut input, table)?;
+            input.push(b'\n');
+        }
+
+        let mut child = Command::new(&self.executable)
+            .args(&self.args)
+            .env("SNOWFLAKE_MAPPER_DATABASE", database)
+            .env("SNOWFLAKE_MAPPER_OUTPUT_DIR", output_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| self.error(format!("failed to start: {}", e)))?;
+
+        // Feed stdin from a separate task so a plugin that streams output can't deadlock on a full pipe
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let feeder = tokio::spawn(async move {
+            stdin.write_all(&input).await?;
+            stdin.shutdown().await
+        });
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| self.error(format!("failed while running: {}", e)))?;
+        if let Ok(Err(e)) = feeder.await {
+            // A plugin may legitimately exit without reading everything; only report it if it also failed
+            if !output.status.success() {
+                return Err(self.error(format!("failed to read input: {}", e)));
+            }
+        }
+        if !output.status.success() {
+            return Err(self.error(format!("exited with {}", output.status)));
+        }
+        Ok(output.stdout)
+    }
+
+    fn error(&self, message: String) -> anyhow::Error {
+        SnowflakeMapperError::OutputError(format!("Format plugin '{}' {}", self.name, message)).into()
+    }
+}
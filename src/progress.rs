@@ -0,0 +1,126 @@
+//! Structured progress events for the CLI bar and for embedding applications.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A step in mapping an account
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// Mapping is about to start on `total` databases
+    Started { total: usize },
+    DatabaseStarted { database: String, index: usize, total: usize },
+    /// A backend finished `completed` of `total` fetch steps for a database
+    DatabaseStep { database: String, completed: usize, total: usize, message: String },
+    DatabaseFinished { database: String, tables: usize },
+    DatabaseFailed { database: String, error: String },
+    /// `retries` counts every retried database fetch, across all databases
+    Finished { databases: usize, tables: usize, failed: usize, retries: usize },
+}
+
+/// Receives progress events as mapping runs
+pub trait ProgressListener: Send + Sync {
+    fn on_event(&self, event: &ProgressEvent);
+}
+
+/// Discards every event
+pub struct NoProgress;
+
+impl ProgressListener for NoProgress {
+    fn on_event(&self, _event: &ProgressEvent) {}
+}
+
+/// Forward events to a channel, e.g. to stream them from a server
+impl ProgressListener for UnboundedSender<ProgressEvent> {
+    fn on_event(&self, event: &ProgressEvent) {
+        // A dropped receiver only means nobody is listening any more
+        let _ = self.send(event.clone());
+    }
+}
+
+impl<F> ProgressListener for F
+where
+    F: Fn(&ProgressEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+/// Draws an overall bar plus one bar per in-flight database
+pub struct BarProgress {
+    bars: MultiProgress,
+    overall: ProgressBar,
+    databases: Mutex<HashMap<String, ProgressBar>>,
+}
+
+impl BarProgress {
+    pub fn new() -> Self {
+        let bars = MultiProgress::new();
+        let overall = bars.add(ProgressBar::new(0));
+        overall.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} ETA {eta} {msg}")
+                .unwrap()
+                .progress_chars("##-"),
+        );
+        Self { bars, overall, databases: Mutex::new(HashMap::new()) }
+    }
+
+    fn database_bar(&self, database: &str) -> ProgressBar {
+        let mut databases = self.databases.lock().unwrap();
+        databases
+            .entry(database.to_string())
+            .or_insert_with(|| {
+                let bar = self.bars.add(ProgressBar::new(0));
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template("  {prefix:20} {bar:20.green/white} {pos}/{len} ETA {eta} {msg}")
+                        .unwrap()
+                        .progress_chars("##-"),
+                );
+                bar.set_prefix(database.to_string());
+                bar
+            })
+            .clone()
+    }
+
+    fn remove_database_bar(&self, database: &str) {
+        if let Some(bar) = self.databases.lock().unwrap().remove(database) {
+            bar.finish_and_clear();
+            self.bars.remove(&bar);
+        }
+    }
+}
+
+impl Default for BarProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressListener for BarProgress {
+    fn on_event(&self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::Started { total } => self.overall.set_length(*total as u64),
+            ProgressEvent::DatabaseStarted { database, .. } => {
+                self.database_bar(database).set_message("listing columns");
+            }
+            ProgressEvent::DatabaseStep { database, completed, total, message } => {
+                let bar = self.database_bar(database);
+                bar.set_length(*total as u64);
+                bar.set_position(*completed as u64);
+                bar.set_message(message.clone());
+            }
+            ProgressEvent::DatabaseFinished { database, .. } | ProgressEvent::DatabaseFailed { database, .. } => {
+                self.remove_database_bar(database);
+                self.overall.inc(1);
+            }
+            ProgressEvent::Finished { retries: 0, .. } => self.overall.finish_with_message("Done!"),
+            ProgressEvent::Finished { retries, .. } => self.overall.finish_with_message(format!("Done! ({} retries)", retries)),
+        }
+    }
+}
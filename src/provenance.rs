@@ -0,0 +1,125 @@
+//! How a snapshot was produced, embedded in the documents it's made of.
+//!
+//! Catalog files stay plain arrays of tables, so every table carries the provenance of the
+//! run that mapped it; account-level documents carry one for the whole document.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::backends::Backend;
+use crate::builder::DEFAULT_ROLE;
+use crate::cli::ConnectionArgs;
+use crate::guardrails::QueryBudget;
+
+/// Flags whose values are secrets and are masked in `arguments`
+const SECRET_FLAGS: &[&str] = &["--redact-salt"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Provenance {
+    /// `snowflake-mapper <version>`
+    pub tool: String,
+    /// Snowflake account, role and warehouse of the session; `None` for other backends and
+    /// replayed fixtures
+    pub account: Option<String>,
+    pub role: Option<String>,
+    pub warehouse: Option<String>,
+    /// RFC 3339 times the run started and the document was finished
+    pub started_at: String,
+    pub finished_at: String,
+    /// Queries the run had sent by the time the document was finished
+    pub queries: usize,
+    /// Command line arguments, with secret values and the credentials of URLs masked
+    pub arguments: Vec<String>,
+}
+
+/// Tracks a run to stamp its documents with
+pub struct RunProvenance {
+    started: DateTime<Utc>,
+    account: Option<String>,
+    role: Option<String>,
+    warehouse: Option<String>,
+    budget: Arc<QueryBudget>,
+    arguments: Vec<String>,
+}
+
+impl RunProvenance {
+    /// Start tracking a run that queries through `connection`, counting the queries of `budget`
+    pub fn start(connection: &ConnectionArgs, budget: Arc<QueryBudget>) -> Self {
+        // Replayed fixtures never reach an account
+        let replaying = connection.fixture.is_some() && !connection.record;
+        let snowflake = connection.backend == Backend::Snowflake && !replaying;
+        let env = |name: &str| std::env::var(name).ok().filter(|_| snowflake);
+        Self {
+            started: Utc::now(),
+            account: env("SNOWFLAKE_ACCOUNT"),
+            role: connection
+                .role
+                .clone()
+                .filter(|_| snowflake)
+                .or_else(|| env("SNOWFLAKE_ROLE"))
+                .or_else(|| snowflake.then(|| DEFAULT_ROLE.to_string())),
+            // Mapping queries run on the metadata warehouse when one is given
+            warehouse: connection.metadata_warehouse.clone().filter(|_| snowflake).or_else(|| env("SNOWFLAKE_WAREHOUSE")),
+            budget,
+            arguments: mask_secrets(std::env::args().skip(1)),
+        }
+    }
+
+    /// For a `--merge-roles` sweep, connecting as each of `roles`
+    pub fn with_roles(self, roles: &[String]) -> Self {
+        let role = self.role.as_ref().map(|_| roles.join(","));
+        Self { role, ..self }
+    }
+
+    /// Provenance of a document finished now
+    pub fn finish(&self) -> Provenance {
+        Provenance {
+            tool: format!("snowflake-mapper {}", env!("CARGO_PKG_VERSION")),
+            account: self.account.clone(),
+            role: self.role.clone(),
+            warehouse: self.warehouse.clone(),
+            started_at: self.started.to_rfc3339_opts(SecondsFormat::Secs, true),
+            finished_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            queries: self.budget.used(),
+            arguments: self.arguments.clone(),
+        }
+    }
+}
+
+impl Provenance {
+    /// Without the account and arguments, which name what `--redact` hides
+    pub fn redacted(self) -> Self {
+        Self { account: None, arguments: Vec::new(), ..self }
+    }
+}
+
+fn mask_secrets(arguments: impl Iterator<Item = String>) -> Vec<String> {
+    let mut masked = Vec::new();
+    let mut secret_next = false;
+    for argument in arguments {
+        if std::mem::take(&mut secret_next) {
+            masked.push("***".to_string());
+        } else if let Some(flag) = SECRET_FLAGS.iter().find(|flag| argument.starts_with(&format!("{}=", flag))) {
+            masked.push(format!("{}=***", flag));
+        } else {
+            secret_next = SECRET_FLAGS.contains(&argument.as_str());
+            masked.push(mask_userinfo(argument));
+        }
+    }
+    masked
+}
+
+/// `argument` with the `user:password@` of a URL in it, such as `--atlas-url`, masked
+fn mask_userinfo(argument: String) -> String {
+    let Some(scheme_end) = argument.find("://").map(|index| index + 3) else {
+        return argument;
+    };
+    let authority = argument[scheme_end..].split(['/', '?', '#']).next().unwrap_or_default();
+    match authority.rfind('@') {
+        Some(at) => format!("{}***{}", &argument[..scheme_end], &argument[scheme_end + at..]),
+        None => argument,
+    }
+}
@@ -0,0 +1,45 @@
+//! JSONPath queries over a catalog, for questions like "which columns are VARIANT?".
+//!
+//! Expressions follow RFC 9535 and run against the merged catalog, an array with one
+//! object per table as written by `--output -`.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+use crate::{SnowflakeMapperError, TableInfo};
+
+/// One match, with the table it was found in
+#[derive(Debug, Clone, Serialize)]
+pub struct Match {
+    /// `database.schema.table`, when the match is inside a table
+    pub table: Option<String>,
+    /// Normalized path of the match, e.g. `$[3]['columns'][1]`
+    pub path: String,
+    pub value: Value,
+}
+
+pub fn parse(expression: &str) -> Result<JsonPath> {
+    JsonPath::parse(expression)
+        .map_err(|e| SnowflakeMapperError::InvalidExpression(format!("`{}`: {}", expression, e)).into())
+}
+
+/// Evaluate `path` against the catalog, in document order
+pub fn query_catalog(tables: &[TableInfo], path: &JsonPath) -> Result<Vec<Match>> {
+    let catalog = serde_json::to_value(tables)?;
+    Ok(path
+        .query_located(&catalog)
+        .into_iter()
+        .map(|node| {
+            let location = node.location().to_string();
+            let table = node
+                .location()
+                .first()
+                .and_then(|element| element.as_index())
+                .and_then(|index| tables.get(index))
+                .map(|table| table.qualified_name());
+            Match { table, path: location, value: node.node().clone() }
+        })
+        .collect())
+}
@@ -0,0 +1,95 @@
+//! Audit log of every executed query, with credentials redacted.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::SnowflakeMapperError;
+
+/// `PASSWORD = '...'`, `AWS_SECRET_KEY='...'` style assignments whose literal is replaced
+static CREDENTIAL_ASSIGNMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(\w*(?:password|passphrase|secret|token|private_key|key_id)\w*|identified\s+by)(\s*=?\s*)'(?:[^']|'')*'")
+        .unwrap()
+});
+
+const REDACTED: &str = "'***'";
+
+/// One line of the query log
+#[derive(Debug, Serialize)]
+struct QueryLogEntry<'a> {
+    timestamp: String,
+    backend: &'a str,
+    query: String,
+    duration_ms: f64,
+    rows: Option<usize>,
+    error: Option<String>,
+    /// Served from a fixture rather than the warehouse
+    replayed: bool,
+}
+
+/// Appends one JSON line per executed query; shared by every connection of a run
+pub struct QueryLog {
+    file: Mutex<File>,
+    secrets: Mutex<Vec<String>>,
+}
+
+// Hand-written so the secrets never end up in debug output
+impl std::fmt::Debug for QueryLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryLog").finish_non_exhaustive()
+    }
+}
+
+impl QueryLog {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| SnowflakeMapperError::OutputError(format!("Failed to create query log {}: {}", path.display(), e)))?;
+        Ok(Self { file: Mutex::new(file), secrets: Mutex::new(Vec::new()) })
+    }
+
+    /// Redact this value wherever it appears, e.g. the connection password
+    pub fn add_secret(&self, secret: &str) {
+        if !secret.is_empty() {
+            self.secrets.lock().unwrap().push(secret.to_string());
+        }
+    }
+
+    /// Record a query; `outcome` is the row count or the error it failed with
+    pub fn record(&self, backend: &str, query: &str, duration: Duration, outcome: Result<usize, String>, replayed: bool) {
+        let (rows, error) = match outcome {
+            Ok(rows) => (Some(rows), None),
+            Err(error) => (None, Some(self.redact(&error))),
+        };
+        let entry = QueryLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            backend,
+            query: self.redact(query),
+            duration_ms: duration.as_secs_f64() * 1000.0,
+            rows,
+            error,
+            replayed,
+        };
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        // Logging must never fail the run it is auditing
+        let mut file = self.file.lock().unwrap();
+        let _ = writeln!(file, "{}", line).and_then(|_| file.flush());
+    }
+
+    fn redact(&self, text: &str) -> String {
+        let mut redacted = CREDENTIAL_ASSIGNMENT
+            .replace_all(text, |captures: &regex::Captures| format!("{}{}{}", &captures[1], &captures[2], REDACTED))
+            .into_owned();
+        for secret in self.secrets.lock().unwrap().iter() {
+            redacted = redacted.replace(secret.as_str(), "***");
+        }
+        redacted
+    }
+}
@@ -0,0 +1,71 @@
+//! Pseudonymized snapshots that keep types and structure but hide the data model.
+//!
+//! Every database, schema, table, column and constraint name is replaced by a pseudonym
+//! derived from a hash of the name, so the same name maps to the same pseudonym everywhere
+//! (foreign keys and primary keys still line up, and two runs stay diffable). Free text that
+//! could leak names or data (comments, defaults, clustering expressions) is dropped.
+
+use crate::hash::fnv1a;
+use crate::provenance::Provenance;
+use crate::{ColumnInfo, ForeignKeyInfo, TableInfo};
+
+pub struct Redactor {
+    salt: String,
+}
+
+impl Redactor {
+    /// A salt makes pseudonyms unguessable from a list of likely names; without one, the
+    /// same name always maps to the same pseudonym
+    pub fn new(salt: Option<&str>) -> Self {
+        Self { salt: salt.unwrap_or_default().to_string() }
+    }
+
+    pub fn database(&self, name: &str) -> String {
+        self.pseudonym("db", name)
+    }
+
+    pub fn redact_tables(&self, tables: Vec<TableInfo>) -> Vec<TableInfo> {
+        tables.into_iter().map(|table| self.redact_table(table)).collect()
+    }
+
+    fn redact_table(&self, table: TableInfo) -> TableInfo {
+        TableInfo {
+            database_name: self.database(&table.database_name),
+            schema_name: self.pseudonym("schema", &table.schema_name),
+            table_name: self.pseudonym("table", &table.table_name),
+            comment: None,
+            columns: table.columns.into_iter().map(|column| self.redact_column(column)).collect(),
+            primary_key: table.primary_key.iter().map(|column| self.pseudonym("col", column)).collect(),
+            foreign_keys: table.foreign_keys.into_iter().map(|fk| self.redact_foreign_key(fk)).collect(),
+            clustering_key: None,
+            provenance: table.provenance.map(Provenance::redacted),
+            ..table
+        }
+    }
+
+    fn redact_column(&self, column: ColumnInfo) -> ColumnInfo {
+        ColumnInfo {
+            name: self.pseudonym("col", &column.name),
+            comment: None,
+            column_default: None,
+            ..column
+        }
+    }
+
+    fn redact_foreign_key(&self, fk: ForeignKeyInfo) -> ForeignKeyInfo {
+        ForeignKeyInfo {
+            name: self.pseudonym("fk", &fk.name),
+            columns: fk.columns.iter().map(|column| self.pseudonym("col", column)).collect(),
+            referenced_database: self.database(&fk.referenced_database),
+            referenced_schema: self.pseudonym("schema", &fk.referenced_schema),
+            referenced_table: self.pseudonym("table", &fk.referenced_table),
+            referenced_columns: fk.referenced_columns.iter().map(|column| self.pseudonym("col", column)).collect(),
+        }
+    }
+
+    fn pseudonym(&self, kind: &str, name: &str) -> String {
+        let hash = fnv1a(format!("{}\0{}", self.salt, name).as_bytes());
+        // 48 bits keeps pseudonyms short with collisions unlikely below millions of names
+        format!("{}_{:012x}", kind, hash >> 16)
+    }
+}
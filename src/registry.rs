@@ -0,0 +1,127 @@
+//! Publishing generated Avro schemas to a Confluent Schema Registry.
+
+use anyhow::Result;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::formats::avro;
+use crate::{SnowflakeMapperError, TableInfo};
+
+const CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+/// What happened to a subject when a schema was published
+#[derive(Debug, PartialEq, Eq)]
+pub enum PublishOutcome {
+    /// First version of a new subject
+    Created(i64),
+    /// New version passing the subject's compatibility check
+    Registered(i64),
+}
+
+#[derive(Deserialize)]
+struct CompatibilityResponse {
+    is_compatible: bool,
+    #[serde(default)]
+    messages: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RegisterResponse {
+    id: i64,
+}
+
+pub struct SchemaRegistryClient {
+    http: reqwest::Client,
+    base_url: String,
+    credentials: Option<(String, String)>,
+}
+
+impl SchemaRegistryClient {
+    pub fn new(base_url: &str, credentials: Option<(String, String)>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            credentials,
+        }
+    }
+
+    /// Publish the Avro schema of every table under `{prefix}{database}.{schema}.{table}`
+    pub async fn publish_tables(&self, prefix: &str, tables: &[TableInfo]) -> Result<()> {
+        for table in tables {
+            let subject = format!("{}{}.{}.{}", prefix, table.database_name, table.schema_name, table.table_name);
+            match self.publish(&subject, &avro::table_schema(table)).await? {
+                PublishOutcome::Created(id) => info!("Created subject {} with schema {}", subject, id),
+                PublishOutcome::Registered(id) => info!("Registered schema {} for subject {}", id, subject),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check `schema` against the latest version of `subject` and register it if compatible.
+    /// Re-registering an identical schema is a no-op in the registry and returns the existing id.
+    pub async fn publish(&self, subject: &str, schema: &Value) -> Result<PublishOutcome> {
+        let body = json!({ "schema": schema.to_string() });
+
+        let compatibility = self
+            .post(&format!("/compatibility/subjects/{}/versions/latest", encode(subject)), &body)
+            .await?;
+        let is_new_subject = match compatibility.status() {
+            StatusCode::NOT_FOUND => true,
+            status if status.is_success() => {
+                let result: CompatibilityResponse = compatibility.json().await?;
+                if !result.is_compatible {
+                    return Err(SnowflakeMapperError::RegistryError(format!(
+                        "Schema for {} is not compatible with the latest registered version{}",
+                        subject,
+                        if result.messages.is_empty() { String::new() } else { format!(": {}", result.messages.join("; ")) }
+                    ))
+                    .into());
+                }
+                false
+            }
+            _ => return Err(Self::failure(subject, compatibility).await),
+        };
+
+        let response = self.post(&format!("/subjects/{}/versions", encode(subject)), &body).await?;
+        if !response.status().is_success() {
+            return Err(Self::failure(subject, response).await);
+        }
+        let RegisterResponse { id } = response.json().await?;
+
+        Ok(if is_new_subject { PublishOutcome::Created(id) } else { PublishOutcome::Registered(id) })
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<reqwest::Response> {
+        let mut request = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+            .json(body);
+        if let Some((username, password)) = &self.credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| SnowflakeMapperError::RegistryError(format!("Request to {} failed: {}", path, e)).into())
+    }
+
+    async fn failure(subject: &str, response: reqwest::Response) -> anyhow::Error {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        SnowflakeMapperError::RegistryError(format!("Publishing {} failed with {}: {}", subject, status, text)).into()
+    }
+}
+
+/// Percent-encode a subject for use as a URL path segment
+fn encode(subject: &str) -> String {
+    subject
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+//! Replication and failover groups and per-database replication status, for disaster
+//! recovery reviews.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::provenance::Provenance;
+
+/// Written next to the catalog files; `catalog::load_snapshot` skips it
+pub const REPLICATION_FILE: &str = "replication.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ReplicationSnapshot {
+    /// Snapshot structure version, see [`crate::upgrade`]
+    #[serde(default = "crate::upgrade::unversioned")]
+    pub format_version: u32,
+    /// RFC 3339 time the snapshot was taken
+    pub captured_at: String,
+    /// The run that took it; `None` in documents from before it was recorded
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    pub groups: Vec<ReplicationGroup>,
+    pub databases: Vec<DatabaseReplication>,
+}
+
+/// One row of `SHOW REPLICATION GROUPS`, which also lists failover groups
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ReplicationGroup {
+    pub name: String,
+    /// `REPLICATION` or `FAILOVER`
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Account the group lives in, as `ORG.ACCOUNT`
+    pub account: String,
+    pub is_primary: bool,
+    /// Fully qualified name of the primary group
+    pub primary: Option<String>,
+    /// e.g. `DATABASES`, `ROLES`, `USERS`
+    pub object_types: Vec<String>,
+    pub allowed_accounts: Vec<String>,
+    pub replication_schedule: Option<String>,
+    /// `STARTED` or `SUSPENDED` on secondaries
+    pub secondary_state: Option<String>,
+    pub next_scheduled_refresh: Option<String>,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+    /// From `SHOW DATABASES IN REPLICATION GROUP`; empty for groups in other accounts
+    pub databases: Vec<String>,
+}
+
+/// One row of `SHOW REPLICATION DATABASES`: a primary or secondary copy of a database
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct DatabaseReplication {
+    pub name: String,
+    pub account: String,
+    pub region: Option<String>,
+    pub is_primary: bool,
+    /// Fully qualified name of the primary database
+    pub primary: Option<String>,
+    pub replication_allowed_to: Vec<String>,
+    pub failover_allowed_to: Vec<String>,
+    pub created_on: Option<String>,
+    pub comment: Option<String>,
+}
+
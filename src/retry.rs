@@ -0,0 +1,117 @@
+//! When and how often failed work is retried.
+//!
+//! Errors are sorted into classes from their message: a dropped connection or a 503 is
+//! worth retrying, a missing grant or a dropped database never succeeds on a second try.
+//! A [`RetryPolicy`] says which classes retry, how many times, and how the delay grows.
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::SnowflakeMapperError;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorClass {
+    /// Network failures, timeouts, throttling and 5xx responses
+    Transient,
+    /// Missing privileges, failed authentication and read-only violations
+    Permission,
+    /// Objects that don't exist (or aren't visible to the role)
+    NotFound,
+    /// Anything not recognized
+    Unknown,
+}
+
+/// Sort `error` into a class by its type and message
+pub fn classify(error: &anyhow::Error) -> ErrorClass {
+    if let Some(SnowflakeMapperError::ReadOnlyViolation(_)) = error.downcast_ref() {
+        return ErrorClass::Permission;
+    }
+    // The whole chain, since context layers often hide the underlying message
+    let message = format!("{:#}", error).to_lowercase();
+    let contains = |needles: &[&str]| needles.iter().any(|needle| message.contains(needle));
+
+    // Snowflake reports a missing object and a missing grant on it the same way (002003);
+    // a query missing from the fixtures won't appear on a retry either
+    if contains(&["does not exist", "not found", "002003", "no fixture recorded"]) {
+        ErrorClass::NotFound
+    } else if contains(&[
+        "insufficient privileges",
+        "not authorized",
+        "access denied",
+        "permission denied",
+        "incorrect username or password",
+        "authentication failed",
+        "authentication was denied",
+        "was not approved",
+        "forbidden",
+    ]) {
+        ErrorClass::Permission
+    } else if contains(&[
+        "timeout",
+        "timed out",
+        "connection",
+        "broken pipe",
+        "temporarily",
+        "unavailable",
+        "too many requests",
+        "session no longer exists",
+        "token has expired",
+    ]) || status_code().is_match(&message)
+    {
+        ErrorClass::Transient
+    } else {
+        ErrorClass::Unknown
+    }
+}
+
+/// HTTP statuses worth retrying
+fn status_code() -> &'static Regex {
+    static STATUS: OnceLock<Regex> = OnceLock::new();
+    STATUS.get_or_init(|| Regex::new(r"\b(429|50[0234])\b").expect("valid regex"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub delay: Duration,
+    /// Factor the delay grows by on each further retry; 1 keeps it constant
+    pub backoff: f64,
+    pub max_delay: Duration,
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            delay: Duration::from_secs(5),
+            backoff: 2.0,
+            max_delay: Duration::from_secs(60),
+            retry_on: vec![ErrorClass::Transient, ErrorClass::Unknown],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether to retry after `error`, with `retries` retries already made. A spent query
+    /// budget is never retried.
+    pub fn should_retry(&self, error: &anyhow::Error, retries: u32) -> bool {
+        if let Some(SnowflakeMapperError::BudgetExceeded(_)) = error.downcast_ref() {
+            return false;
+        }
+        retries < self.max_retries && self.retry_on.contains(&classify(error))
+    }
+
+    /// How long to wait before retry number `retry` (starting at 1)
+    pub fn delay_before(&self, retry: u32) -> Duration {
+        let factor = self.backoff.max(1.0).powi(retry.saturating_sub(1) as i32);
+        let cap = self.max_delay.max(self.delay).as_secs_f64();
+        Duration::from_secs_f64((self.delay.as_secs_f64() * factor).min(cap))
+    }
+}
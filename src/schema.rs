@@ -0,0 +1,63 @@
+//! JSON Schemas for the documents snowflake-mapper writes, generated from the serde types.
+//!
+//! Schemas describe output as written: every field is required (absent values are written as
+//! `null`) and objects don't allow unknown properties, so consumers can validate strictly.
+
+use clap::ValueEnum;
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema};
+
+use crate::account::AccountSnapshot;
+use crate::diff::ClassifiedChange;
+use crate::failures::FailureManifest;
+use crate::replication::ReplicationSnapshot;
+use crate::shares::ShareInventory;
+use crate::users::UserInventory;
+use crate::{TableInfo, WarehouseInfo};
+
+/// An output document with a schema
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Document {
+    /// A per-database catalog file, or the merged catalog on stdout: an array of tables
+    Catalog,
+    /// One table, as written per line with `--ndjson`
+    Table,
+    /// `warehouses.json`
+    Warehouses,
+    /// `account.json`
+    Account,
+    /// `users.json`
+    Users,
+    /// `shares.json`
+    Shares,
+    /// `replication.json`
+    Replication,
+    /// `failures.json`
+    Failures,
+    /// `diff --json`
+    Diff,
+}
+
+impl Document {
+    pub fn schema(self) -> Schema {
+        let mut schema = match self {
+            Document::Catalog => schema_for::<Vec<TableInfo>>(),
+            Document::Table => schema_for::<TableInfo>(),
+            Document::Warehouses => schema_for::<Vec<WarehouseInfo>>(),
+            Document::Account => schema_for::<AccountSnapshot>(),
+            Document::Users => schema_for::<UserInventory>(),
+            Document::Shares => schema_for::<ShareInventory>(),
+            Document::Replication => schema_for::<ReplicationSnapshot>(),
+            Document::Failures => schema_for::<FailureManifest>(),
+            Document::Diff => schema_for::<Vec<ClassifiedChange<'static>>>(),
+        };
+        let name = self.to_possible_value().expect("no skipped variants").get_name().to_string();
+        schema.insert("title".to_string(), format!("snowflake-mapper {}", name).into());
+        schema
+    }
+}
+
+/// Draft 2020-12 schema of `T` as serialized
+fn schema_for<T: JsonSchema>() -> Schema {
+    SchemaSettings::draft2020_12().for_serialize().into_generator().into_root_schema_for::<T>()
+}
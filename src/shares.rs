@@ -0,0 +1,60 @@
+//! Inbound and outbound shares, with the objects each one exposes, for auditing what data
+//! leaves the account and what is consumed from others.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::provenance::Provenance;
+
+/// Written next to the catalog files; `catalog::load_snapshot` skips it
+pub const SHARES_FILE: &str = "shares.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ShareInventory {
+    /// Snapshot structure version, see [`crate::upgrade`]
+    #[serde(default = "crate::upgrade::unversioned")]
+    pub format_version: u32,
+    /// RFC 3339 time the inventory was taken
+    pub captured_at: String,
+    /// The run that took it; `None` in documents from before it was recorded
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    pub shares: Vec<ShareInfo>,
+}
+
+/// One row of `SHOW SHARES`, plus the objects from `DESCRIBE SHARE`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ShareInfo {
+    /// Qualified with the provider account, e.g. `AB12345.SALES_SHARE`
+    pub name: String,
+    /// `INBOUND` or `OUTBOUND`
+    pub kind: String,
+    /// Database the share exposes (outbound) or was mounted as (inbound)
+    pub database_name: Option<String>,
+    /// Consumer accounts of an outbound share
+    pub consumers: Vec<String>,
+    /// Marketplace or private listing the share backs
+    pub listing: Option<String>,
+    pub created_on: Option<String>,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+    pub objects: Vec<SharedObject>,
+}
+
+/// One row of `DESCRIBE SHARE`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SharedObject {
+    /// `DATABASE`, `SCHEMA`, `TABLE`, `VIEW`, `FUNCTION`, ...
+    pub kind: String,
+    pub name: String,
+    pub shared_on: Option<String>,
+}
+
+impl ShareInfo {
+    pub fn is_outbound(&self) -> bool {
+        self.kind.eq_ignore_ascii_case("OUTBOUND")
+    }
+}
@@ -0,0 +1,72 @@
+//! Staleness report: tables with no recent DDL or reads, as candidates for deprecation.
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::{output, TableInfo};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleTable {
+    pub database: String,
+    pub schema: String,
+    pub table: String,
+    pub table_type: Option<String>,
+    pub last_altered: Option<String>,
+    pub last_accessed: Option<String>,
+    /// Days since the most recent of `last_altered` and `last_accessed` (or `created`)
+    pub idle_days: i64,
+}
+
+/// Tables whose latest activity is older than `older_than`, most idle first.
+///
+/// Activity is the later of `last_altered` and `last_accessed`, falling back to `created`.
+/// Tables without any timestamp are left out since their age is unknown.
+pub fn stale_tables(tables: &[TableInfo], older_than: Duration, now: DateTime<Utc>) -> Vec<StaleTable> {
+    let threshold = chrono::Duration::from_std(older_than).unwrap_or(chrono::Duration::MAX);
+    let mut stale: Vec<StaleTable> = tables
+        .iter()
+        .filter_map(|table| {
+            let activity = [&table.last_altered, &table.last_accessed]
+                .into_iter()
+                .filter_map(|timestamp| timestamp.as_deref().and_then(parse_timestamp))
+                .max()
+                .or_else(|| table.created.as_deref().and_then(parse_timestamp))?;
+            let idle = now.signed_duration_since(activity);
+            (idle > threshold).then(|| StaleTable {
+                database: table.database_name.clone(),
+                schema: table.schema_name.clone(),
+                table: table.table_name.clone(),
+                table_type: table.table_type.clone(),
+                last_altered: table.last_altered.clone(),
+                last_accessed: table.last_accessed.clone(),
+                idle_days: idle.num_days(),
+            })
+        })
+        .collect();
+    stale.sort_by(|a, b| b.idle_days.cmp(&a.idle_days).then_with(|| a.table.cmp(&b.table)));
+    stale
+}
+
+/// Parse an RFC 3339 timestamp, or the `YYYY-MM-DD HH:MI:SS.FFF +ZZZZ` form Snowflake prints by default
+pub fn parse_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f %z"))
+        .ok()
+}
+
+/// Render the report as an aligned text table
+pub fn render_text(stale: &[StaleTable]) -> String {
+    let rows: Vec<[String; 4]> = stale
+        .iter()
+        .map(|table| {
+            [
+                format!("{}.{}.{}", table.database, table.schema, table.table),
+                table.idle_days.to_string(),
+                table.last_altered.clone().unwrap_or_else(|| "-".to_string()),
+                table.last_accessed.clone().unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    output::render_table(["TABLE", "IDLE DAYS", "LAST ALTERED", "LAST ACCESSED"], &rows)
+}
@@ -0,0 +1,150 @@
+//! Summary statistics over a catalog.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{output, TableInfo};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogStats {
+    pub tables: usize,
+    pub views: usize,
+    pub columns: usize,
+    /// Share of columns with a non-empty comment, from 0 to 100
+    pub commented_columns_pct: f64,
+    /// Share of tables and views with a non-empty comment, from 0 to 100
+    pub commented_tables_pct: f64,
+    pub tables_per_schema: Vec<SchemaCount>,
+    pub columns_per_table: Distribution,
+    /// Base data types (without length or precision), most frequent first
+    pub data_types: Vec<TypeCount>,
+    /// Tables with the most columns, widest first
+    pub widest_tables: Vec<TableWidth>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaCount {
+    /// `database.schema`
+    pub schema: String,
+    pub tables: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Distribution {
+    pub min: usize,
+    pub median: usize,
+    pub p90: usize,
+    pub max: usize,
+    pub mean: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeCount {
+    pub data_type: String,
+    pub columns: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableWidth {
+    /// `database.schema.table`
+    pub table: String,
+    pub columns: usize,
+}
+
+/// Aggregate the catalog, keeping the `top` widest tables
+pub fn catalog_stats(tables: &[TableInfo], top: usize) -> CatalogStats {
+    let columns: usize = tables.iter().map(|t| t.columns.len()).sum();
+    let commented_columns =
+        tables.iter().flat_map(|t| &t.columns).filter(|c| c.comment.as_deref().is_some_and(|c| !c.is_empty())).count();
+    let commented_tables = tables.iter().filter(|t| t.comment.as_deref().is_some_and(|c| !c.is_empty())).count();
+
+    let mut per_schema: BTreeMap<String, usize> = BTreeMap::new();
+    for table in tables {
+        *per_schema.entry(format!("{}.{}", table.database_name, table.schema_name)).or_default() += 1;
+    }
+
+    let mut type_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for column in tables.iter().flat_map(|t| &t.columns) {
+        *type_counts.entry(column.data_type.to_uppercase()).or_default() += 1;
+    }
+    let mut data_types: Vec<TypeCount> =
+        type_counts.into_iter().map(|(data_type, columns)| TypeCount { data_type, columns }).collect();
+    data_types.sort_by_key(|t| std::cmp::Reverse(t.columns));
+
+    let mut widths: Vec<TableWidth> = tables
+        .iter()
+        .map(|t| TableWidth {
+            table: t.qualified_name(),
+            columns: t.columns.len(),
+        })
+        .collect();
+    widths.sort_by_key(|t| std::cmp::Reverse(t.columns));
+
+    let mut counts: Vec<usize> = tables.iter().map(|t| t.columns.len()).collect();
+    counts.sort_unstable();
+
+    CatalogStats {
+        tables: tables.iter().filter(|t| t.is_base_table()).count(),
+        views: tables.iter().filter(|t| !t.is_base_table()).count(),
+        columns,
+        commented_columns_pct: percentage(commented_columns, columns),
+        commented_tables_pct: percentage(commented_tables, tables.len()),
+        tables_per_schema: per_schema.into_iter().map(|(schema, tables)| SchemaCount { schema, tables }).collect(),
+        columns_per_table: Distribution {
+            min: counts.first().copied().unwrap_or(0),
+            median: quantile(&counts, 0.5),
+            p90: quantile(&counts, 0.9),
+            max: counts.last().copied().unwrap_or(0),
+            mean: if counts.is_empty() { 0.0 } else { columns as f64 / counts.len() as f64 },
+        },
+        data_types,
+        widest_tables: widths.into_iter().take(top).collect(),
+    }
+}
+
+pub fn render_text(stats: &CatalogStats) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{} tables, {} views, {} columns\n{:.1}% of tables and {:.1}% of columns have a comment\n",
+        stats.tables, stats.views, stats.columns, stats.commented_tables_pct, stats.commented_columns_pct
+    ));
+    let d = &stats.columns_per_table;
+    out.push_str(&format!(
+        "columns per table: min {}, median {}, p90 {}, max {}, mean {:.1}\n\n",
+        d.min, d.median, d.p90, d.max, d.mean
+    ));
+
+    let rows: Vec<[String; 2]> =
+        stats.tables_per_schema.iter().map(|s| [s.schema.clone(), s.tables.to_string()]).collect();
+    out.push_str(&output::render_table(["SCHEMA", "TABLES"], &rows));
+    out.push('\n');
+
+    let rows: Vec<[String; 3]> = stats
+        .data_types
+        .iter()
+        .map(|t| [t.data_type.clone(), t.columns.to_string(), format!("{:.1}%", percentage(t.columns, stats.columns))])
+        .collect();
+    out.push_str(&output::render_table(["DATA TYPE", "COLUMNS", "SHARE"], &rows));
+    out.push('\n');
+
+    let rows: Vec<[String; 2]> = stats.widest_tables.iter().map(|t| [t.table.clone(), t.columns.to_string()]).collect();
+    out.push_str(&output::render_table(["WIDEST TABLE", "COLUMNS"], &rows));
+    out
+}
+
+fn percentage(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+/// Nearest-rank quantile of sorted values
+fn quantile(sorted: &[usize], q: f64) -> usize {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
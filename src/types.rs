@@ -0,0 +1,283 @@
+//! One interpretation of column types, shared by every exporter.
+//!
+//! [`SnowflakeType`] is parsed from the information_schema fields of a column, or from type
+//! text such as `NUMBER(10,2)`, folding Snowflake's aliases into the type they stand for.
+//! Formatting writes the canonical Snowflake spelling, which parses back to the same value,
+//! except for [`SnowflakeType::TimeTz`]. Other warehouses whose names mean something else,
+//! such as PostgreSQL's `timestamp with time zone`, are mapped by their backends.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{ColumnInfo, TimeZoneHandling};
+
+/// Digits of fractional seconds Snowflake keeps when a time or timestamp doesn't say
+pub const DEFAULT_DATETIME_PRECISION: u32 = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnowflakeType {
+    /// NUMBER and its aliases; `NUMBER(38,0)` when precision and scale aren't given
+    Number { precision: u32, scale: u32 },
+    /// FLOAT, DOUBLE, REAL and their aliases, all 64-bit
+    Float,
+    /// VARCHAR, TEXT, STRING and CHAR; `None` is the maximum length
+    Varchar { length: Option<u32> },
+    Binary { length: Option<u32> },
+    Boolean,
+    Date,
+    /// `precision` is the digits of fractional seconds for this and the timestamps
+    Time { precision: u32 },
+    /// PostgreSQL's TIME WITH TIME ZONE. Snowflake has no such type, so it's written as a
+    /// TIME, which drops the offset.
+    TimeTz { precision: u32 },
+    TimestampNtz { precision: u32 },
+    TimestampLtz { precision: u32 },
+    TimestampTz { precision: u32 },
+    /// A bare TIMESTAMP, mapped by the session's TIMESTAMP_TYPE_MAPPING
+    Timestamp { precision: u32 },
+    Variant,
+    Object,
+    Array,
+    Geography,
+    Geometry,
+    /// Anything else, such as VECTOR or types of other warehouses, as reported
+    Other { name: String },
+}
+
+impl SnowflakeType {
+    /// Parse type text, e.g. `VARCHAR(255)`, `INT` or `TIMESTAMP_TZ(3)`. Unknown types and
+    /// malformed arguments give [`SnowflakeType::Other`].
+    pub fn parse(text: &str) -> Self {
+        let other = || SnowflakeType::Other { name: text.trim().to_string() };
+        let upper = text.trim().to_uppercase();
+        let (name, args) = match (upper.find('('), upper.rfind(')')) {
+            (Some(open), Some(close)) if open < close => {
+                let args: Option<Vec<u32>> = upper[open + 1..close].split(',').map(|arg| arg.trim().parse().ok()).collect();
+                let Some(args) = args else {
+                    return other();
+                };
+                // PostgreSQL puts the precision before the zone: `TIMESTAMP(3) WITH TIME ZONE`
+                (format!("{} {}", &upper[..open], &upper[close + 1..]), args)
+            }
+            _ => (upper.clone(), Vec::new()),
+        };
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        let (first, second) = (args.first().copied(), args.get(1).copied());
+        let precision = first.unwrap_or(DEFAULT_DATETIME_PRECISION);
+
+        match (name.as_str(), args.len()) {
+            ("NUMBER" | "DECIMAL" | "DEC" | "NUMERIC", 0..=2) => SnowflakeType::Number { precision: first.unwrap_or(38), scale: second.unwrap_or(0) },
+            ("INT" | "INTEGER" | "BIGINT" | "SMALLINT" | "TINYINT" | "BYTEINT", 0) => SnowflakeType::Number { precision: 38, scale: 0 },
+            ("FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" | "DOUBLE PRECISION" | "REAL", 0) => SnowflakeType::Float,
+            ("VARCHAR" | "TEXT" | "STRING" | "NVARCHAR" | "NVARCHAR2" | "CHAR VARYING" | "NCHAR VARYING" | "CHARACTER VARYING", 0..=1) => {
+                SnowflakeType::Varchar { length: first }
+            }
+            ("CHAR" | "CHARACTER" | "NCHAR", 0..=1) => SnowflakeType::Varchar { length: Some(first.unwrap_or(1)) },
+            ("BINARY" | "VARBINARY" | "BYTEA", 0..=1) => SnowflakeType::Binary { length: first },
+            ("BOOLEAN", 0) => SnowflakeType::Boolean,
+            ("DATE", 0) => SnowflakeType::Date,
+            ("TIME" | "TIME WITHOUT TIME ZONE", 0..=1) => SnowflakeType::Time { precision },
+            ("TIME WITH TIME ZONE" | "TIMETZ", 0..=1) => SnowflakeType::TimeTz { precision },
+            ("TIMESTAMP_NTZ" | "TIMESTAMPNTZ" | "DATETIME" | "TIMESTAMP WITHOUT TIME ZONE", 0..=1) => SnowflakeType::TimestampNtz { precision },
+            ("TIMESTAMP_LTZ" | "TIMESTAMPLTZ" | "TIMESTAMP WITH LOCAL TIME ZONE", 0..=1) => SnowflakeType::TimestampLtz { precision },
+            ("TIMESTAMP_TZ" | "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE", 0..=1) => SnowflakeType::TimestampTz { precision },
+            ("TIMESTAMP", 0..=1) => SnowflakeType::Timestamp { precision },
+            ("VARIANT" | "JSON" | "JSONB", 0) => SnowflakeType::Variant,
+            ("OBJECT", 0) => SnowflakeType::Object,
+            ("ARRAY", 0) => SnowflakeType::Array,
+            ("GEOGRAPHY", 0) => SnowflakeType::Geography,
+            ("GEOMETRY", 0) => SnowflakeType::Geometry,
+            _ => other(),
+        }
+    }
+
+    /// The type of a column, from its `data_type` and length, precision and scale fields.
+    /// PostgreSQL reports its fixed-width integers by name with binary precision; they
+    /// become the decimal digits every value fits in.
+    pub fn from_column(column: &ColumnInfo) -> Self {
+        let field = |value: Option<i32>| value.and_then(|v| u32::try_from(v).ok());
+        match column.data_type.to_uppercase().as_str() {
+            "SMALLINT" | "INT2" => return SnowflakeType::Number { precision: 4, scale: 0 },
+            "INTEGER" | "INT4" => return SnowflakeType::Number { precision: 9, scale: 0 },
+            "BIGINT" | "INT8" => return SnowflakeType::Number { precision: 18, scale: 0 },
+            _ => {}
+        }
+        let datetime = |precision: u32| field(column.datetime_precision).unwrap_or(precision);
+        match SnowflakeType::parse(&column.data_type) {
+            SnowflakeType::Number { precision, scale } => match field(column.numeric_precision) {
+                Some(precision) => SnowflakeType::Number { precision, scale: field(column.numeric_scale).unwrap_or(0) },
+                None => SnowflakeType::Number { precision, scale },
+            },
+            SnowflakeType::Varchar { length } => SnowflakeType::Varchar { length: field(column.character_maximum_length).or(length) },
+            SnowflakeType::Binary { length } => SnowflakeType::Binary { length: field(column.character_maximum_length).or(length) },
+            SnowflakeType::Time { precision } => SnowflakeType::Time { precision: datetime(precision) },
+            SnowflakeType::TimeTz { precision } => SnowflakeType::TimeTz { precision: datetime(precision) },
+            SnowflakeType::TimestampNtz { precision } => SnowflakeType::TimestampNtz { precision: datetime(precision) },
+            SnowflakeType::TimestampLtz { precision } => SnowflakeType::TimestampLtz { precision: datetime(precision) },
+            SnowflakeType::TimestampTz { precision } => SnowflakeType::TimestampTz { precision: datetime(precision) },
+            SnowflakeType::Timestamp { precision } => SnowflakeType::Timestamp { precision: datetime(precision) },
+            parsed => parsed,
+        }
+    }
+
+    /// Digits of fractional seconds of times and timestamps
+    pub fn datetime_precision(&self) -> Option<u32> {
+        match self {
+            SnowflakeType::Time { precision }
+            | SnowflakeType::TimeTz { precision }
+            | SnowflakeType::TimestampNtz { precision }
+            | SnowflakeType::TimestampLtz { precision }
+            | SnowflakeType::TimestampTz { precision }
+            | SnowflakeType::Timestamp { precision } => Some(*precision),
+            _ => None,
+        }
+    }
+
+    /// The same type with `precision` digits of fractional seconds, if it's a time or timestamp
+    pub fn with_datetime_precision(self, precision: u32) -> Self {
+        match self {
+            SnowflakeType::Time { .. } => SnowflakeType::Time { precision },
+            SnowflakeType::TimeTz { .. } => SnowflakeType::TimeTz { precision },
+            SnowflakeType::TimestampNtz { .. } => SnowflakeType::TimestampNtz { precision },
+            SnowflakeType::TimestampLtz { .. } => SnowflakeType::TimestampLtz { precision },
+            SnowflakeType::TimestampTz { .. } => SnowflakeType::TimestampTz { precision },
+            SnowflakeType::Timestamp { .. } => SnowflakeType::Timestamp { precision },
+            other => other,
+        }
+    }
+
+    /// How values of times and timestamps relate to time zones; `None` for other types, and
+    /// for a bare TIMESTAMP whose mapping depends on the session
+    pub fn time_zone(&self) -> Option<TimeZoneHandling> {
+        match self {
+            SnowflakeType::Time { .. } | SnowflakeType::TimestampNtz { .. } => Some(TimeZoneHandling::Naive),
+            SnowflakeType::TimestampLtz { .. } => Some(TimeZoneHandling::Local),
+            SnowflakeType::TimestampTz { .. } | SnowflakeType::TimeTz { .. } => Some(TimeZoneHandling::Offset),
+            _ => None,
+        }
+    }
+
+    /// VARIANT, OBJECT or ARRAY
+    pub fn is_semi_structured(&self) -> bool {
+        matches!(self, SnowflakeType::Variant | SnowflakeType::Object | SnowflakeType::Array)
+    }
+}
+
+impl fmt::Display for SnowflakeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, precision) = match self {
+            SnowflakeType::Number { precision, scale } => return write!(f, "NUMBER({},{})", precision, scale),
+            SnowflakeType::Varchar { length: Some(length) } => return write!(f, "VARCHAR({})", length),
+            SnowflakeType::Varchar { length: None } => return f.write_str("VARCHAR"),
+            SnowflakeType::Binary { length: Some(length) } => return write!(f, "BINARY({})", length),
+            SnowflakeType::Binary { length: None } => return f.write_str("BINARY"),
+            SnowflakeType::Other { name } => return f.write_str(name),
+            SnowflakeType::Float => ("FLOAT", None),
+            SnowflakeType::Boolean => ("BOOLEAN", None),
+            SnowflakeType::Date => ("DATE", None),
+            SnowflakeType::Time { precision } | SnowflakeType::TimeTz { precision } => ("TIME", Some(precision)),
+            SnowflakeType::TimestampNtz { precision } => ("TIMESTAMP_NTZ", Some(precision)),
+            SnowflakeType::TimestampLtz { precision } => ("TIMESTAMP_LTZ", Some(precision)),
+            SnowflakeType::TimestampTz { precision } => ("TIMESTAMP_TZ", Some(precision)),
+            SnowflakeType::Timestamp { precision } => ("TIMESTAMP", Some(precision)),
+            SnowflakeType::Variant => ("VARIANT", None),
+            SnowflakeType::Object => ("OBJECT", None),
+            SnowflakeType::Array => ("ARRAY", None),
+            SnowflakeType::Geography => ("GEOGRAPHY", None),
+            SnowflakeType::Geometry => ("GEOMETRY", None),
+        };
+        match precision {
+            Some(precision) if *precision != DEFAULT_DATETIME_PRECISION => write!(f, "{}({})", name, precision),
+            _ => f.write_str(name),
+        }
+    }
+}
+
+impl std::str::FromStr for SnowflakeType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(SnowflakeType::parse(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_parses_back_to_the_same_type() {
+        let types = [
+            SnowflakeType::Number { precision: 38, scale: 0 },
+            SnowflakeType::Number { precision: 10, scale: 2 },
+            SnowflakeType::Float,
+            SnowflakeType::Varchar { length: None },
+            SnowflakeType::Varchar { length: Some(255) },
+            SnowflakeType::Binary { length: None },
+            SnowflakeType::Binary { length: Some(16) },
+            SnowflakeType::Boolean,
+            SnowflakeType::Date,
+            SnowflakeType::Time { precision: 9 },
+            SnowflakeType::Time { precision: 3 },
+            SnowflakeType::TimestampNtz { precision: 9 },
+            SnowflakeType::TimestampNtz { precision: 0 },
+            SnowflakeType::TimestampLtz { precision: 6 },
+            SnowflakeType::TimestampTz { precision: 3 },
+            SnowflakeType::Timestamp { precision: 9 },
+            SnowflakeType::Timestamp { precision: 6 },
+            SnowflakeType::Variant,
+            SnowflakeType::Object,
+            SnowflakeType::Array,
+            SnowflakeType::Geography,
+            SnowflakeType::Geometry,
+            SnowflakeType::Other { name: "VECTOR(FLOAT, 256)".to_string() },
+        ];
+        for t in types {
+            assert_eq!(SnowflakeType::parse(&t.to_string()), t, "{}", t);
+        }
+    }
+
+    #[test]
+    fn times_with_a_zone_are_written_as_times() {
+        assert_eq!(SnowflakeType::TimeTz { precision: 9 }.to_string(), "TIME");
+        assert_eq!(SnowflakeType::TimeTz { precision: 6 }.to_string(), "TIME(6)");
+    }
+
+    #[test]
+    fn aliases_fold_into_the_type_they_stand_for() {
+        let cases = [
+            ("int", SnowflakeType::Number { precision: 38, scale: 0 }),
+            ("BIGINT", SnowflakeType::Number { precision: 38, scale: 0 }),
+            ("DECIMAL(10, 2)", SnowflakeType::Number { precision: 10, scale: 2 }),
+            ("NUMERIC", SnowflakeType::Number { precision: 38, scale: 0 }),
+            ("DOUBLE PRECISION", SnowflakeType::Float),
+            ("REAL", SnowflakeType::Float),
+            ("TEXT", SnowflakeType::Varchar { length: None }),
+            ("CHARACTER VARYING(40)", SnowflakeType::Varchar { length: Some(40) }),
+            ("CHAR", SnowflakeType::Varchar { length: Some(1) }),
+            ("VARBINARY(8)", SnowflakeType::Binary { length: Some(8) }),
+            ("DATETIME", SnowflakeType::TimestampNtz { precision: 9 }),
+            ("TIMESTAMP(3) WITH TIME ZONE", SnowflakeType::TimestampTz { precision: 3 }),
+            ("TIMESTAMPLTZ", SnowflakeType::TimestampLtz { precision: 9 }),
+            ("TIMESTAMP WITHOUT TIME ZONE", SnowflakeType::TimestampNtz { precision: 9 }),
+            ("time(6) with time zone", SnowflakeType::TimeTz { precision: 6 }),
+            ("JSONB", SnowflakeType::Variant),
+            ("VARCHAR(abc)", SnowflakeType::Other { name: "VARCHAR(abc)".to_string() }),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(SnowflakeType::parse(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn time_zone_handling_follows_the_parsed_type() {
+        assert_eq!(TimeZoneHandling::of("TIME WITH TIME ZONE"), Some(TimeZoneHandling::Offset));
+        assert_eq!(TimeZoneHandling::of("TIMESTAMP WITH LOCAL TIME ZONE"), Some(TimeZoneHandling::Local));
+        assert_eq!(TimeZoneHandling::of("timestamp with time zone"), Some(TimeZoneHandling::Offset));
+        assert_eq!(TimeZoneHandling::of("DATETIME"), Some(TimeZoneHandling::Naive));
+        assert_eq!(TimeZoneHandling::of("TIMESTAMP"), None);
+        assert_eq!(TimeZoneHandling::of("VARCHAR"), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! Snapshot format versions, and upgrading snapshots written by older releases.
+//!
+//! Every record in a catalog file, and every account-level file, carries a
+//! `format_version`. Records without one predate versioning and are version 1. Loading a
+//! snapshot upgrades old records in memory, so `diff` and `changelog` can compare
+//! snapshots written by different releases; the `upgrade` subcommand rewrites them on disk.
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::Path;
+
+use crate::account::{AccountSnapshot, ACCOUNT_FILE};
+use crate::catalog::WAREHOUSES_FILE;
+use crate::backends::postgres;
+use crate::failures::{FailureManifest, FAILURES_FILE};
+use crate::output;
+use crate::replication::{ReplicationSnapshot, REPLICATION_FILE};
+use crate::shares::{ShareInventory, SHARES_FILE};
+use crate::users::{UserInventory, USERS_FILE};
+use crate::{ColumnInfo, SnowflakeMapperError, TableInfo, WarehouseInfo};
+
+/// Version of the snapshot structure this release writes
+pub const FORMAT_VERSION: u32 = 4;
+
+/// Version of records written before `format_version` was stamped
+pub fn unversioned() -> u32 {
+    1
+}
+
+/// Migrations between consecutive versions; entry `n` upgrades a record from version `n + 1`
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[
+    // 1 -> 2: only the stamp itself; fields added since are filled with their defaults on load
+    |_| {},
+    // 2 -> 3: time zone handling of time and timestamp columns, derived from their type.
+    // Fractional second digits weren't captured and stay unknown. PostgreSQL's names are
+    // read the way its backend reads them.
+    |record| {
+        let Some(Value::Array(columns)) = record.get_mut("columns") else {
+            return;
+        };
+        for column in columns.iter_mut().filter_map(Value::as_object_mut) {
+            let time_zone = column.get("data_type").and_then(Value::as_str).and_then(postgres::time_zone);
+            if let Some(time_zone) = time_zone {
+                column.entry("time_zone").or_insert_with(|| serde_json::to_value(time_zone).expect("time zones serialize"));
+            }
+        }
+    },
+    // 3 -> 4: the parsed type of each column, next to the raw fields, again reading
+    // PostgreSQL's names the way its backend does
+    |record| {
+        let Some(Value::Array(columns)) = record.get_mut("columns") else {
+            return;
+        };
+        for column in columns.iter_mut() {
+            // Columns that don't parse are left for loading to report
+            let Ok(info) = serde_json::from_value::<ColumnInfo>(column.clone()) else {
+                continue;
+            };
+            if let Some(column) = column.as_object_mut() {
+                column.insert("snowflake_type".to_string(), serde_json::to_value(postgres::snowflake_type(&info)).expect("types serialize"));
+            }
+        }
+    },
+];
+
+/// Version a record was written with. Fails for records written by a newer release, whose
+/// structure this one can't know.
+fn record_version(record: &Map<String, Value>, source: &Path) -> Result<u32> {
+    let version = match record.get("format_version") {
+        None => unversioned(),
+        Some(version) => version.as_u64().and_then(|v| u32::try_from(v).ok()).filter(|v| *v >= 1).ok_or_else(|| {
+            SnowflakeMapperError::SnapshotError(format!("Invalid format_version {} in {}", version, source.display()))
+        })?,
+    };
+    if version > FORMAT_VERSION {
+        return Err(SnowflakeMapperError::SnapshotError(format!(
+            "{} was written with format version {}, newer than this release supports ({}); upgrade snowflake-mapper",
+            source.display(),
+            version,
+            FORMAT_VERSION
+        ))
+        .into());
+    }
+    Ok(version)
+}
+
+/// Bring one record up to [`FORMAT_VERSION`]
+pub fn upgrade_record(mut value: Value, source: &Path) -> Result<Value> {
+    let Some(record) = value.as_object_mut() else {
+        return Ok(value);
+    };
+    let version = record_version(record, source)?;
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(record);
+    }
+    record.insert("format_version".to_string(), Value::from(FORMAT_VERSION));
+    Ok(value)
+}
+
+/// Rewrite one snapshot file at the current version, if any of its records is older. Files
+/// are re-serialized through their current types, so they gain every current field. Returns
+/// whether the file was out of date; with `check`, nothing is written.
+pub fn upgrade_file(path: &Path, check: bool) -> Result<bool> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Failed to read {}: {}", path.display(), e)))?;
+    let parse_error = |e: serde_json::Error| SnowflakeMapperError::SnapshotError(format!("Failed to parse {}: {}", path.display(), e));
+    let ndjson = path.extension().is_some_and(|ext| ext == "ndjson");
+
+    let (records, is_array): (Vec<Value>, bool) = if ndjson {
+        let lines = contents.lines().filter(|line| !line.trim().is_empty());
+        (lines.map(serde_json::from_str).collect::<Result<_, _>>().map_err(parse_error)?, true)
+    } else {
+        match serde_json::from_str(&contents).map_err(parse_error)? {
+            Value::Array(records) => (records, true),
+            record => (vec![record], false),
+        }
+    };
+    let mut outdated = false;
+    for record in records.iter().filter_map(Value::as_object) {
+        outdated |= record_version(record, path)? < FORMAT_VERSION;
+    }
+    if !outdated || check {
+        return Ok(outdated);
+    }
+
+    let records: Vec<Value> = records.into_iter().map(|record| upgrade_record(record, path)).collect::<Result<_>>()?;
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let single = || records.first().cloned().unwrap_or_default();
+    let upgraded = match file_name {
+        WAREHOUSES_FILE => current::<Vec<WarehouseInfo>>(Value::Array(records), path)?,
+        ACCOUNT_FILE => current::<AccountSnapshot>(single(), path)?,
+        USERS_FILE => current::<UserInventory>(single(), path)?,
+        SHARES_FILE => current::<ShareInventory>(single(), path)?,
+        REPLICATION_FILE => current::<ReplicationSnapshot>(single(), path)?,
+        FAILURES_FILE => current::<FailureManifest>(single(), path)?,
+        _ if ndjson => {
+            let mut lines = String::new();
+            for record in records {
+                let table: TableInfo = serde_json::from_value(record).map_err(parse_error)?;
+                lines.push_str(&serde_json::to_string(&table)?);
+                lines.push('\n');
+            }
+            lines
+        }
+        _ if is_array => current::<Vec<TableInfo>>(Value::Array(records), path)?,
+        _ => current::<TableInfo>(single(), path)?,
+    };
+    output::write_atomically(path, |file| file.write_all(upgraded.as_bytes()))?;
+    Ok(true)
+}
+
+/// Re-serialize an upgraded document through its current type, filling fields added since
+/// it was written
+fn current<T: DeserializeOwned + Serialize>(value: Value, source: &Path) -> Result<String> {
+    let document: T = serde_json::from_value(value)
+        .map_err(|e| SnowflakeMapperError::SnapshotError(format!("Failed to parse {}: {}", source.display(), e)))?;
+    Ok(serde_json::to_string_pretty(&document)?)
+}
@@ -0,0 +1,63 @@
+//! Users and service accounts, with the defaults and sign-in activity governance reviews ask for.
+
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::provenance::Provenance;
+
+/// Written next to the catalog files; `catalog::load_snapshot` skips it
+pub const USERS_FILE: &str = "users.json";
+
+/// Where user metadata is read from
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum UserSource {
+    /// `SHOW USERS`: current, but needs MANAGE GRANTS or ownership of the users
+    #[default]
+    Show,
+    /// `SNOWFLAKE.ACCOUNT_USAGE.USERS`: needs the SNOWFLAKE database privileges and lags up to 2 hours
+    AccountUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct UserInventory {
+    /// Snapshot structure version, see [`crate::upgrade`]
+    #[serde(default = "crate::upgrade::unversioned")]
+    pub format_version: u32,
+    /// RFC 3339 time the inventory was taken
+    pub captured_at: String,
+    /// The run that took it; `None` in documents from before it was recorded
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    pub source: UserSource,
+    pub users: Vec<UserInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct UserInfo {
+    pub name: String,
+    pub login_name: Option<String>,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    /// `PERSON`, `SERVICE` or `LEGACY_SERVICE`; `None` when unset or not reported
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub disabled: bool,
+    pub default_role: Option<String>,
+    pub default_warehouse: Option<String>,
+    pub has_password: bool,
+    pub has_rsa_public_key: bool,
+    pub created_on: Option<String>,
+    pub last_success_login: Option<String>,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl UserInfo {
+    pub fn is_service(&self) -> bool {
+        self.type_.as_deref().is_some_and(|t| t.eq_ignore_ascii_case("SERVICE") || t.eq_ignore_ascii_case("LEGACY_SERVICE"))
+    }
+}
@@ -0,0 +1,126 @@
+//! Shape inference for semi-structured (VARIANT / OBJECT / ARRAY) columns.
+//!
+//! Sampled values are folded into a single shape: objects merge their fields (a field
+//! missing from some samples becomes optional), arrays merge their items, integers widen to
+//! numbers, and anything else that disagrees becomes a union.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::types::SnowflakeType;
+
+/// Inferred structure of a semi-structured column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VariantShape {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array {
+        /// `None` when every sampled array was empty
+        items: Option<Box<VariantShape>>,
+    },
+    Object {
+        fields: BTreeMap<String, VariantField>,
+    },
+    Union {
+        variants: Vec<VariantShape>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(extend("unevaluatedProperties" = false))]
+pub struct VariantField {
+    #[serde(flatten)]
+    pub shape: VariantShape,
+    /// Whether the field appeared in every sampled object
+    pub required: bool,
+}
+
+/// Whether a Snowflake data type holds semi-structured values
+pub fn is_semi_structured(data_type: &str) -> bool {
+    SnowflakeType::parse(data_type).is_semi_structured()
+}
+
+/// Fold sampled values into one shape; `None` when there are no samples
+pub fn infer<'a>(samples: impl IntoIterator<Item = &'a Value>) -> Option<VariantShape> {
+    samples.into_iter().map(shape_of).reduce(merge)
+}
+
+fn shape_of(value: &Value) -> VariantShape {
+    match value {
+        Value::Null => VariantShape::Null,
+        Value::Bool(_) => VariantShape::Boolean,
+        Value::Number(n) if n.is_i64() || n.is_u64() => VariantShape::Integer,
+        Value::Number(_) => VariantShape::Number,
+        Value::String(_) => VariantShape::String,
+        Value::Array(items) => VariantShape::Array { items: infer(items).map(Box::new) },
+        Value::Object(fields) => VariantShape::Object {
+            fields: fields
+                .iter()
+                .map(|(name, value)| (name.clone(), VariantField { shape: shape_of(value), required: true }))
+                .collect(),
+        },
+    }
+}
+
+fn merge(left: VariantShape, right: VariantShape) -> VariantShape {
+    use VariantShape::*;
+    match (left, right) {
+        (left, right) if left == right => left,
+        (Integer, Number) | (Number, Integer) => Number,
+        (Array { items: left }, Array { items: right }) => Array {
+            items: match (left, right) {
+                (Some(left), Some(right)) => Some(Box::new(merge(*left, *right))),
+                (items, None) | (None, items) => items,
+            },
+        },
+        (Object { fields: left }, Object { fields: mut right }) => {
+            let mut fields: BTreeMap<_, _> = left
+                .into_iter()
+                .map(|(name, field)| {
+                    let merged = match right.remove(&name) {
+                        Some(other) => VariantField {
+                            shape: merge(field.shape, other.shape),
+                            required: field.required && other.required,
+                        },
+                        None => VariantField { required: false, ..field },
+                    };
+                    (name, merged)
+                })
+                .collect();
+            fields.extend(right.into_iter().map(|(name, field)| (name, VariantField { required: false, ..field })));
+            Object { fields }
+        }
+        (Union { variants }, other) | (other, Union { variants }) => add_variant(variants, other),
+        (left, right) => Union { variants: vec![left, right] },
+    }
+}
+
+/// Merge `variant` into the union member of the same kind, or append it
+fn add_variant(mut variants: Vec<VariantShape>, variant: VariantShape) -> VariantShape {
+    if let VariantShape::Union { variants: nested } = variant {
+        return nested.into_iter().fold(VariantShape::Union { variants }, |union, variant| match union {
+            VariantShape::Union { variants } => add_variant(variants, variant),
+            other => merge(other, variant),
+        });
+    }
+    match variants.iter().position(|existing| same_kind(existing, &variant)) {
+        Some(index) => {
+            let existing = variants.remove(index);
+            variants.insert(index, merge(existing, variant));
+        }
+        None => variants.push(variant),
+    }
+    VariantShape::Union { variants }
+}
+
+fn same_kind(left: &VariantShape, right: &VariantShape) -> bool {
+    use VariantShape::*;
+    matches!((left, right), (Integer | Number, Integer | Number))
+        || std::mem::discriminant(left) == std::mem::discriminant(right)
+}
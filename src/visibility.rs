@@ -0,0 +1,166 @@
+//! Objects in the account the current role can't see, for checking that a catalog is complete.
+//!
+//! SNOWFLAKE.ACCOUNT_USAGE lists every database, schema and table regardless of grants, but
+//! lags by up to three hours; SHOW and information_schema only return what the role has
+//! privileges on. Objects in the first but not the second are hidden from the mapper.
+//! Temporary tables and INFORMATION_SCHEMA are left out on both sides, and imported
+//! databases, which ACCOUNT_USAGE doesn't cover, are ignored.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{output, TableInfo};
+
+/// A database, schema or table; `schema` and `table` are `None` for databases and schemas
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccountObject {
+    pub database: String,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    /// Owning role
+    pub owner: Option<String>,
+}
+
+impl AccountObject {
+    fn key(&self) -> (&str, Option<&str>, Option<&str>) {
+        (&self.database, self.schema.as_deref(), self.table.as_deref())
+    }
+
+    fn kind(&self) -> ObjectKind {
+        match (&self.schema, &self.table) {
+            (None, _) => ObjectKind::Database,
+            (Some(_), None) => ObjectKind::Schema,
+            (Some(_), Some(_)) => ObjectKind::Table,
+        }
+    }
+
+    fn qualified_name(&self) -> String {
+        [Some(self.database.as_str()), self.schema.as_deref(), self.table.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(".")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectKind {
+    Database,
+    Schema,
+    Table,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HiddenObject {
+    pub kind: ObjectKind,
+    /// `database`, `database.schema` or `database.schema.table`
+    pub name: String,
+    /// Owning role, which can grant access to it
+    pub owner: Option<String>,
+    /// Tables ACCOUNT_USAGE lists within a hidden database or schema
+    pub tables: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VisibilityReport {
+    /// Objects the role can't see. The schemas and tables of a hidden database, and the
+    /// tables of a hidden schema, aren't listed separately.
+    pub hidden: Vec<HiddenObject>,
+    /// Visible schemas and tables ACCOUNT_USAGE doesn't list yet, usually created within its
+    /// latency
+    pub unlisted: Vec<String>,
+    /// Objects listed by ACCOUNT_USAGE, and visible to the role
+    pub account_usage_objects: usize,
+    pub visible_objects: usize,
+}
+
+/// Compare the objects ACCOUNT_USAGE lists with those `visible` to the role. With
+/// `tables_only`, `visible` was derived from a catalog, which can't tell empty databases and
+/// schemas apart from hidden ones, so those aren't reported.
+pub fn compare(account_usage: &[AccountObject], visible: &[AccountObject], tables_only: bool) -> VisibilityReport {
+    let seen: HashSet<_> = visible.iter().map(AccountObject::key).collect();
+    let mut tables: HashMap<(&str, Option<&str>), usize> = HashMap::new();
+    for object in account_usage.iter().filter(|object| object.kind() == ObjectKind::Table) {
+        *tables.entry((&object.database, None)).or_default() += 1;
+        *tables.entry((&object.database, object.schema.as_deref())).or_default() += 1;
+    }
+
+    let mut sorted: Vec<&AccountObject> = account_usage.iter().collect();
+    sorted.sort_by(|a, b| a.key().cmp(&b.key()));
+    let mut hidden = Vec::new();
+    let mut hidden_parents: HashSet<(&str, Option<&str>)> = HashSet::new();
+    for object in sorted {
+        let kind = object.kind();
+        let database = (object.database.as_str(), None);
+        let schema = (object.database.as_str(), object.schema.as_deref());
+        if hidden_parents.contains(&database) || (kind == ObjectKind::Table && hidden_parents.contains(&schema)) {
+            continue;
+        }
+        if seen.contains(&object.key()) {
+            continue;
+        }
+        let contained = match kind {
+            ObjectKind::Database => tables.get(&database).copied().unwrap_or(0),
+            ObjectKind::Schema => tables.get(&schema).copied().unwrap_or(0),
+            ObjectKind::Table => 0,
+        };
+        if kind != ObjectKind::Table {
+            hidden_parents.insert(if kind == ObjectKind::Database { database } else { schema });
+            if tables_only && contained == 0 {
+                continue;
+            }
+        }
+        hidden.push(HiddenObject { kind, name: object.qualified_name(), owner: object.owner.clone(), tables: contained });
+    }
+
+    let listed: HashSet<_> = account_usage.iter().map(AccountObject::key).collect();
+    let listed_databases: HashSet<&str> = account_usage.iter().map(|object| object.database.as_str()).collect();
+    let mut unlisted: Vec<String> = visible
+        .iter()
+        .filter(|object| object.kind() != ObjectKind::Database && listed_databases.contains(object.database.as_str()))
+        .filter(|object| !listed.contains(&object.key()))
+        .map(AccountObject::qualified_name)
+        .collect();
+    unlisted.sort();
+
+    VisibilityReport { hidden, unlisted, account_usage_objects: account_usage.len(), visible_objects: visible.len() }
+}
+
+/// The databases, schemas and tables of a catalog
+pub fn catalog_objects(tables: &[TableInfo]) -> Vec<AccountObject> {
+    let mut objects: Vec<AccountObject> = Vec::new();
+    let mut seen = HashSet::new();
+    for table in tables {
+        let database = AccountObject { database: table.database_name.clone(), schema: None, table: None, owner: None };
+        let schema = AccountObject { schema: Some(table.schema_name.clone()), ..database.clone() };
+        let table = AccountObject { table: Some(table.table_name.clone()), ..schema.clone() };
+        for object in [database, schema, table] {
+            if seen.insert(object.clone()) {
+                objects.push(object);
+            }
+        }
+    }
+    objects
+}
+
+pub fn render_text(report: &VisibilityReport) -> String {
+    let rows: Vec<[String; 4]> = report
+        .hidden
+        .iter()
+        .map(|object| {
+            let kind = match object.kind {
+                ObjectKind::Database => "database",
+                ObjectKind::Schema => "schema",
+                ObjectKind::Table => "table",
+            };
+            let tables = if object.kind == ObjectKind::Table { String::new() } else { object.tables.to_string() };
+            [kind.to_string(), object.name.clone(), object.owner.clone().unwrap_or_default(), tables]
+        })
+        .collect();
+    let mut text = output::render_table(["KIND", "HIDDEN OBJECT", "OWNER", "TABLES"], &rows);
+    if !report.unlisted.is_empty() {
+        text.push_str(&format!(
+            "\n{} visible objects aren't in ACCOUNT_USAGE yet: {}\n",
+            report.unlisted.len(),
+            report.unlisted.join(", ")
+        ));
+    }
+    text
+}